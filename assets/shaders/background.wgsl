@@ -0,0 +1,101 @@
+#import bevy_sprite::{
+    mesh2d_vertex_output::VertexOutput,
+    mesh2d_view_bindings::view,
+}
+
+@group(1) @binding(0) var<uniform> color: vec4<f32>;
+@group(1) @binding(1) var<uniform> line_color: vec4<f32>;
+@group(1) @binding(2) var<uniform> spacing: f32;
+@group(1) @binding(3) var<uniform> pattern: u32;
+
+// Lines closer than this on screen are faded out and doubled in spacing.
+const MIN_SPACING_PX: f32 = 12.;
+
+// World units covered by one screen pixel (`OrthographicProjection.scale`).
+fn world_per_pixel() -> f32 {
+    return 2. / (view.projection[0][0] * view.viewport.z);
+}
+
+fn hash2(p: vec2<f32>) -> f32 {
+    return fract(sin(dot(p, vec2<f32>(12.9898, 78.233))) * 43758.5453);
+}
+
+fn value_noise(p: vec2<f32>) -> f32 {
+    let i = floor(p);
+    let f = fract(p);
+    let u = f * f * (3. - 2. * f);
+    let a = hash2(i);
+    let b = hash2(i + vec2<f32>(1., 0.));
+    let c = hash2(i + vec2<f32>(0., 1.));
+    let d = hash2(i + vec2<f32>(1., 1.));
+    return mix(mix(a, b, u.x), mix(c, d, u.x), u.y);
+}
+
+fn slate(p: vec2<f32>) -> f32 {
+    var sum = 0.;
+    var amplitude = 0.5;
+    var q = p / 256.;
+    for (var i = 0; i < 5; i++) {
+        sum += amplitude * value_noise(q);
+        q = q * 2.03;
+        amplitude *= 0.5;
+    }
+    return sum;
+}
+
+// Coverage of 1px wide lines every `gap` world units along each axis.
+fn lines(p: vec2<f32>, gap: f32, wpp: f32) -> vec2<f32> {
+    let d = abs(p - gap * round(p / gap)) / wpp;
+    return 1. - smoothstep(vec2<f32>(0.5), vec2<f32>(1.5), d);
+}
+
+fn dots(p: vec2<f32>, gap: f32, wpp: f32) -> f32 {
+    let d = length(p - gap * round(p / gap)) / wpp;
+    return 1. - smoothstep(1., 2., d);
+}
+
+fn staves(y: f32, gap: f32, wpp: f32) -> f32 {
+    // five lines, then a gap of three line spacings
+    let index = floor(y / gap + 0.5);
+    let in_staff = step(((index % 8.) + 8.) % 8., 4.5);
+    return lines(vec2<f32>(y), gap, wpp).x * in_staff;
+}
+
+@fragment
+fn fragment(mesh: VertexOutput) -> @location(0) vec4<f32> {
+    let p = mesh.world_position.xy;
+    let wpp = world_per_pixel();
+
+    // Adaptive spacing: `fine` doubles every time it would get closer
+    // than MIN_SPACING_PX and fades out while approaching that limit.
+    let level = max(log2(MIN_SPACING_PX * wpp / spacing), 0.);
+    let k = floor(level);
+    let fine = spacing * exp2(k);
+    let coarse = fine * 2.;
+    let fade = 1. - (level - k);
+
+    var coverage = 0.;
+    var base = color;
+    switch pattern {
+        case 1u: {
+            let grain = slate(p);
+            base = vec4<f32>(color.rgb + vec3<f32>(0.06, 0.07, 0.06) * grain, color.a);
+        }
+        case 2u: {
+            let f = lines(p, fine, wpp);
+            let c = lines(p, coarse, wpp);
+            coverage = max(max(c.x, c.y), max(f.x, f.y) * fade);
+        }
+        case 3u: {
+            coverage = max(dots(p, coarse, wpp), dots(p, fine, wpp) * fade);
+        }
+        case 4u: {
+            coverage = max(lines(p, coarse, wpp).y, lines(p, fine, wpp).y * fade);
+        }
+        case 5u: {
+            coverage = staves(p.y, fine, wpp);
+        }
+        default: {}
+    }
+    return mix(base, vec4<f32>(line_color.rgb, 1.), coverage * line_color.a);
+}
@@ -1,4 +1,8 @@
-#import bevy_sprite::mesh2d_vertex_output::VertexOutput
+#import bevy_sprite::{
+    mesh2d_functions as mesh_functions,
+    mesh2d_vertex_output::VertexOutput,
+    mesh2d_view_bindings::view,
+}
 
 // MIT License. © Stefan Gustavson, Munrocket
 //
@@ -38,8 +42,52 @@ fn perlinNoise2(P: vec2<f32>) -> f32 {
 }
 
 @group(1) @binding(0) var<uniform> material_color: vec4<f32>;
+@group(1) @binding(1) var<uniform> grain_scale: f32;
+@group(1) @binding(2) var<uniform> density: f32;
+@group(1) @binding(3) var<uniform> contrast: f32;
+@group(1) @binding(4) var<uniform> seed: f32;
+@group(1) @binding(5) var<uniform> grain_space: u32;
+
+struct Vertex {
+    @builtin(instance_index) instance_index: u32,
+    @location(0) position: vec3<f32>,
+};
+
+// Same as the default mesh2d vertex shader, but keeps the stroke-local
+// position in `uv` so the grain can follow the stroke when it moves.
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+    let model = mesh_functions::get_model_matrix(vertex.instance_index);
+    out.world_position = mesh_functions::mesh2d_position_local_to_world(
+        model,
+        vec4<f32>(vertex.position, 1.0)
+    );
+    out.position = mesh_functions::mesh2d_position_world_to_clip(out.world_position);
+    out.uv = vertex.position.xy;
+    return out;
+}
+
+// World units covered by one screen pixel (`OrthographicProjection.scale`).
+fn world_per_pixel() -> f32 {
+    return 2. / (view.projection[0][0] * view.viewport.z);
+}
+
+fn grain(p: vec2<f32>, level: f32) -> f32 {
+    let offset = vec2<f32>(seed * 17.31, seed * 43.17);
+    return perlinNoise2(p * grain_scale / exp2(level) + offset);
+}
 
 @fragment
 fn fragment(mesh: VertexOutput) -> @location(0) vec4<f32> {
-    return material_color * perlinNoise2(mesh.world_position.xy);
+    var p = mesh.world_position.xy;
+    if grain_space == 1u {
+        p = mesh.uv;
+    }
+    // Sample the two power-of-two zoom levels around the current one and
+    // blend them, so the grain keeps its screen size without popping.
+    let level = log2(world_per_pixel());
+    let base = floor(level);
+    let noise = mix(grain(p, base), grain(p, base + 1.), level - base);
+    return material_color * mix(1., noise * contrast, density);
 }
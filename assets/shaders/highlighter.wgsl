@@ -0,0 +1,17 @@
+#import bevy_sprite::mesh2d_vertex_output::VertexOutput
+
+@group(1) @binding(0) var<uniform> material_color: vec4<f32>;
+@group(1) @binding(1) var<uniform> opacity: f32;
+
+@fragment
+fn fragment(mesh: VertexOutput) -> @location(0) vec4<f32> {
+#ifdef HIGHLIGHTER_MULTIPLY
+    // The pipeline multiplies the output with the color underneath,
+    // so white keeps the board as it is.
+    let tint = mix(vec3<f32>(1.), material_color.rgb, opacity * material_color.a);
+    return vec4<f32>(tint, 1.);
+#else
+    // On dark boards the color is blended over the board instead.
+    return vec4<f32>(material_color.rgb, opacity * material_color.a);
+#endif
+}
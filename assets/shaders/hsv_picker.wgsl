@@ -0,0 +1,21 @@
+// This shader draws the saturation/value square or the hue strip of the color picker
+#import bevy_ui::ui_vertex_output::UiVertexOutput
+
+@group(1) @binding(0) var<uniform> hue: f32;
+@group(1) @binding(1) var<uniform> mode: u32;
+
+fn hsv_to_rgb(c: vec3<f32>) -> vec3<f32> {
+    let k = vec4<f32>(1.0, 2.0 / 3.0, 1.0 / 3.0, 3.0);
+    let p = abs(fract(c.xxx + k.xyz) * 6.0 - k.www);
+    return c.z * mix(k.xxx, clamp(p - k.xxx, vec3<f32>(0.0), vec3<f32>(1.0)), c.y);
+}
+
+@fragment
+fn fragment(in: UiVertexOutput) -> @location(0) vec4<f32> {
+    var hsv = vec3<f32>(hue, in.uv.x, 1.0 - in.uv.y);
+    if mode == 1u {
+        hsv = vec3<f32>(in.uv.x, 1.0, 1.0);
+    }
+    // the picker works in sRGB, the render target expects linear colors
+    return vec4<f32>(pow(hsv_to_rgb(hsv), vec3<f32>(2.2)), 1.0);
+}
@@ -0,0 +1,11 @@
+#import bevy_sprite::mesh2d_vertex_output::VertexOutput
+
+@group(1) @binding(0) var<uniform> material_color: vec4<f32>;
+@group(1) @binding(1) var<uniform> intensity: f32;
+
+// Drawn with additive blending, a white core makes the stroke look lit.
+@fragment
+fn fragment(mesh: VertexOutput) -> @location(0) vec4<f32> {
+    let core = mix(material_color.rgb, vec3<f32>(1.), 0.25);
+    return vec4<f32>(core * intensity, material_color.a);
+}
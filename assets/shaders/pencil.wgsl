@@ -0,0 +1,16 @@
+#import bevy_sprite::mesh2d_vertex_output::VertexOutput
+
+@group(1) @binding(0) var<uniform> material_color: vec4<f32>;
+@group(1) @binding(1) var<uniform> grain_scale: f32;
+@group(1) @binding(2) var<uniform> density: f32;
+
+fn hash2(p: vec2<f32>) -> f32 {
+    return fract(sin(dot(p, vec2<f32>(12.9898, 78.233))) * 43758.5453);
+}
+
+@fragment
+fn fragment(mesh: VertexOutput) -> @location(0) vec4<f32> {
+    let cell = floor(mesh.world_position.xy * grain_scale);
+    let grain = step(hash2(cell), density);
+    return vec4<f32>(material_color.rgb, material_color.a * grain * 0.9);
+}
@@ -0,0 +1,12 @@
+use bevy::prelude::*;
+use lines::{plugins::LinesPlugins, states::ToolButton};
+
+fn main() {
+    let mut app = App::new();
+    app.add_plugins(DefaultPlugins);
+    let camera = app.world.spawn(Camera2dBundle::default()).id();
+    app.add_plugins(LinesPlugins::default().with_camera(camera).with_tools(
+        vec![ToolButton::Cursor, ToolButton::Pen, ToolButton::Eraser],
+    ))
+    .run();
+}
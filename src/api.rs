@@ -0,0 +1,1006 @@
+// 1. 本机控制接口：脚本或其他程序用 JSON-RPC 2.0 操作白板，一行一个 JSON 消息
+// 2. 监听 TCP 地址或 Unix socket（地址以 unix: 开头），默认关闭，由环境变量 LINES_API 或 ApiConfig 打开
+//    接口没有认证且可以写文件，TCP 只允许监听本机回环地址
+// 3. 每个连接一个读取线程，请求在 PreUpdate 中按到达顺序处理；修改白板时与本地工具一样发送 BoardOp
+//    回复与通知放入连接的发送队列，由连接的发送线程写出，队列满的连接会被断开
+// 4. 调用 subscribe 的连接会收到 board.changed 与 tool.changed 通知
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{self, Receiver, Sender, SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+
+use bevy::{ecs::system::SystemParam, prelude::*, window::RequestRedraw};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    background::BoardBackground,
+    board::{stroke_ops, validate_style, BrushKind, StrokeData},
+    board_text::{spawn_text, TextData},
+    board_view::{BoardCrdt, BoardModel, BoardOpEvent, ObjectId},
+    document::Document,
+    draw::LineSpawner,
+    excalidraw::{arrowhead, ExcalidrawScene, ELLIPSE_SEGMENTS},
+    inkml::write_inkml,
+    keybindings::{ActionRegistry, PendingActions},
+    projection_2d_control::MainCamera,
+    recording::{BoardOp, Recording},
+    replay::ReplayState,
+    selected::Selected,
+    states::ToolButton,
+    tools::ToolRegistry,
+};
+
+pub const DEFAULT_API_PORT: u16 = 7879;
+
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(20);
+
+/// 每个连接最多积压的回复与通知
+const OUTGOING_LIMIT: usize = 1024;
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+/// 请求合法但无法完成，例如回放中修改白板、写文件失败
+pub const SERVER_ERROR: i64 = -32000;
+
+pub struct ApiPlugin;
+
+impl Plugin for ApiPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ApiConfig>()
+            .init_resource::<Api>()
+            .add_systems(
+                PreUpdate,
+                (
+                    listen.run_if(resource_changed::<ApiConfig>()),
+                    handle_requests,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                PostUpdate,
+                (
+                    publish_board_ops,
+                    publish_tool.run_if(state_changed::<ToolButton>()),
+                    request_redraw.run_if(api_listening),
+                ),
+            );
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct ApiConfig {
+    /** 例如 127.0.0.1:7879 或 unix:/tmp/lines.sock，TCP 地址必须是回环地址；None 表示关闭 */
+    pub address: Option<String>,
+}
+
+/// 默认从环境变量 LINES_API 读取
+impl Default for ApiConfig {
+    fn default() -> Self {
+        ApiConfig {
+            address: std::env::var("LINES_API").ok(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Request {
+    /** 没有 id 的请求是通知，不回复 */
+    #[serde(default)]
+    pub id: Option<Value>,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Response {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+impl Response {
+    fn new(id: Value, result: Result<Value, RpcError>) -> Self {
+        let (result, error) = match result {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+        Response {
+            jsonrpc: "2.0",
+            id,
+            result,
+            error,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Notification<'a> {
+    jsonrpc: &'static str,
+    method: &'a str,
+    params: Value,
+}
+
+enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener, PathBuf),
+}
+
+impl Listener {
+    /// 返回实际监听的地址
+    fn bind(address: &str) -> io::Result<(Self, String)> {
+        #[cfg(unix)]
+        if let Some(path) = address.strip_prefix("unix:") {
+            use std::os::unix::fs::FileTypeExt;
+            // 上次没有清理的 socket 文件
+            if fs::metadata(path).is_ok_and(|meta| meta.file_type().is_socket())
+            {
+                fs::remove_file(path)?;
+            }
+            let listener = UnixListener::bind(path)?;
+            listener.set_nonblocking(true)?;
+            return Ok((Listener::Unix(listener, path.into()), address.into()));
+        }
+        let addresses: Vec<_> = address.to_socket_addrs()?.collect();
+        if addresses.iter().any(|address| !address.ip().is_loopback()) {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "only loopback addresses are allowed",
+            ));
+        }
+        let listener = TcpListener::bind(&addresses[..])?;
+        listener.set_nonblocking(true)?;
+        let address = listener.local_addr()?.to_string();
+        Ok((Listener::Tcp(listener), address))
+    }
+
+    fn accept(&self) -> io::Result<Stream> {
+        match self {
+            Listener::Tcp(listener) => {
+                let (stream, _) = listener.accept()?;
+                stream.set_nonblocking(false)?;
+                let _ = stream.set_nodelay(true);
+                Ok(Stream::Tcp(stream))
+            }
+            #[cfg(unix)]
+            Listener::Unix(listener, _) => {
+                let (stream, _) = listener.accept()?;
+                stream.set_nonblocking(false)?;
+                Ok(Stream::Unix(stream))
+            }
+        }
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Listener::Unix(_, path) = self {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+enum Stream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Stream {
+    fn try_clone(&self) -> io::Result<Self> {
+        match self {
+            Stream::Tcp(stream) => stream.try_clone().map(Stream::Tcp),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.try_clone().map(Stream::Unix),
+        }
+    }
+
+    fn shutdown(&self) {
+        let _ = match self {
+            Stream::Tcp(stream) => stream.shutdown(Shutdown::Both),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.shutdown(Shutdown::Both),
+        };
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+struct Client {
+    stream: Stream,
+    /** 发送线程的队列，一项是一行消息 */
+    outgoing: SyncSender<Vec<u8>>,
+    subscribed: bool,
+}
+
+impl Client {
+    /// 只放入发送队列；队列满时关闭连接并返回 false
+    fn send<T: Serialize>(&self, message: &T) -> bool {
+        let Ok(mut line) = serde_json::to_vec(message) else {
+            return true;
+        };
+        line.push(b'\n');
+        if let Err(TrySendError::Full(_)) = self.outgoing.try_send(line) {
+            self.stream.shutdown();
+            return false;
+        }
+        true
+    }
+}
+
+/// 连接线程与白板共用的连接表
+#[derive(Default)]
+struct Clients {
+    clients: Mutex<BTreeMap<u64, Client>>,
+    last_client: AtomicU64,
+}
+
+impl Clients {
+    fn add(&self, stream: Stream) -> io::Result<u64> {
+        let mut writer = stream.try_clone()?;
+        let (outgoing, lines) = mpsc::sync_channel::<Vec<u8>>(OUTGOING_LIMIT);
+        thread::spawn(move || {
+            for line in lines {
+                if writer.write_all(&line).is_err() {
+                    writer.shutdown();
+                    return;
+                }
+            }
+        });
+        let client = self.last_client.fetch_add(1, Ordering::Relaxed) + 1;
+        let client_info = Client {
+            stream,
+            outgoing,
+            subscribed: false,
+        };
+        self.clients.lock().unwrap().insert(client, client_info);
+        Ok(client)
+    }
+
+    fn remove(&self, client: u64) {
+        self.clients.lock().unwrap().remove(&client);
+    }
+
+    fn subscribe(&self, client: u64) {
+        if let Some(client) = self.clients.lock().unwrap().get_mut(&client) {
+            client.subscribed = true;
+        }
+    }
+
+    /// 不读取回复的连接在队列满时被移除
+    fn send<T: Serialize>(&self, client: u64, message: &T) {
+        let mut clients = self.clients.lock().unwrap();
+        if clients.get(&client).is_some_and(|info| !info.send(message)) {
+            warn!("API client {} is not reading, disconnecting it", client);
+            clients.remove(&client);
+        }
+    }
+
+    fn publish(&self, method: &str, params: Value) {
+        let notification = Notification {
+            jsonrpc: "2.0",
+            method,
+            params,
+        };
+        self.clients.lock().unwrap().retain(|client, info| {
+            let kept = !info.subscribed || info.send(&notification);
+            if !kept {
+                warn!("API client {} is not reading, disconnecting it", client);
+            }
+            kept
+        });
+    }
+
+    fn close(&self) {
+        for client in self.clients.lock().unwrap().values() {
+            client.stream.shutdown();
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Api {
+    /** 实际监听的地址，端口为 0 时可以从这里得到分配的端口 */
+    pub address: Option<String>,
+    requests: Option<Mutex<Receiver<(u64, Request)>>>,
+    clients: Arc<Clients>,
+    closed: Arc<AtomicBool>,
+}
+
+impl Api {
+    fn open(address: &str) -> io::Result<Self> {
+        let (listener, address) = Listener::bind(address)?;
+        let (sender, receiver) = mpsc::channel();
+        let api = Api {
+            address: Some(address),
+            requests: Some(Mutex::new(receiver)),
+            ..default()
+        };
+        let clients = api.clients.clone();
+        let closed = api.closed.clone();
+        thread::spawn(move || {
+            accept_connections(listener, clients, sender, closed)
+        });
+        Ok(api)
+    }
+
+    fn close(&mut self) {
+        self.closed.store(true, Ordering::Relaxed);
+        self.clients.close();
+        *self = Api::default();
+    }
+}
+
+fn accept_connections(
+    listener: Listener,
+    clients: Arc<Clients>,
+    requests: Sender<(u64, Request)>,
+    closed: Arc<AtomicBool>,
+) {
+    while !closed.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok(stream) => {
+                let clients = clients.clone();
+                let requests = requests.clone();
+                thread::spawn(move || {
+                    handle_connection(stream, clients, requests)
+                });
+            }
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                thread::sleep(ACCEPT_INTERVAL)
+            }
+            Err(err) => {
+                warn!("failed to accept an API connection: {}", err);
+                thread::sleep(ACCEPT_INTERVAL);
+            }
+        }
+    }
+}
+
+/// 格式错误的消息直接在连接线程中回复
+fn handle_connection(
+    stream: Stream,
+    clients: Arc<Clients>,
+    requests: Sender<(u64, Request)>,
+) {
+    let Ok(client) = stream.try_clone().and_then(|writer| clients.add(writer))
+    else {
+        return;
+    };
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let value: Value = match serde_json::from_str(&line) {
+            Ok(value) => value,
+            Err(err) => {
+                let error = RpcError::new(PARSE_ERROR, err.to_string());
+                clients.send(client, &Response::new(Value::Null, Err(error)));
+                continue;
+            }
+        };
+        let id = value.get("id").cloned().unwrap_or(Value::Null);
+        match serde_json::from_value(value) {
+            Ok(request) => {
+                if requests.send((client, request)).is_err() {
+                    break;
+                }
+            }
+            Err(err) => {
+                let error = RpcError::new(INVALID_REQUEST, err.to_string());
+                clients.send(client, &Response::new(id, Err(error)));
+            }
+        }
+    }
+    clients.remove(client);
+}
+
+fn listen(config: Res<ApiConfig>, mut api: ResMut<Api>) {
+    api.close();
+    let Some(address) = &config.address else {
+        return;
+    };
+    match Api::open(address) {
+        Ok(opened) => {
+            info!("control API listening on {:?}", opened.address);
+            *api = opened;
+        }
+        Err(err) => error!("failed to listen on {}: {}", address, err),
+    }
+}
+
+fn handle_requests(api: Res<Api>, mut board: ApiBoard) {
+    let Some(requests) = &api.requests else {
+        return;
+    };
+    let requests: Vec<(u64, Request)> =
+        requests.lock().unwrap().try_iter().collect();
+    for (client, request) in requests {
+        let result = if request.method == "subscribe" {
+            api.clients.subscribe(client);
+            Ok(Value::Bool(true))
+        } else {
+            board.call(&request.method, request.params)
+        };
+        if let Some(id) = request.id {
+            api.clients.send(client, &Response::new(id, result));
+        }
+    }
+}
+
+fn publish_board_ops(api: Res<Api>, mut board_ops: EventReader<BoardOpEvent>) {
+    for BoardOpEvent(op) in board_ops.read() {
+        if api.address.is_some() {
+            api.clients.publish("board.changed", json!({ "op": op }));
+        }
+    }
+}
+
+fn api_listening(api: Res<Api>) -> bool {
+    api.address.is_some()
+}
+
+/// 请求随时会到达，监听时不等待输入事件
+fn request_redraw(mut redraw: EventWriter<RequestRedraw>) {
+    redraw.send(RequestRedraw);
+}
+
+fn publish_tool(api: Res<Api>, tool: Res<State<ToolButton>>) {
+    if api.address.is_some() {
+        let tool = tool_name(tool.get());
+        api.clients.publish("tool.changed", json!({ "tool": tool }));
+    }
+}
+
+/// 工具在接口中的名字
+pub fn tool_name(tool: &ToolButton) -> String {
+    match tool {
+        ToolButton::Pen => "pen",
+        ToolButton::Cursor => "cursor",
+        ToolButton::Eraser => "eraser",
+        ToolButton::Eyedropper => "eyedropper",
+        ToolButton::Laser => "laser",
+        ToolButton::MoveCamera => "move_camera",
+        ToolButton::TextInput => "text_input",
+        ToolButton::Custom(name) => name,
+    }
+    .to_string()
+}
+
+pub(crate) fn color_hex(color: Color) -> String {
+    let [r, g, b, a] = color.as_rgba_u8();
+    if a == u8::MAX {
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    } else {
+        format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+    }
+}
+
+fn parse<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    // 没有参数时按空对象处理
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params)
+        .map_err(|err| RpcError::new(INVALID_PARAMS, err.to_string()))
+}
+
+fn invalid_params(message: impl Into<String>) -> RpcError {
+    RpcError::new(INVALID_PARAMS, message)
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct StyleParams {
+    /** 形如 #rrggbb 或 #rrggbbaa */
+    color: Option<String>,
+    width: Option<f32>,
+    brush: Option<BrushKind>,
+    layer: Option<i8>,
+}
+
+#[derive(Deserialize)]
+struct StrokeParams {
+    points: Vec<Vec2>,
+    #[serde(flatten)]
+    style: StyleParams,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum ShapeKind {
+    Line,
+    Arrow,
+    Rectangle,
+    Ellipse,
+}
+
+#[derive(Deserialize)]
+struct ShapeParams {
+    shape: ShapeKind,
+    from: Vec2,
+    to: Vec2,
+    #[serde(flatten)]
+    style: StyleParams,
+}
+
+#[derive(Deserialize)]
+struct TextParams {
+    text: String,
+    /** 左上角的世界坐标 */
+    position: Vec2,
+    #[serde(default)]
+    size: Option<f32>,
+    #[serde(default)]
+    color: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ActionParams {
+    action: String,
+}
+
+#[derive(Deserialize)]
+struct ToolParams {
+    tool: String,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct SelectionParams {
+    strokes: Vec<u64>,
+    /** board.objects 返回的文字 id */
+    texts: Vec<u64>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct CameraParams {
+    center: Option<Vec2>,
+    scale: Option<f32>,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum ExportFormat {
+    Lines,
+    Excalidraw,
+    Inkml,
+}
+
+#[derive(Deserialize)]
+struct ExportParams {
+    format: ExportFormat,
+    path: PathBuf,
+}
+
+const DEFAULT_WIDTH: f32 = 4.;
+const DEFAULT_TEXT_SIZE: f32 = 32.;
+
+/// 线条折线，箭头由箭身与箭头两条组成
+fn shape_polylines(shape: ShapeKind, from: Vec2, to: Vec2) -> Vec<Vec<Vec2>> {
+    match shape {
+        ShapeKind::Line => vec![vec![from, to]],
+        ShapeKind::Arrow => vec![vec![from, to], arrowhead(from, to)],
+        ShapeKind::Rectangle => vec![vec![
+            from,
+            Vec2::new(to.x, from.y),
+            to,
+            Vec2::new(from.x, to.y),
+            from,
+        ]],
+        ShapeKind::Ellipse => {
+            let center = (from + to) / 2.;
+            let radius = (to - from).abs() / 2.;
+            vec![(0..=ELLIPSE_SEGMENTS)
+                .map(|i| {
+                    let theta = i as f32 / ELLIPSE_SEGMENTS as f32
+                        * std::f32::consts::TAU;
+                    center + Vec2::new(theta.cos(), theta.sin()) * radius
+                })
+                .collect()]
+        }
+    }
+}
+
+/// 处理请求用到的白板状态
+#[derive(SystemParam)]
+struct ApiBoard<'w, 's> {
+    spawner: LineSpawner<'w, 's>,
+    board_ops: EventWriter<'w, BoardOpEvent>,
+    model: Res<'w, BoardModel>,
+    crdt: ResMut<'w, BoardCrdt>,
+    background: Res<'w, BoardBackground>,
+    recording: Res<'w, Recording>,
+    objects: Query<'w, 's, (Entity, &'static ObjectId)>,
+    selected: ResMut<'w, Selected>,
+    pending: ResMut<'w, PendingActions>,
+    actions: Res<'w, ActionRegistry>,
+    tools: Res<'w, ToolRegistry>,
+    tool: Res<'w, State<ToolButton>>,
+    replay: Res<'w, State<ReplayState>>,
+    camera: Query<
+        'w,
+        's,
+        (&'static mut Transform, &'static mut OrthographicProjection),
+        With<MainCamera>,
+    >,
+}
+
+impl ApiBoard<'_, '_> {
+    fn call(&mut self, method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            "actions.list" => Ok(self.list_actions()),
+            "actions.run" => self.run_action(parse(params)?),
+            "tools.list" => Ok(self.list_tools()),
+            "tools.get" => Ok(json!(tool_name(self.tool.get()))),
+            "tools.set" => self.set_tool(parse(params)?),
+            "board.add_stroke" => self.add_stroke(parse(params)?),
+            "board.add_shape" => self.add_shape(parse(params)?),
+            "board.add_text" => self.add_text(parse(params)?),
+            "board.objects" => Ok(self.objects()),
+            "board.export" => self.export(parse(params)?),
+            "selection.get" => Ok(self.selection()),
+            "selection.set" => self.select(parse(params)?),
+            "camera.get" => self.camera(),
+            "camera.set" => self.move_camera(parse(params)?),
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("unknown method {:?}", method),
+            )),
+        }
+    }
+
+    fn list_actions(&self) -> Value {
+        self.actions
+            .0
+            .iter()
+            .filter(|info| !info.hold)
+            .map(|info| json!({ "id": info.id, "title": info.title }))
+            .collect()
+    }
+
+    /// 与按下快捷键相同，下一帧生效
+    fn run_action(&mut self, params: ActionParams) -> Result<Value, RpcError> {
+        match self.actions.get(&params.action) {
+            Some(info) if !info.hold => {
+                self.pending.trigger(&params.action);
+                Ok(Value::Null)
+            }
+            Some(_) => Err(invalid_params(format!(
+                "{:?} must be held and cannot be run",
+                params.action
+            ))),
+            None => Err(invalid_params(format!(
+                "unknown action {:?}",
+                params.action
+            ))),
+        }
+    }
+
+    fn list_tools(&self) -> Value {
+        self.tools
+            .0
+            .iter()
+            .map(|info| {
+                json!({ "tool": tool_name(&info.tool), "title": info.title })
+            })
+            .collect()
+    }
+
+    fn set_tool(&mut self, params: ToolParams) -> Result<Value, RpcError> {
+        let Some(info) = self
+            .tools
+            .0
+            .iter()
+            .find(|info| tool_name(&info.tool) == params.tool)
+        else {
+            return Err(invalid_params(format!(
+                "unknown tool {:?}",
+                params.tool
+            )));
+        };
+        self.pending.trigger(&info.action);
+        Ok(Value::Null)
+    }
+
+    fn editable(&self) -> Result<(), RpcError> {
+        if *self.replay.get() != ReplayState::Off {
+            return Err(RpcError::new(SERVER_ERROR, "the board is replaying"));
+        }
+        Ok(())
+    }
+
+    fn spawn_stroke(
+        &mut self,
+        points: Vec<Vec2>,
+        style: &StyleParams,
+    ) -> Result<u64, RpcError> {
+        let color = match &style.color {
+            Some(color) => Color::hex(color).map_err(|err| {
+                invalid_params(format!("invalid color {:?}: {}", color, err))
+            })?,
+            None => Color::WHITE,
+        };
+        let width = style.width.unwrap_or(DEFAULT_WIDTH);
+        let (id, next_layer) = self.spawner.next();
+        let layer = style.layer.unwrap_or(next_layer);
+        validate_style(width, layer)
+            .map_err(|err| invalid_params(err.to_string()))?;
+        if points.is_empty() || !points.iter().all(|point| point.is_finite()) {
+            return Err(invalid_params("points must be finite and not empty"));
+        }
+        let stroke = StrokeData {
+            id,
+            points,
+            color: color.into(),
+            width,
+            layer,
+            brush: style.brush.unwrap_or_default(),
+            ..default()
+        };
+        self.spawner.spawn(&stroke);
+        self.board_ops
+            .send_batch(stroke_ops(&stroke).into_iter().map(BoardOpEvent));
+        Ok(id)
+    }
+
+    fn add_stroke(&mut self, params: StrokeParams) -> Result<Value, RpcError> {
+        self.editable()?;
+        let id = self.spawn_stroke(params.points, &params.style)?;
+        Ok(json!({ "id": id }))
+    }
+
+    fn add_shape(&mut self, params: ShapeParams) -> Result<Value, RpcError> {
+        self.editable()?;
+        let polylines = shape_polylines(params.shape, params.from, params.to);
+        let ids = polylines
+            .into_iter()
+            .map(|points| self.spawn_stroke(points, &params.style))
+            .collect::<Result<Vec<u64>, RpcError>>()?;
+        Ok(json!({ "ids": ids }))
+    }
+
+    fn add_text(&mut self, params: TextParams) -> Result<Value, RpcError> {
+        self.editable()?;
+        let color = match &params.color {
+            Some(color) => Color::hex(color).map_err(|err| {
+                invalid_params(format!("invalid color {:?}: {}", color, err))
+            })?,
+            None => Color::WHITE,
+        };
+        let size = params.size.unwrap_or(DEFAULT_TEXT_SIZE);
+        if !(size.is_finite() && size > 0.) || !params.position.is_finite() {
+            return Err(invalid_params("invalid text size or position"));
+        }
+        let (id, layer) = self.spawner.next();
+        let text = TextData {
+            id,
+            text: params.text,
+            position: params.position,
+            size,
+            color: color.into(),
+            layer,
+        };
+        spawn_text(&mut self.spawner.commands, &text);
+        self.board_ops.send(BoardOpEvent(BoardOp::AddText { text }));
+        Ok(json!({ "id": id }))
+    }
+
+    /// 当前页的对象，坐标为世界坐标
+    fn objects(&self) -> Value {
+        let page = self.model.0.page();
+        let strokes: Vec<Value> = page
+            .ordered()
+            .into_iter()
+            .map(|stroke| {
+                json!({
+                    "id": stroke.id,
+                    "points": stroke.world_points().collect::<Vec<_>>(),
+                    "color": color_hex(stroke.color.into()),
+                    "width": stroke.width,
+                    "layer": stroke.layer,
+                    "brush": stroke.brush,
+                })
+            })
+            .collect();
+        let texts: Vec<Value> = page
+            .texts()
+            .map(|text| {
+                json!({
+                    "id": text.id,
+                    "text": text.text,
+                    "position": text.position,
+                    "size": text.size,
+                    "color": color_hex(text.color.into()),
+                    "layer": text.layer,
+                })
+            })
+            .collect();
+        let images: Vec<Value> = page
+            .images()
+            .map(|image| {
+                json!({
+                    "id": image.id,
+                    "position": image.position,
+                    "scale": image.scale,
+                    "layer": image.layer,
+                    "locked": image.locked,
+                })
+            })
+            .collect();
+        json!({ "strokes": strokes, "texts": texts, "images": images })
+    }
+
+    fn selection(&self) -> Value {
+        let page = self.model.0.page();
+        let ids: Vec<u64> = self
+            .selected
+            .0
+            .iter()
+            .filter_map(|entity| self.objects.get(*entity).ok())
+            .map(|(_, &ObjectId(id))| id)
+            .collect();
+        let strokes: Vec<u64> = ids
+            .iter()
+            .copied()
+            .filter(|id| page.get(*id).is_some())
+            .collect();
+        let texts: Vec<u64> = ids
+            .iter()
+            .copied()
+            .filter(|id| page.text(*id).is_some())
+            .collect();
+        json!({ "strokes": strokes, "texts": texts })
+    }
+
+    fn select(&mut self, params: SelectionParams) -> Result<Value, RpcError> {
+        let page = self.model.0.page();
+        if let Some(id) =
+            params.strokes.iter().find(|id| page.get(**id).is_none())
+        {
+            return Err(invalid_params(format!("unknown stroke {}", id)));
+        }
+        if let Some(id) =
+            params.texts.iter().find(|id| page.text(**id).is_none())
+        {
+            return Err(invalid_params(format!("unknown text {}", id)));
+        }
+        let ids: Vec<u64> =
+            params.strokes.into_iter().chain(params.texts).collect();
+        self.selected.0 = ids
+            .iter()
+            .filter_map(|id| {
+                self.objects
+                    .iter()
+                    .find(|(_, &ObjectId(object))| object == *id)
+                    .map(|(entity, _)| entity)
+            })
+            .collect();
+        Ok(Value::Null)
+    }
+
+    fn camera(&self) -> Result<Value, RpcError> {
+        let (transform, projection) = self
+            .camera
+            .get_single()
+            .map_err(|_| RpcError::new(SERVER_ERROR, "no camera"))?;
+        Ok(json!({
+            "center": transform.translation.truncate(),
+            "scale": projection.scale,
+        }))
+    }
+
+    fn move_camera(&mut self, params: CameraParams) -> Result<Value, RpcError> {
+        if params.center.is_some_and(|center| !center.is_finite())
+            || params
+                .scale
+                .is_some_and(|scale| !(scale.is_finite() && scale > 0.))
+        {
+            return Err(invalid_params("invalid camera center or scale"));
+        }
+        let (mut transform, mut projection) = self
+            .camera
+            .get_single_mut()
+            .map_err(|_| RpcError::new(SERVER_ERROR, "no camera"))?;
+        if let Some(center) = params.center {
+            transform.translation = center.extend(transform.translation.z);
+        }
+        if let Some(scale) = params.scale {
+            projection.scale = scale;
+        }
+        Ok(Value::Null)
+    }
+
+    fn export(&mut self, params: ExportParams) -> Result<Value, RpcError> {
+        let page = self.model.0.page();
+        let strokes = page.to_strokes();
+        let result = match params.format {
+            ExportFormat::Lines => Document::from_board(
+                &self.model.0,
+                &mut self.crdt,
+                &self.recording,
+                &self.background,
+            )
+            .save(&params.path),
+            ExportFormat::Excalidraw => {
+                let texts: Vec<TextData> = page.texts().cloned().collect();
+                ExcalidrawScene::from_board(&strokes, &texts).save(&params.path)
+            }
+            ExportFormat::Inkml => {
+                fs::write(&params.path, write_inkml(&strokes))
+            }
+        };
+        result.map_err(|err| {
+            RpcError::new(
+                SERVER_ERROR,
+                format!("failed to export {:?}: {}", params.path, err),
+            )
+        })?;
+        info!("exported board to {:?}", params.path);
+        Ok(json!({ "path": params.path }))
+    }
+}
@@ -0,0 +1,330 @@
+// 1. 定时把白板写入恢复目录中的快照，只保留最近几份；窗口失去焦点时也会保存
+// 2. 每个实例持有自己的 session-<pid>-<n>.lock 并在运行期间加锁，正常退出时删除
+// 3. 启动时能加锁的其他锁文件说明它的实例已经异常退出，提示恢复最近的快照；正在运行的实例不受影响
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use bevy::{app::AppExit, prelude::*, window::WindowFocused};
+
+use crate::{
+    background::BoardBackground,
+    board_view::{BoardCrdt, BoardModel, BoardOpEvent},
+    document::Document,
+    keybindings::{
+        action_just_pressed, actions, KeyBindings, KeyChord, RegisterAction,
+    },
+    recording::Recording,
+    replay::ReplayState,
+    ui::TOOL_BUTTON_BACKGROUND,
+};
+
+pub struct AutosavePlugin;
+
+impl Plugin for AutosavePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_action_with_keys(
+            actions::RECOVERY_RESTORE,
+            "Recovery: Restore autosave",
+            vec![KeyChord::key(KeyCode::Y)],
+        )
+        .register_action_with_keys(
+            actions::RECOVERY_DISMISS,
+            "Recovery: Dismiss",
+            vec![KeyChord::key(KeyCode::N)],
+        )
+        .init_resource::<AutosaveConfig>()
+        .init_resource::<AutosaveState>()
+        .add_systems(Startup, check_unclean_shutdown)
+        .add_systems(Update, autosave.run_if(in_state(ReplayState::Off)))
+        .add_systems(
+            Update,
+            (
+                restore_autosave
+                    .run_if(action_just_pressed(actions::RECOVERY_RESTORE)),
+                dismiss_recovery
+                    .run_if(action_just_pressed(actions::RECOVERY_DISMISS)),
+            )
+                .run_if(resource_exists::<PendingRecovery>()),
+        )
+        .add_systems(Last, mark_clean_shutdown.run_if(on_event::<AppExit>()));
+    }
+}
+
+#[derive(Resource, Clone)]
+pub struct AutosaveConfig {
+    /** 自动保存间隔 */
+    pub interval: Duration,
+    /** 恢复文件所在目录 */
+    pub dir: PathBuf,
+    /** 保留的快照数量 */
+    pub keep: usize,
+}
+
+impl Default for AutosaveConfig {
+    fn default() -> Self {
+        AutosaveConfig {
+            interval: Duration::from_secs(60),
+            dir: "recovery".into(),
+            keep: 5,
+        }
+    }
+}
+
+impl AutosaveConfig {
+    /// 同一进程中的多个白板（例如测试）也使用不同的锁文件
+    fn new_lock_path(&self) -> PathBuf {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        self.dir.join(format!(
+            "session-{}-{}.lock",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ))
+    }
+
+    /// 所有实例的锁文件，包括旧版本使用的 session.lock
+    fn lock_paths(&self) -> Vec<PathBuf> {
+        fs::read_dir(&self.dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                let name = path.file_name()?.to_str()?;
+                (name.starts_with("session") && name.ends_with(".lock"))
+                    .then_some(path)
+            })
+            .collect()
+    }
+
+    fn snapshot_path(&self, sequence: u64) -> PathBuf {
+        self.dir
+            .join(format!("autosave-{:06}.lines.json", sequence))
+    }
+
+    /// 按序号升序列出已有的快照
+    fn snapshots(&self) -> Vec<(u64, PathBuf)> {
+        let mut snapshots: Vec<(u64, PathBuf)> = fs::read_dir(&self.dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                let sequence = path
+                    .file_name()?
+                    .to_str()?
+                    .strip_prefix("autosave-")?
+                    .strip_suffix(".lines.json")?
+                    .parse()
+                    .ok()?;
+                Some((sequence, path))
+            })
+            .collect();
+        snapshots.sort_by_key(|(sequence, _)| *sequence);
+        snapshots
+    }
+}
+
+#[derive(Resource, Default)]
+struct AutosaveState {
+    dirty: bool,
+    last_save: f32,
+    next_sequence: u64,
+}
+
+/// 本实例的锁文件，运行期间保持加锁；进程异常退出后锁由系统释放，文件留下
+#[derive(Resource)]
+struct SessionLock {
+    path: PathBuf,
+    file: Option<File>,
+}
+
+/// 上次异常退出时留下的快照，等待用户确认是否恢复
+#[derive(Resource)]
+struct PendingRecovery(PathBuf);
+
+#[derive(Component)]
+struct RecoveryPrompt;
+
+fn check_unclean_shutdown(
+    mut commands: Commands,
+    config: Res<AutosaveConfig>,
+    mut state: ResMut<AutosaveState>,
+    bindings: Res<KeyBindings>,
+) {
+    let snapshots = config.snapshots();
+    state.next_sequence = snapshots.last().map_or(0, |(seq, _)| seq + 1);
+
+    // 先锁住自己的文件，同时启动的实例不会把它当作异常退出留下的
+    let lock_path = config.new_lock_path();
+    let lock = fs::create_dir_all(&config.dir).and_then(|_| {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&lock_path)?;
+        file.try_lock()?;
+        write!(file, "{}", std::process::id())?;
+        Ok(file)
+    });
+    match lock {
+        Ok(file) => commands.insert_resource(SessionLock {
+            path: lock_path.clone(),
+            file: Some(file),
+        }),
+        Err(err) => warn!("failed to create {:?}: {}", lock_path, err),
+    }
+
+    let mut unclean = false;
+    for path in config.lock_paths() {
+        if path == lock_path {
+            continue;
+        }
+        let Ok(file) = OpenOptions::new().write(true).open(&path) else {
+            continue;
+        };
+        // 加锁失败说明实例仍在运行
+        if file.try_lock().is_ok() {
+            drop(file);
+            unclean = true;
+            let _ = fs::remove_file(&path);
+        }
+    }
+    if unclean {
+        if let Some((_, path)) = snapshots.last() {
+            commands.insert_resource(PendingRecovery(path.clone()));
+            spawn_recovery_prompt(&mut commands, &bindings);
+        }
+    }
+}
+
+fn mark_clean_shutdown(lock: Option<ResMut<SessionLock>>) {
+    if let Some(mut lock) = lock {
+        // 先解锁关闭，有的系统不能删除打开中的文件
+        lock.file = None;
+        let _ = fs::remove_file(&lock.path);
+    }
+}
+
+fn autosave(
+    time: Res<Time<Real>>,
+    config: Res<AutosaveConfig>,
+    mut state: ResMut<AutosaveState>,
+    mut board_ops: EventReader<BoardOpEvent>,
+    mut focus_events: EventReader<WindowFocused>,
+    model: Res<BoardModel>,
+    mut crdt: ResMut<BoardCrdt>,
+    background: Res<BoardBackground>,
+    recording: Res<Recording>,
+) {
+    let background_changed = background.is_changed() && !background.is_added();
+    if board_ops.read().count() > 0 || background_changed {
+        state.dirty = true;
+    }
+    let focus_lost = focus_events.read().any(|event| !event.focused);
+    let now = time.elapsed_seconds();
+    let interval_elapsed =
+        now - state.last_save >= config.interval.as_secs_f32();
+    if !state.dirty || !(interval_elapsed || focus_lost) {
+        return;
+    }
+
+    let path = config.snapshot_path(state.next_sequence);
+    let document =
+        Document::from_board(&model.0, &mut crdt, &recording, &background);
+    if let Err(err) = document.save(&path) {
+        warn!("autosave to {:?} failed: {}", path, err);
+        return;
+    }
+    state.dirty = false;
+    state.last_save = now;
+    state.next_sequence += 1;
+
+    let snapshots = config.snapshots();
+    let stale = snapshots.len().saturating_sub(config.keep);
+    for (_, path) in snapshots.into_iter().take(stale) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+fn restore_autosave(
+    mut commands: Commands,
+    pending: Res<PendingRecovery>,
+    prompt: Query<Entity, With<RecoveryPrompt>>,
+    mut recording: ResMut<Recording>,
+) {
+    match Document::load(&pending.0) {
+        Ok(document) => {
+            document.load_into_board(&mut commands, &mut recording);
+            info!("restored autosave {:?}", pending.0);
+        }
+        Err(err) => error!("failed to restore {:?}: {}", pending.0, err),
+    }
+    close_recovery_prompt(&mut commands, &prompt);
+}
+
+fn dismiss_recovery(
+    mut commands: Commands,
+    prompt: Query<Entity, With<RecoveryPrompt>>,
+) {
+    close_recovery_prompt(&mut commands, &prompt);
+}
+
+fn close_recovery_prompt(
+    commands: &mut Commands,
+    prompt: &Query<Entity, With<RecoveryPrompt>>,
+) {
+    commands.remove_resource::<PendingRecovery>();
+    for entity in prompt.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// 提示中显示当前绑定的按键
+fn spawn_recovery_prompt(commands: &mut Commands, bindings: &KeyBindings) {
+    let key = |action| {
+        bindings
+            .chords(action)
+            .first()
+            .map_or("unbound".to_string(), KeyChord::to_string)
+    };
+    let text = format!(
+        "Lines was not closed properly. Restore the last autosave? ({} / {})",
+        key(actions::RECOVERY_RESTORE),
+        key(actions::RECOVERY_DISMISS),
+    );
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    top: Val::Px(76.),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                z_index: ZIndex::Global(1001),
+                ..default()
+            },
+            RecoveryPrompt,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        padding: UiRect::all(Val::Px(12.)),
+                        ..default()
+                    },
+                    background_color: TOOL_BUTTON_BACKGROUND.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        text,
+                        TextStyle::default(),
+                    ));
+                });
+        });
+}
@@ -0,0 +1,217 @@
+// 1. 白板背景：纯色、黑板、方格、点阵、横线、五线谱
+// 2. 背景由跟随相机的全屏网格绘制，图案按缩放自动调整间距
+// 3. Ctrl+B 切换图案，背景随文档一起保存
+use bevy::{
+    core_pipeline::clear_color::ClearColorConfig,
+    prelude::*,
+    render::{
+        camera::CameraUpdateSystem,
+        render_resource::{AsBindGroup, ShaderRef},
+    },
+    sprite::{Material2d, Material2dPlugin, MaterialMesh2dBundle},
+    transform::TransformSystem,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    keybindings::{action_just_pressed, actions},
+    projection_2d_control::MainCamera,
+};
+
+pub struct BackgroundPlugin;
+
+impl Plugin for BackgroundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(Material2dPlugin::<BackgroundMaterial>::default())
+            .init_resource::<BoardBackground>()
+            .add_systems(Startup, spawn_background)
+            .add_systems(
+                Update,
+                (
+                    cycle_background_pattern
+                        .run_if(action_just_pressed(actions::NEXT_BACKGROUND)),
+                    update_background_material.run_if(
+                        resource_changed::<BoardBackground>()
+                            .or_else(main_camera_added),
+                    ),
+                )
+                    .chain(),
+            )
+            .add_systems(
+                PostUpdate,
+                follow_camera
+                    .after(CameraUpdateSystem)
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum BackgroundPattern {
+    #[default]
+    Plain,
+    /** 带粉笔擦痕的黑板 */
+    Slate,
+    Grid,
+    Dots,
+    /** 横线纸 */
+    Ruled,
+    /** 五线谱 */
+    Music,
+}
+
+impl BackgroundPattern {
+    pub const ALL: [BackgroundPattern; 6] = [
+        BackgroundPattern::Plain,
+        BackgroundPattern::Slate,
+        BackgroundPattern::Grid,
+        BackgroundPattern::Dots,
+        BackgroundPattern::Ruled,
+        BackgroundPattern::Music,
+    ];
+
+    pub fn next(&self) -> BackgroundPattern {
+        let index = Self::ALL.iter().position(|p| p == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    fn shader_index(&self) -> u32 {
+        match self {
+            BackgroundPattern::Plain => 0,
+            BackgroundPattern::Slate => 1,
+            BackgroundPattern::Grid => 2,
+            BackgroundPattern::Dots => 3,
+            BackgroundPattern::Ruled => 4,
+            BackgroundPattern::Music => 5,
+        }
+    }
+}
+
+/// 当前白板的背景，保存在文档中
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct BoardBackground {
+    pub pattern: BackgroundPattern,
+    pub color: Color,
+    pub line_color: Color,
+    /** 图案的间距（世界坐标），缩小画布时按 2 的倍数放大 */
+    pub spacing: f32,
+}
+
+impl Default for BoardBackground {
+    fn default() -> Self {
+        BoardBackground {
+            pattern: BackgroundPattern::Plain,
+            color: Color::rgb(0.0, 0.0, 0.0),
+            line_color: Color::rgba(1., 1., 1., 0.15),
+            spacing: 32.,
+        }
+    }
+}
+
+impl BoardBackground {
+    /// 按相对亮度判断，深色背景上不能用相乘混合
+    pub fn is_dark(&self) -> bool {
+        let [r, g, b, _] = self.color.as_rgba_f32();
+        0.2126 * r + 0.7152 * g + 0.0722 * b < 0.5
+    }
+}
+
+#[derive(Asset, AsBindGroup, Default, Reflect, Debug, Clone)]
+#[reflect(Default, Debug)]
+pub struct BackgroundMaterial {
+    #[uniform(0)]
+    pub color: Color,
+    #[uniform(1)]
+    pub line_color: Color,
+    #[uniform(2)]
+    pub spacing: f32,
+    #[uniform(3)]
+    pub pattern: u32,
+}
+
+impl From<&BoardBackground> for BackgroundMaterial {
+    fn from(value: &BoardBackground) -> Self {
+        BackgroundMaterial {
+            color: value.color,
+            line_color: value.line_color,
+            spacing: value.spacing,
+            pattern: value.pattern.shader_index(),
+        }
+    }
+}
+
+impl Material2d for BackgroundMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/background.wgsl".into()
+    }
+}
+
+#[derive(Component)]
+struct BackgroundQuad;
+
+/// 在所有图层之下，但仍在相机的近平面之内
+const BACKGROUND_Z: f32 = -0.05;
+
+fn spawn_background(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<BackgroundMaterial>>,
+    background: Res<BoardBackground>,
+) {
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: meshes.add(shape::Quad::new(Vec2::ONE).into()).into(),
+            material: materials.add(background.as_ref().into()),
+            transform: Transform::from_xyz(0., 0., BACKGROUND_Z),
+            ..default()
+        },
+        BackgroundQuad,
+    ));
+}
+
+fn cycle_background_pattern(mut background: ResMut<BoardBackground>) {
+    background.pattern = background.pattern.next();
+}
+
+fn update_background_material(
+    background: Res<BoardBackground>,
+    quad: Query<&Handle<BackgroundMaterial>, With<BackgroundQuad>>,
+    mut materials: ResMut<Assets<BackgroundMaterial>>,
+    mut cameras: Query<&mut Camera2d, With<MainCamera>>,
+) {
+    for handle in quad.iter() {
+        if let Some(material) = materials.get_mut(handle) {
+            *material = background.as_ref().into();
+        }
+    }
+    // 只改白板相机的清屏颜色，不影响嵌入程序的其他相机
+    for mut camera in cameras.iter_mut() {
+        camera.clear_color = ClearColorConfig::Custom(background.color);
+    }
+}
+
+fn main_camera_added(cameras: Query<(), Added<MainCamera>>) -> bool {
+    !cameras.is_empty()
+}
+
+/// 网格铺满相机的可见区域
+fn follow_camera(
+    camera: Query<(&Transform, &OrthographicProjection), With<MainCamera>>,
+    mut quad: Query<
+        &mut Transform,
+        (With<BackgroundQuad>, Without<MainCamera>),
+    >,
+) {
+    let Ok((camera, proj)) = camera.get_single() else {
+        return;
+    };
+    for mut transform in quad.iter_mut() {
+        let center = camera.translation.xy() + proj.area.center();
+        transform.translation = center.extend(BACKGROUND_Z);
+        transform.scale = proj.area.size().extend(1.);
+    }
+}
@@ -0,0 +1,17 @@
+// 协作服务器：lines-server [地址]，默认监听 0.0.0.0:7878
+// 客户端设置环境变量 LINES_SERVER=<服务器地址>:7878 后启动 lines 即可加入
+use std::{io, net::TcpListener};
+
+use bevy::log::{info, tracing_subscriber, Level};
+use lines::collab::DEFAULT_PORT;
+
+fn main() -> io::Result<()> {
+    // 服务器用 info! / warn! 输出监听地址与加入、离开等信息
+    tracing_subscriber::fmt().with_max_level(Level::INFO).init();
+    let address = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| format!("0.0.0.0:{}", DEFAULT_PORT));
+    let listener = TcpListener::bind(&address)?;
+    info!("lines server listening on {}", listener.local_addr()?);
+    lines::collab_server::serve(listener)
+}
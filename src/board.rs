@@ -0,0 +1,1140 @@
+// 1. 与渲染无关的白板模型：若干页，每页有线条、文字、图片与组，对象的样式、图层顺序，以及修改它们的 BoardOp
+// 2. 所有修改都经过 apply 校验，失败时模型保持不变；只使用 bevy 的数学类型，颜色用自己的 Rgba，不依赖渲染与 World
+// 3. 对象保存世界坐标，组只记录成员：移动、缩放组就是移动、缩放其中的每个对象
+// 4. ECS 中的实体是当前页的视图（见 board_view.rs）；保存、导出、控制接口与网页直播都只读取模型
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    path::PathBuf,
+};
+
+use bevy::math::{Rect, Vec2};
+use serde::{Deserialize, Serialize};
+
+/// 图层的取值范围，超出范围的图层无法排在其他对象之上
+pub const LAYER_MAX: i8 = i8::MAX - 2;
+
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum BrushKind {
+    #[default]
+    Chalk,
+    Marker,
+    Highlighter,
+    Pencil,
+    Neon,
+}
+
+impl BrushKind {
+    pub const ALL: [BrushKind; 5] = [
+        BrushKind::Chalk,
+        BrushKind::Marker,
+        BrushKind::Highlighter,
+        BrushKind::Pencil,
+        BrushKind::Neon,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BrushKind::Chalk => "chalk",
+            BrushKind::Marker => "marker",
+            BrushKind::Highlighter => "highlighter",
+            BrushKind::Pencil => "pencil",
+            BrushKind::Neon => "neon",
+        }
+    }
+
+    pub fn next(&self) -> BrushKind {
+        let index = Self::ALL.iter().position(|kind| kind == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+/// 线条颜色，sRGB 分量在 0..=1 之间
+/// 序列化格式与 bevy 的 Color::Rgba 相同，以前保存的文档仍然可以读取
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(from = "RgbaRepr", into = "RgbaRepr")]
+pub struct Rgba {
+    pub red: f32,
+    pub green: f32,
+    pub blue: f32,
+    pub alpha: f32,
+}
+
+impl Rgba {
+    pub const WHITE: Rgba = Rgba::new(1., 1., 1., 1.);
+
+    pub const fn new(red: f32, green: f32, blue: f32, alpha: f32) -> Self {
+        Rgba {
+            red,
+            green,
+            blue,
+            alpha,
+        }
+    }
+}
+
+impl Default for Rgba {
+    fn default() -> Self {
+        Rgba::WHITE
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+enum RgbaRepr {
+    Rgba {
+        red: f32,
+        green: f32,
+        blue: f32,
+        alpha: f32,
+    },
+}
+
+impl From<RgbaRepr> for Rgba {
+    fn from(repr: RgbaRepr) -> Self {
+        let RgbaRepr::Rgba {
+            red,
+            green,
+            blue,
+            alpha,
+        } = repr;
+        Rgba::new(red, green, blue, alpha)
+    }
+}
+
+impl From<Rgba> for RgbaRepr {
+    fn from(color: Rgba) -> Self {
+        RgbaRepr::Rgba {
+            red: color.red,
+            green: color.green,
+            blue: color.blue,
+            alpha: color.alpha,
+        }
+    }
+}
+
+/// 线条的外观
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Style {
+    pub color: Rgba,
+    pub width: f32,
+    pub brush: BrushKind,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct StrokeData {
+    pub id: u64,
+    pub points: Vec<Vec2>,
+    pub color: Rgba,
+    pub width: f32,
+    pub layer: i8,
+    #[serde(default)]
+    pub offset: Vec2,
+    /** 每个点相对笔画开始的秒数，没有时为空 */
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub times: Vec<f32>,
+    /** 每个点的压感，没有时为空 */
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pressures: Vec<f32>,
+    #[serde(default)]
+    pub brush: BrushKind,
+}
+
+impl StrokeData {
+    pub fn style(&self) -> Style {
+        Style {
+            color: self.color,
+            width: self.width,
+            brush: self.brush,
+        }
+    }
+
+    /// 加上偏移后的世界坐标
+    pub fn world_points(&self) -> impl Iterator<Item = Vec2> + '_ {
+        self.points.iter().map(|point| *point + self.offset)
+    }
+
+    fn validate(&self) -> Result<(), BoardError> {
+        validate_style(self.width, self.layer)?;
+        if !self.points.iter().all(|point| point.is_finite())
+            || !self.offset.is_finite()
+        {
+            return Err(BoardError::InvalidPoint(self.id));
+        }
+        let len = self.points.len();
+        if !(self.times.is_empty() || self.times.len() == len)
+            || !(self.pressures.is_empty() || self.pressures.len() == len)
+        {
+            return Err(BoardError::Samples(self.id));
+        }
+        Ok(())
+    }
+}
+
+/// 白板上的文字
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TextData {
+    /** 旧版文档中的文字没有 id，打开时重新分配 */
+    #[serde(default)]
+    pub id: u64,
+    pub text: String,
+    /** 左上角的世界坐标 */
+    pub position: Vec2,
+    pub size: f32,
+    pub color: Rgba,
+    pub layer: i8,
+}
+
+impl TextData {
+    fn validate(&self) -> Result<(), BoardError> {
+        validate_size(self.size, self.layer)?;
+        if !self.position.is_finite() {
+            return Err(BoardError::InvalidPoint(self.id));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageSource {
+    Path(PathBuf),
+    Embedded {
+        /** 文件扩展名，如 png、jpg */
+        format: String,
+        /** base64 编码的文件内容 */
+        data: String,
+    },
+}
+
+fn default_scale() -> f32 {
+    1.
+}
+
+/// 白板上的图片
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ImageData {
+    /** 旧版文档中的图片没有 id，打开时重新分配 */
+    #[serde(default)]
+    pub id: u64,
+    pub source: ImageSource,
+    /** 图片中心的世界坐标 */
+    pub position: Vec2,
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+    pub layer: i8,
+    /** 锁定的图片可以选中，但不能拖动或缩放 */
+    #[serde(default)]
+    pub locked: bool,
+}
+
+impl ImageData {
+    fn validate(&self) -> Result<(), BoardError> {
+        validate_size(self.scale, self.layer)?;
+        if !self.position.is_finite() {
+            return Err(BoardError::InvalidPoint(self.id));
+        }
+        Ok(())
+    }
+}
+
+/// 组只记录成员，成员可以是线条、文字、图片或其他组
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GroupData {
+    pub id: u64,
+    pub members: Vec<u64>,
+}
+
+/// 一页上的全部对象，文档按这个格式保存；内层的组排在外层之前
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct PageData {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub strokes: Vec<StrokeData>,
+    #[serde(default)]
+    pub texts: Vec<TextData>,
+    #[serde(default)]
+    pub images: Vec<ImageData>,
+    #[serde(default)]
+    pub groups: Vec<GroupData>,
+}
+
+/// 白板上的一次操作，按顺序重放即可重建整个白板
+/// ECS 中通过 BoardOpEvent 传递（见 board_view.rs）
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum BoardOp {
+    StrokeStart {
+        id: u64,
+        color: Rgba,
+        width: f32,
+        layer: i8,
+        #[serde(default)]
+        brush: BrushKind,
+    },
+    StrokePoint {
+        id: u64,
+        point: Vec2,
+        /** 相对笔画开始的秒数 */
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        time: Option<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pressure: Option<f32>,
+    },
+    StrokeEnd {
+        id: u64,
+    },
+    /// 删除任意对象，删除组时连同成员一起删除
+    Erase {
+        id: u64,
+    },
+    /// 移动组时移动其中的每个对象
+    Move {
+        id: u64,
+        delta: Vec2,
+    },
+    /// 以 center 为中心缩放
+    Scale {
+        id: u64,
+        center: Vec2,
+        factor: f32,
+    },
+    /// 调整图层顺序
+    Reorder {
+        id: u64,
+        layer: i8,
+    },
+    /// 清除当前页的所有线条
+    Clear,
+    AddText {
+        text: TextData,
+    },
+    AddImage {
+        image: ImageData,
+    },
+    /// 锁定或解锁图片
+    Lock {
+        id: u64,
+        locked: bool,
+    },
+    /// members 编为一组，它们必须都不在组中或在同一个组中
+    Group {
+        id: u64,
+        members: Vec<u64>,
+    },
+    /// 成员回到组原来所在的组
+    Ungroup {
+        id: u64,
+    },
+    /// 在最后加入一页并切换过去
+    AddPage {
+        name: String,
+    },
+    SelectPage {
+        index: usize,
+    },
+    RenamePage {
+        index: usize,
+        name: String,
+    },
+}
+
+impl BoardOp {
+    /// 操作的对象，Clear 与页面操作没有
+    pub fn id(&self) -> Option<u64> {
+        match self {
+            BoardOp::StrokeStart { id, .. }
+            | BoardOp::StrokePoint { id, .. }
+            | BoardOp::StrokeEnd { id }
+            | BoardOp::Erase { id }
+            | BoardOp::Move { id, .. }
+            | BoardOp::Scale { id, .. }
+            | BoardOp::Reorder { id, .. }
+            | BoardOp::Lock { id, .. }
+            | BoardOp::Group { id, .. }
+            | BoardOp::Ungroup { id } => Some(*id),
+            BoardOp::AddText { text } => Some(text.id),
+            BoardOp::AddImage { image } => Some(image.id),
+            BoardOp::Clear
+            | BoardOp::AddPage { .. }
+            | BoardOp::SelectPage { .. }
+            | BoardOp::RenamePage { .. } => None,
+        }
+    }
+}
+
+/// 一次性加入白板的线条（导入、粘贴等）对应的操作序列
+pub fn stroke_ops(stroke: &StrokeData) -> Vec<BoardOp> {
+    let id = stroke.id;
+    let mut ops = vec![BoardOp::StrokeStart {
+        id,
+        color: stroke.color,
+        width: stroke.width,
+        layer: stroke.layer,
+        brush: stroke.brush,
+    }];
+    ops.extend(stroke.points.iter().enumerate().map(|(i, &point)| {
+        BoardOp::StrokePoint {
+            id,
+            point,
+            time: stroke.times.get(i).copied(),
+            pressure: stroke.pressures.get(i).copied(),
+        }
+    }));
+    if stroke.offset != Vec2::ZERO {
+        ops.push(BoardOp::Move {
+            id,
+            delta: stroke.offset,
+        });
+    }
+    ops.push(BoardOp::StrokeEnd { id });
+    ops
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BoardError {
+    DuplicateId(u64),
+    UnknownId(u64),
+    /** 线条已经结束，不能再添加点 */
+    Finished(u64),
+    InvalidPoint(u64),
+    InvalidWidth(f32),
+    /** 文字大小或图片缩放 */
+    InvalidSize(f32),
+    InvalidLayer(i8),
+    /** 时间或压感与点的数量不一致 */
+    Samples(u64),
+    /** 对象不支持这个操作，例如给文字添加点、锁定线条 */
+    WrongKind(u64),
+    EmptyGroup(u64),
+    /** 编组的成员不在同一个组中 */
+    Grouped(u64),
+    UnknownPage(usize),
+    /** 页面操作只能应用到整个白板 */
+    PageOp,
+}
+
+impl fmt::Display for BoardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BoardError::DuplicateId(id) => {
+                write!(f, "object {} already exists", id)
+            }
+            BoardError::UnknownId(id) => write!(f, "no object {}", id),
+            BoardError::Finished(id) => {
+                write!(f, "stroke {} is already finished", id)
+            }
+            BoardError::InvalidPoint(id) => {
+                write!(f, "object {} has a non-finite point", id)
+            }
+            BoardError::InvalidWidth(width) => {
+                write!(f, "invalid stroke width {}", width)
+            }
+            BoardError::InvalidSize(size) => write!(f, "invalid size {}", size),
+            BoardError::InvalidLayer(layer) => {
+                write!(f, "layer {} is outside 0..={}", layer, LAYER_MAX)
+            }
+            BoardError::Samples(id) => write!(
+                f,
+                "stroke {} has times or pressures for only some points",
+                id
+            ),
+            BoardError::WrongKind(id) => {
+                write!(f, "object {} does not support this operation", id)
+            }
+            BoardError::EmptyGroup(id) => {
+                write!(f, "group {} has no members", id)
+            }
+            BoardError::Grouped(id) => write!(
+                f,
+                "object {} is not in the same group as the other members",
+                id
+            ),
+            BoardError::UnknownPage(index) => write!(f, "no page {}", index),
+            BoardError::PageOp => {
+                write!(f, "page operations apply to the whole board")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BoardError {}
+
+pub(crate) fn validate_style(width: f32, layer: i8) -> Result<(), BoardError> {
+    if !(width.is_finite() && width > 0.) {
+        return Err(BoardError::InvalidWidth(width));
+    }
+    validate_layer(layer)
+}
+
+fn validate_size(size: f32, layer: i8) -> Result<(), BoardError> {
+    if !(size.is_finite() && size > 0.) {
+        return Err(BoardError::InvalidSize(size));
+    }
+    validate_layer(layer)
+}
+
+pub(crate) fn validate_layer(layer: i8) -> Result<(), BoardError> {
+    if !(0..=LAYER_MAX).contains(&layer) {
+        return Err(BoardError::InvalidLayer(layer));
+    }
+    Ok(())
+}
+
+/// 有位置的对象，组不在其中
+enum ObjectMut<'a> {
+    Stroke(&'a mut StrokeData),
+    Text(&'a mut TextData),
+    Image(&'a mut ImageData),
+}
+
+/// 一页白板，所有对象按 id 保存，id 在页内唯一
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Page {
+    pub name: String,
+    strokes: BTreeMap<u64, StrokeData>,
+    texts: BTreeMap<u64, TextData>,
+    images: BTreeMap<u64, ImageData>,
+    groups: BTreeMap<u64, GroupData>,
+    /** 组的成员所在的组 */
+    parents: BTreeMap<u64, u64>,
+    /** 还在绘制中的线条 */
+    open: BTreeSet<u64>,
+}
+
+impl Page {
+    pub fn new(name: impl Into<String>) -> Self {
+        Page {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    /// 不满足约束的对象被跳过，返回对应的错误
+    pub fn from_data(data: PageData) -> (Self, Vec<BoardError>) {
+        let mut page = Page::new(data.name);
+        let mut errors = vec![];
+        for stroke in data.strokes {
+            if let Err(err) = page.insert(stroke) {
+                errors.push(err);
+            }
+        }
+        let ops = data
+            .texts
+            .into_iter()
+            .map(|text| BoardOp::AddText { text })
+            .chain(
+                data.images
+                    .into_iter()
+                    .map(|image| BoardOp::AddImage { image }),
+            )
+            .chain(data.groups.into_iter().map(|GroupData { id, members }| {
+                BoardOp::Group { id, members }
+            }));
+        for op in ops {
+            if let Err(err) = page.apply(&op) {
+                errors.push(err);
+            }
+        }
+        (page, errors)
+    }
+
+    pub fn to_data(&self) -> PageData {
+        PageData {
+            name: self.name.clone(),
+            strokes: self.to_strokes(),
+            texts: self.texts.values().cloned().collect(),
+            images: self.images.values().cloned().collect(),
+            groups: self.nested_groups().into_iter().cloned().collect(),
+        }
+    }
+
+    /// id 对应的线条
+    pub fn get(&self, id: u64) -> Option<&StrokeData> {
+        self.strokes.get(&id)
+    }
+
+    pub fn text(&self, id: u64) -> Option<&TextData> {
+        self.texts.get(&id)
+    }
+
+    pub fn image(&self, id: u64) -> Option<&ImageData> {
+        self.images.get(&id)
+    }
+
+    pub fn group(&self, id: u64) -> Option<&GroupData> {
+        self.groups.get(&id)
+    }
+
+    /// 任意类型的对象
+    pub fn contains(&self, id: u64) -> bool {
+        self.strokes.contains_key(&id)
+            || self.texts.contains_key(&id)
+            || self.images.contains_key(&id)
+            || self.groups.contains_key(&id)
+    }
+
+    pub fn is_open(&self, id: u64) -> bool {
+        self.open.contains(&id)
+    }
+
+    /// 所有对象的数量，包括组
+    pub fn len(&self) -> usize {
+        self.strokes.len()
+            + self.texts.len()
+            + self.images.len()
+            + self.groups.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 最大的对象 id
+    pub fn last_id(&self) -> Option<u64> {
+        [
+            self.strokes.keys().last(),
+            self.texts.keys().last(),
+            self.images.keys().last(),
+            self.groups.keys().last(),
+        ]
+        .into_iter()
+        .flatten()
+        .max()
+        .copied()
+    }
+
+    /// 按 id 顺序
+    pub fn strokes(&self) -> impl Iterator<Item = &StrokeData> {
+        self.strokes.values()
+    }
+
+    pub fn texts(&self) -> impl Iterator<Item = &TextData> {
+        self.texts.values()
+    }
+
+    pub fn images(&self) -> impl Iterator<Item = &ImageData> {
+        self.images.values()
+    }
+
+    /// 按 id 顺序复制所有线条
+    pub fn to_strokes(&self) -> Vec<StrokeData> {
+        self.strokes.values().cloned().collect()
+    }
+
+    /// 对象所在的组
+    pub fn group_of(&self, id: u64) -> Option<u64> {
+        self.parents.get(&id).copied()
+    }
+
+    /// 对象本身与组内的所有子孙，没有这个对象时为空
+    pub fn descendants(&self, id: u64) -> Vec<u64> {
+        if !self.contains(id) {
+            return vec![];
+        }
+        let mut ids = vec![id];
+        let mut next = 0;
+        while let Some(&id) = ids.get(next) {
+            if let Some(group) = self.groups.get(&id) {
+                ids.extend(&group.members);
+            }
+            next += 1;
+        }
+        ids
+    }
+
+    /// 内层的组在前，按这个顺序可以逐个重建
+    pub fn nested_groups(&self) -> Vec<&GroupData> {
+        let depth = |mut id: u64| {
+            let mut depth = 0;
+            while let Some(parent) = self.parents.get(&id) {
+                depth += 1;
+                id = *parent;
+            }
+            depth
+        };
+        let mut groups: Vec<&GroupData> = self.groups.values().collect();
+        groups.sort_by_key(|group| (Reverse(depth(group.id)), group.id));
+        groups
+    }
+
+    /// 对象锚点（线条的点、文字的左上角、图片的中心）的包围盒，组为所有成员的
+    pub fn bounds(&self, id: u64) -> Option<Rect> {
+        self.descendants(id)
+            .into_iter()
+            .flat_map(|id| self.anchors(id))
+            .map(|point| Rect::from_corners(point, point))
+            .reduce(|a, b| a.union(b))
+    }
+
+    fn anchors(&self, id: u64) -> Vec<Vec2> {
+        if let Some(stroke) = self.strokes.get(&id) {
+            return stroke.world_points().collect();
+        }
+        let text = self.texts.get(&id).map(|text| text.position);
+        let image = self.images.get(&id).map(|image| image.position);
+        text.or(image).into_iter().collect()
+    }
+
+    /// 从空白页重建这一页的操作序列，绘制中的线条不结束
+    pub fn to_ops(&self) -> Vec<BoardOp> {
+        let mut ops = vec![];
+        for stroke in self.strokes.values() {
+            ops.extend(stroke_ops(stroke));
+            if self.is_open(stroke.id) {
+                ops.pop();
+            }
+        }
+        ops.extend(
+            self.texts
+                .values()
+                .map(|text| BoardOp::AddText { text: text.clone() }),
+        );
+        ops.extend(self.images.values().map(|image| BoardOp::AddImage {
+            image: image.clone(),
+        }));
+        ops.extend(self.nested_groups().into_iter().map(|group| {
+            BoardOp::Group {
+                id: group.id,
+                members: group.members.clone(),
+            }
+        }));
+        ops
+    }
+
+    /// 绘制顺序：先按图层，同一图层内先画的在下面
+    pub fn ordered(&self) -> Vec<&StrokeData> {
+        let mut strokes: Vec<_> = self.strokes.values().collect();
+        strokes.sort_by_key(|stroke| (stroke.layer, stroke.id));
+        strokes
+    }
+
+    /// 加入一条已经画完的线条（打开文档、导入等）
+    pub fn insert(&mut self, stroke: StrokeData) -> Result<(), BoardError> {
+        if self.contains(stroke.id) {
+            return Err(BoardError::DuplicateId(stroke.id));
+        }
+        stroke.validate()?;
+        self.strokes.insert(stroke.id, stroke);
+        Ok(())
+    }
+
+    pub fn apply(&mut self, op: &BoardOp) -> Result<(), BoardError> {
+        match op {
+            BoardOp::StrokeStart {
+                id,
+                color,
+                width,
+                layer,
+                brush,
+            } => {
+                if self.contains(*id) {
+                    return Err(BoardError::DuplicateId(*id));
+                }
+                validate_style(*width, *layer)?;
+                self.strokes.insert(
+                    *id,
+                    StrokeData {
+                        id: *id,
+                        color: *color,
+                        width: *width,
+                        layer: *layer,
+                        brush: *brush,
+                        ..Default::default()
+                    },
+                );
+                self.open.insert(*id);
+            }
+            BoardOp::StrokePoint {
+                id,
+                point,
+                time,
+                pressure,
+            } => {
+                if !self.open.contains(id) {
+                    return Err(self.not_open(*id));
+                }
+                let stroke = self.strokes.get_mut(id).unwrap();
+                if !point.is_finite() {
+                    return Err(BoardError::InvalidPoint(*id));
+                }
+                // 第一个点决定这条线是否带有时间与压感
+                let len = stroke.points.len();
+                let matches = |samples: &Vec<f32>, sample: &Option<f32>| {
+                    samples.len() == if sample.is_some() { len } else { 0 }
+                };
+                if !matches(&stroke.times, time)
+                    || !matches(&stroke.pressures, pressure)
+                {
+                    return Err(BoardError::Samples(*id));
+                }
+                stroke.points.push(*point);
+                stroke.times.extend(time);
+                stroke.pressures.extend(pressure);
+            }
+            BoardOp::StrokeEnd { id } => {
+                if !self.open.remove(id) {
+                    return Err(self.not_open(*id));
+                }
+            }
+            BoardOp::Erase { id } => self.remove(*id)?,
+            BoardOp::Move { id, delta } => {
+                let ids = self.leaves(*id)?;
+                if !delta.is_finite() {
+                    return Err(BoardError::InvalidPoint(*id));
+                }
+                for id in ids {
+                    match self.object_mut(id) {
+                        Some(ObjectMut::Stroke(stroke)) => {
+                            stroke.offset += *delta
+                        }
+                        Some(ObjectMut::Text(text)) => text.position += *delta,
+                        Some(ObjectMut::Image(image)) => {
+                            image.position += *delta
+                        }
+                        None => {}
+                    }
+                }
+            }
+            BoardOp::Scale { id, center, factor } => {
+                let ids = self.leaves(*id)?;
+                if !(center.is_finite() && factor.is_finite() && *factor > 0.) {
+                    return Err(BoardError::InvalidPoint(*id));
+                }
+                let scale = |point: Vec2| *center + (point - *center) * *factor;
+                for id in ids {
+                    match self.object_mut(id) {
+                        Some(ObjectMut::Stroke(stroke)) => {
+                            for point in stroke.points.iter_mut() {
+                                *point *= *factor;
+                            }
+                            stroke.offset = scale(stroke.offset);
+                        }
+                        Some(ObjectMut::Text(text)) => {
+                            text.size *= *factor;
+                            text.position = scale(text.position);
+                        }
+                        Some(ObjectMut::Image(image)) => {
+                            image.scale *= *factor;
+                            image.position = scale(image.position);
+                        }
+                        None => {}
+                    }
+                }
+            }
+            BoardOp::Reorder { id, layer } => {
+                validate_layer(*layer)?;
+                for id in self.leaves(*id)? {
+                    match self.object_mut(id) {
+                        Some(ObjectMut::Stroke(stroke)) => {
+                            stroke.layer = *layer
+                        }
+                        Some(ObjectMut::Text(text)) => text.layer = *layer,
+                        Some(ObjectMut::Image(image)) => image.layer = *layer,
+                        None => {}
+                    }
+                }
+            }
+            BoardOp::Clear => {
+                let ids: Vec<u64> = self.strokes.keys().copied().collect();
+                for id in ids {
+                    self.detach(id);
+                }
+                self.strokes.clear();
+                self.open.clear();
+                self.remove_empty_groups();
+            }
+            BoardOp::AddText { text } => {
+                if self.contains(text.id) {
+                    return Err(BoardError::DuplicateId(text.id));
+                }
+                text.validate()?;
+                self.texts.insert(text.id, text.clone());
+            }
+            BoardOp::AddImage { image } => {
+                if self.contains(image.id) {
+                    return Err(BoardError::DuplicateId(image.id));
+                }
+                image.validate()?;
+                self.images.insert(image.id, image.clone());
+            }
+            BoardOp::Lock { id, locked } => match self.images.get_mut(id) {
+                Some(image) => image.locked = *locked,
+                None => return Err(self.wrong_kind(*id)),
+            },
+            BoardOp::Group { id, members } => self.group(*id, members)?,
+            BoardOp::Ungroup { id } => {
+                let Some(group) = self.groups.remove(id) else {
+                    return Err(self.wrong_kind(*id));
+                };
+                let parent = self.parents.remove(id);
+                for member in &group.members {
+                    match parent {
+                        Some(parent) => self.parents.insert(*member, parent),
+                        None => self.parents.remove(member),
+                    };
+                }
+                if let Some(parent) = parent {
+                    let parent = self.groups.get_mut(&parent).unwrap();
+                    parent.members.retain(|member| member != id);
+                    parent.members.extend(&group.members);
+                }
+            }
+            BoardOp::AddPage { .. }
+            | BoardOp::SelectPage { .. }
+            | BoardOp::RenamePage { .. } => return Err(BoardError::PageOp),
+        }
+        Ok(())
+    }
+
+    fn group(&mut self, id: u64, members: &[u64]) -> Result<(), BoardError> {
+        if self.contains(id) {
+            return Err(BoardError::DuplicateId(id));
+        }
+        let Some(first) = members.first() else {
+            return Err(BoardError::EmptyGroup(id));
+        };
+        let parent = self.group_of(*first);
+        let mut seen = BTreeSet::new();
+        for member in members {
+            if !self.contains(*member) {
+                return Err(BoardError::UnknownId(*member));
+            }
+            if !seen.insert(*member) {
+                return Err(BoardError::DuplicateId(*member));
+            }
+            if self.group_of(*member) != parent {
+                return Err(BoardError::Grouped(*member));
+            }
+        }
+        for member in members {
+            self.parents.insert(*member, id);
+        }
+        // 在组内编组时，新组代替成员留在原来的组中
+        if let Some(parent) = parent {
+            let group = self.groups.get_mut(&parent).unwrap();
+            group.members.retain(|member| !seen.contains(member));
+            group.members.push(id);
+            self.parents.insert(id, parent);
+        }
+        self.groups.insert(
+            id,
+            GroupData {
+                id,
+                members: members.to_vec(),
+            },
+        );
+        Ok(())
+    }
+
+    /// 删除对象及其子孙；组因此变空时一并删除
+    fn remove(&mut self, id: u64) -> Result<(), BoardError> {
+        let ids = self.descendants(id);
+        if ids.is_empty() {
+            return Err(BoardError::UnknownId(id));
+        }
+        self.detach(id);
+        for id in ids {
+            self.strokes.remove(&id);
+            self.texts.remove(&id);
+            self.images.remove(&id);
+            self.groups.remove(&id);
+            self.parents.remove(&id);
+            self.open.remove(&id);
+        }
+        self.remove_empty_groups();
+        Ok(())
+    }
+
+    /// 从所在的组中移出
+    fn detach(&mut self, id: u64) {
+        if let Some(parent) = self.parents.remove(&id) {
+            if let Some(group) = self.groups.get_mut(&parent) {
+                group.members.retain(|member| *member != id);
+            }
+        }
+    }
+
+    fn remove_empty_groups(&mut self) {
+        while let Some(id) = self
+            .groups
+            .values()
+            .find(|group| group.members.is_empty())
+            .map(|group| group.id)
+        {
+            self.detach(id);
+            self.groups.remove(&id);
+        }
+    }
+
+    /// 对象本身，或者组内所有不是组的子孙
+    fn leaves(&self, id: u64) -> Result<Vec<u64>, BoardError> {
+        let ids = self.descendants(id);
+        if ids.is_empty() {
+            return Err(BoardError::UnknownId(id));
+        }
+        Ok(ids
+            .into_iter()
+            .filter(|id| !self.groups.contains_key(id))
+            .collect())
+    }
+
+    fn object_mut(&mut self, id: u64) -> Option<ObjectMut<'_>> {
+        if let Some(stroke) = self.strokes.get_mut(&id) {
+            return Some(ObjectMut::Stroke(stroke));
+        }
+        if let Some(text) = self.texts.get_mut(&id) {
+            return Some(ObjectMut::Text(text));
+        }
+        self.images.get_mut(&id).map(ObjectMut::Image)
+    }
+
+    fn not_open(&self, id: u64) -> BoardError {
+        if self.strokes.contains_key(&id) {
+            BoardError::Finished(id)
+        } else {
+            self.wrong_kind(id)
+        }
+    }
+
+    fn wrong_kind(&self, id: u64) -> BoardError {
+        if self.contains(id) {
+            BoardError::WrongKind(id)
+        } else {
+            BoardError::UnknownId(id)
+        }
+    }
+}
+
+const FIRST_PAGE: &str = "Page 1";
+
+/// 整个白板：若干页，对象的操作都作用于当前页
+#[derive(Debug, Clone, PartialEq)]
+pub struct Board {
+    pages: Vec<Page>,
+    current: usize,
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Board {
+            pages: vec![Page::new(FIRST_PAGE)],
+            current: 0,
+        }
+    }
+}
+
+impl Board {
+    /// 用已有的线条生成白板
+    pub fn from_strokes(
+        strokes: impl IntoIterator<Item = StrokeData>,
+    ) -> Result<Self, BoardError> {
+        let mut board = Board::default();
+        for stroke in strokes {
+            board.insert(stroke)?;
+        }
+        Ok(board)
+    }
+
+    /// 用保存的页面生成白板，不满足约束的对象被跳过并返回对应的错误
+    pub fn from_data(
+        pages: impl IntoIterator<Item = PageData>,
+        current: usize,
+    ) -> (Self, Vec<BoardError>) {
+        let mut errors = vec![];
+        let pages: Vec<Page> = pages
+            .into_iter()
+            .map(|data| {
+                let (page, page_errors) = Page::from_data(data);
+                errors.extend(page_errors);
+                page
+            })
+            .collect();
+        if pages.is_empty() {
+            return (Board::default(), errors);
+        }
+        let current = current.min(pages.len() - 1);
+        (Board { pages, current }, errors)
+    }
+
+    pub fn to_data(&self) -> Vec<PageData> {
+        self.pages.iter().map(Page::to_data).collect()
+    }
+
+    /// 当前页
+    pub fn page(&self) -> &Page {
+        &self.pages[self.current]
+    }
+
+    pub fn pages(&self) -> &[Page] {
+        &self.pages
+    }
+
+    /// 当前页的序号
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    /// 所有页都没有对象
+    pub fn is_empty(&self) -> bool {
+        self.pages.iter().all(Page::is_empty)
+    }
+
+    pub fn contains(&self, id: u64) -> bool {
+        self.page().contains(id)
+    }
+
+    pub fn insert(&mut self, stroke: StrokeData) -> Result<(), BoardError> {
+        self.pages[self.current].insert(stroke)
+    }
+
+    /// 从空白板重建整个白板的操作序列
+    pub fn to_ops(&self) -> Vec<BoardOp> {
+        let mut ops = vec![];
+        for (index, page) in self.pages.iter().enumerate() {
+            if index > 0 {
+                ops.push(BoardOp::AddPage {
+                    name: page.name.clone(),
+                });
+            } else if page.name != FIRST_PAGE {
+                ops.push(BoardOp::RenamePage {
+                    index,
+                    name: page.name.clone(),
+                });
+            }
+            ops.extend(page.to_ops());
+        }
+        if self.current + 1 != self.pages.len() {
+            ops.push(BoardOp::SelectPage {
+                index: self.current,
+            });
+        }
+        ops
+    }
+
+    pub fn apply(&mut self, op: &BoardOp) -> Result<(), BoardError> {
+        match op {
+            BoardOp::AddPage { name } => {
+                self.pages.push(Page::new(name.clone()));
+                self.current = self.pages.len() - 1;
+            }
+            BoardOp::SelectPage { index } => {
+                if *index >= self.pages.len() {
+                    return Err(BoardError::UnknownPage(*index));
+                }
+                self.current = *index;
+            }
+            BoardOp::RenamePage { index, name } => {
+                self.pages
+                    .get_mut(*index)
+                    .ok_or(BoardError::UnknownPage(*index))?
+                    .name = name.clone();
+            }
+            _ => return self.pages[self.current].apply(op),
+        }
+        Ok(())
+    }
+}
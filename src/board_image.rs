@@ -0,0 +1,174 @@
+use std::{fs, io, path::Path};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bevy::{
+    prelude::*,
+    render::texture::{CompressedImageFormats, ImageSampler, ImageType},
+    window::FileDragAndDrop,
+};
+
+pub use crate::board::{ImageData, ImageSource};
+
+use crate::{
+    board_view::{BoardOpEvent, ObjectId},
+    draw::LineSpawner,
+    layer::Layer,
+    projection_2d_control::{BoardWindow, MainCamera},
+    recording::BoardOp,
+    replay::ReplayState,
+    selected::Locked,
+};
+
+pub struct BoardImagePlugin;
+
+impl Plugin for BoardImagePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ImageImportConfig>().add_systems(
+            Update,
+            import_dropped_images.run_if(in_state(ReplayState::Off)),
+        );
+    }
+}
+
+#[derive(Resource)]
+pub struct ImageImportConfig {
+    /** 为 true 时把图片内容嵌入文档，否则只保存路径 */
+    pub embed: bool,
+}
+
+impl Default for ImageImportConfig {
+    fn default() -> Self {
+        ImageImportConfig { embed: true }
+    }
+}
+
+impl ImageSource {
+    fn bytes(&self) -> io::Result<Vec<u8>> {
+        match self {
+            ImageSource::Path(path) => fs::read(path),
+            ImageSource::Embedded { data, .. } => STANDARD
+                .decode(data)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)),
+        }
+    }
+
+    fn format(&self) -> String {
+        match self {
+            ImageSource::Path(path) => {
+                image_extension(path).unwrap_or_default()
+            }
+            ImageSource::Embedded { format, .. } => format.clone(),
+        }
+    }
+}
+
+/// 白板上的图片对象
+#[derive(Component, Debug, Clone)]
+pub struct BoardImage {
+    pub source: ImageSource,
+}
+
+pub fn spawn_image(
+    commands: &mut Commands,
+    images: &mut Assets<Image>,
+    data: &ImageData,
+) -> io::Result<Entity> {
+    let image = Image::from_buffer(
+        &data.source.bytes()?,
+        ImageType::Extension(&data.source.format()),
+        CompressedImageFormats::default(),
+        true,
+        ImageSampler::Default,
+    )
+    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    let layer = Layer::Foreground(data.layer);
+    let mut entity = commands.spawn((
+        SpriteBundle {
+            texture: images.add(image),
+            transform: Transform::from_translation(
+                data.position.extend(layer.z()),
+            )
+            .with_scale(Vec3::new(data.scale, data.scale, 1.)),
+            ..default()
+        },
+        BoardImage {
+            source: data.source.clone(),
+        },
+        layer,
+        ObjectId(data.id),
+    ));
+    if data.locked {
+        entity.insert(Locked);
+    }
+    Ok(entity.id())
+}
+
+fn image_extension(path: &Path) -> Option<String> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    match extension.as_str() {
+        "png" | "jpg" | "jpeg" => Some(extension),
+        _ => None,
+    }
+}
+
+fn import_dropped_images(
+    mut drop_events: EventReader<FileDragAndDrop>,
+    config: Res<ImageImportConfig>,
+    mut spawner: LineSpawner,
+    mut images: ResMut<Assets<Image>>,
+    board_window: BoardWindow,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut board_ops: EventWriter<BoardOpEvent>,
+) {
+    for event in drop_events.read() {
+        let FileDragAndDrop::DroppedFile { path_buf, .. } = event else {
+            continue;
+        };
+        let Some(format) = image_extension(path_buf) else {
+            continue;
+        };
+
+        let source = if config.embed {
+            match fs::read(path_buf) {
+                Ok(bytes) => ImageSource::Embedded {
+                    format,
+                    data: STANDARD.encode(bytes),
+                },
+                Err(err) => {
+                    error!("failed to read {:?}: {}", path_buf, err);
+                    continue;
+                }
+            }
+        } else {
+            ImageSource::Path(
+                path_buf.canonicalize().unwrap_or_else(|_| path_buf.clone()),
+            )
+        };
+
+        // 放在指针处，指针不在窗口内时放在视口中心
+        let (camera, camera_transform) = camera_query.single();
+        let position = board_window
+            .cursor_position()
+            .and_then(|cursor| {
+                camera.viewport_to_world_2d(camera_transform, cursor)
+            })
+            .unwrap_or(camera_transform.translation().xy());
+
+        let (id, layer) = spawner.next();
+        let data = ImageData {
+            id,
+            source,
+            position,
+            scale: 1.,
+            layer,
+            locked: false,
+        };
+        match spawn_image(&mut spawner.commands, &mut images, &data) {
+            Ok(_) => {
+                board_ops.send(BoardOpEvent(BoardOp::AddImage { image: data }));
+                info!("imported {:?}", path_buf);
+            }
+            Err(err) => error!("failed to import {:?}: {}", path_buf, err),
+        }
+    }
+}
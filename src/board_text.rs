@@ -0,0 +1,66 @@
+use bevy::{
+    prelude::*, render::primitives::Aabb, sprite::Anchor, text::TextLayoutInfo,
+};
+
+pub use crate::board::TextData;
+
+use crate::{board_view::ObjectId, layer::Layer};
+
+pub struct BoardTextPlugin;
+
+impl Plugin for BoardTextPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, update_text_aabb);
+    }
+}
+
+/// 白板上的文字对象
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct BoardText {
+    pub text: String,
+    pub size: f32,
+    pub color: Color,
+}
+
+pub fn spawn_text(commands: &mut Commands, data: &TextData) -> Entity {
+    commands
+        .spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    data.text.clone(),
+                    TextStyle {
+                        font_size: data.size,
+                        color: data.color.into(),
+                        ..default()
+                    },
+                ),
+                text_anchor: Anchor::TopLeft,
+                transform: Transform::from_translation(
+                    data.position.extend(0.),
+                ),
+                ..default()
+            },
+            BoardText {
+                text: data.text.clone(),
+                size: data.size,
+                color: data.color.into(),
+            },
+            Layer::Foreground(data.layer),
+            ObjectId(data.id),
+        ))
+        .id()
+}
+
+/// Text2d 没有包围盒，按排版结果补上以便点选
+fn update_text_aabb(
+    mut commands: Commands,
+    query: Query<(Entity, &TextLayoutInfo), Changed<TextLayoutInfo>>,
+) {
+    for (entity, layout) in query.iter() {
+        let half_extents = layout.logical_size / 2.;
+        commands.entity(entity).insert(Aabb {
+            center: Vec3::new(half_extents.x, -half_extents.y, 0.).into(),
+            half_extents: half_extents.extend(0.).into(),
+        });
+    }
+}
@@ -0,0 +1,263 @@
+// 1. BoardModel 保存白板模型，ECS 中的线条、文字、图片与组实体是当前页的视图，用 ObjectId 对应模型中的对象
+// 2. 绘制、擦除、移动等系统修改实体的同时发送 BoardOp，这里按顺序把 BoardOp 应用到模型
+// 3. 切换页面、打开文档、恢复自动保存后，rebuild_view 按模型重建整页实体（见 ShownPage）
+// 4. 不经过本地工具的修改（协作中其他人的操作）先改模型，再用 sync_stroke_entities 更新线条，其他对象重建整页
+// 5. 模型不依赖 bevy 的渲染与 ECS：BoardOp 包装成 BoardOpEvent 事件发送，颜色在这里与 Color 互相转换
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+
+use crate::{
+    board::{Board, Page, Rgba},
+    board_image::spawn_image,
+    board_text::spawn_text,
+    crdt::{CrdtBoard, CrdtLog},
+    draw::{Line, LineId, LineSamples, LineSpawner},
+    group::{EnteredGroup, Group},
+    layer::Layer,
+    recording::BoardOp,
+    replay::ReplayState,
+    selected::Selected,
+};
+
+pub struct BoardViewPlugin;
+
+impl Plugin for BoardViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<BoardOpEvent>()
+            .init_resource::<BoardModel>()
+            .init_resource::<BoardCrdt>()
+            .init_resource::<ShownPage>()
+            .add_systems(
+                PostUpdate,
+                (
+                    apply_board_ops,
+                    rebuild_view.run_if(in_state(ReplayState::Off)),
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// 本地工具、导入、粘贴等对白板的修改
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct BoardOpEvent(pub BoardOp);
+
+impl From<Color> for Rgba {
+    fn from(color: Color) -> Self {
+        let [red, green, blue, alpha] = color.as_rgba_f32();
+        Rgba::new(red, green, blue, alpha)
+    }
+}
+
+impl From<Rgba> for Color {
+    fn from(color: Rgba) -> Self {
+        Color::rgba(color.red, color.green, color.blue, color.alpha)
+    }
+}
+
+#[derive(Resource, Default, Debug, Clone)]
+pub struct BoardModel(pub Board);
+
+/// 各页线条的 CRDT 副本，保存文档时与模型同步，用于合并分别编辑的文档
+#[derive(Resource, Debug, Clone)]
+pub struct BoardCrdt {
+    site: u16,
+    pages: Vec<CrdtBoard>,
+}
+
+impl Default for BoardCrdt {
+    /// 每次启动与打开文档都使用随机的站点，同一份文档的两个副本之后的编辑不会冲突
+    fn default() -> Self {
+        BoardCrdt::new(rand::random())
+    }
+}
+
+impl BoardCrdt {
+    pub fn new(site: u16) -> Self {
+        BoardCrdt {
+            site,
+            pages: vec![],
+        }
+    }
+
+    /// 读取文档中每页的操作记录，读取失败的页在下次保存时重新导入
+    pub fn load(site: u16, logs: &[CrdtLog]) -> Self {
+        let mut crdt = BoardCrdt::new(site);
+        for log in logs {
+            let page =
+                CrdtBoard::from_log(crdt.site, log).unwrap_or_else(|err| {
+                    warn!("skipped the operation log of a page: {}", err);
+                    CrdtBoard::new(crdt.site)
+                });
+            crdt.pages.push(page);
+        }
+        crdt
+    }
+
+    /// 与模型同步后每页的操作记录
+    pub fn sync(&mut self, model: &Board) -> Vec<CrdtLog> {
+        let site = self.site;
+        self.pages
+            .resize_with(model.pages().len(), || CrdtBoard::new(site));
+        self.pages
+            .iter_mut()
+            .zip(model.pages())
+            .map(|(crdt, page)| {
+                if let Err(err) = crdt.sync_page(page) {
+                    warn!("failed to record page {:?}: {}", page.name, err);
+                }
+                crdt.to_log()
+            })
+            .collect()
+    }
+}
+
+/// 实体对应的模型对象
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObjectId(pub u64);
+
+/// 实体当前显示的页，与模型的当前页不同时重建视图；设为 None 即可强制重建
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct ShownPage(pub Option<usize>);
+
+impl Default for ShownPage {
+    /// 启动时的空白板已经是第一页的视图
+    fn default() -> Self {
+        ShownPage(Some(0))
+    }
+}
+
+pub(crate) fn apply_board_ops(
+    mut board_ops: EventReader<BoardOpEvent>,
+    mut model: ResMut<BoardModel>,
+) {
+    for BoardOpEvent(op) in board_ops.read() {
+        if let Err(err) = model.0.apply(op) {
+            warn!("board model rejected {:?}: {}", op, err);
+        }
+    }
+}
+
+/// 按模型重建当前页的所有实体，组的原点放在成员锚点包围盒的中心
+fn rebuild_view(
+    model: Res<BoardModel>,
+    mut shown: ResMut<ShownPage>,
+    objects: Query<Entity, (With<ObjectId>, Without<Parent>)>,
+    mut spawner: LineSpawner,
+    mut images: ResMut<Assets<Image>>,
+    mut selected: ResMut<Selected>,
+    mut entered: ResMut<EnteredGroup>,
+) {
+    if shown.0 == Some(model.0.current()) {
+        return;
+    }
+    shown.0 = Some(model.0.current());
+    selected.0.clear();
+    entered.0 = None;
+    for entity in objects.iter() {
+        spawner.commands.entity(entity).despawn_recursive();
+    }
+    // 其他页的对象也占用 id，切回去时不会冲突
+    for page in model.0.pages() {
+        if let Some(id) = page.last_id() {
+            spawner.reserve_id(id);
+        }
+    }
+
+    let page = model.0.page();
+    let center =
+        |id: u64| page.bounds(id).map_or(Vec2::ZERO, |bounds| bounds.center());
+    let origin = |id: u64| page.group_of(id).map_or(Vec2::ZERO, center);
+    let mut entities = HashMap::new();
+    for stroke in page.strokes() {
+        let mut stroke = stroke.clone();
+        stroke.offset -= origin(stroke.id);
+        entities.insert(stroke.id, spawner.spawn(&stroke));
+    }
+    for text in page.texts() {
+        let mut text = text.clone();
+        text.position -= origin(text.id);
+        spawner.reserve_layer(text.layer);
+        entities.insert(text.id, spawn_text(&mut spawner.commands, &text));
+    }
+    for image in page.images() {
+        let mut image = image.clone();
+        image.position -= origin(image.id);
+        spawner.reserve_layer(image.layer);
+        match spawn_image(&mut spawner.commands, &mut images, &image) {
+            Ok(entity) => {
+                entities.insert(image.id, entity);
+            }
+            Err(err) => error!("failed to load image {}: {}", image.id, err),
+        }
+    }
+    for group in page.nested_groups() {
+        let translation = (center(group.id) - origin(group.id)).extend(0.);
+        let members: Vec<Entity> = group
+            .members
+            .iter()
+            .filter_map(|member| entities.get(member).copied())
+            .collect();
+        let entity = spawner
+            .commands
+            .spawn((
+                SpatialBundle::from_transform(Transform::from_translation(
+                    translation,
+                )),
+                Group,
+                ObjectId(group.id),
+            ))
+            .push_children(&members)
+            .id();
+        entities.insert(group.id, entity);
+    }
+}
+
+pub type LineEntities<'w, 's> =
+    Query<'w, 's, (Entity, &'static LineId, Has<Parent>)>;
+
+/// 按模型新增、删除或更新 ids 对应的线条实体；组内的线条会被移出组
+pub fn sync_stroke_entities(
+    page: &Page,
+    ids: impl IntoIterator<Item = u64>,
+    spawner: &mut LineSpawner,
+    lines: &LineEntities,
+) {
+    let entities: HashMap<u64, (Entity, bool)> = lines
+        .iter()
+        .map(|(entity, &LineId(id), grouped)| (id, (entity, grouped)))
+        .collect();
+    for id in ids {
+        match (page.get(id), entities.get(&id).copied()) {
+            (Some(stroke), Some((entity, grouped))) => {
+                let line = Line(stroke.points.clone());
+                let layer = Layer::Foreground(stroke.layer);
+                let mut entity = spawner.commands.entity(entity);
+                if grouped {
+                    entity.remove_parent();
+                }
+                entity.insert((
+                    Path::from(&line),
+                    line,
+                    Transform::from_translation(
+                        stroke.offset.extend(layer.z()),
+                    ),
+                    layer,
+                    LineSamples {
+                        times: stroke.times.clone(),
+                        pressures: stroke.pressures.clone(),
+                    },
+                ));
+            }
+            (Some(stroke), None) => {
+                spawner.spawn(stroke);
+            }
+            (None, Some((entity, _))) => {
+                spawner.commands.entity(entity).despawn_recursive();
+            }
+            (None, None) => {}
+        }
+    }
+}
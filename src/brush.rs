@@ -0,0 +1,401 @@
+// 1. 笔刷种类：粉笔、马克笔、荧光笔、铅笔、霓虹，每种笔刷有自己的材质与参数
+// 2. 工具栏上点击笔刷名称或执行 brush.next 切换笔刷，笔刷随线条一起保存
+use bevy::{
+    ecs::system::{EntityCommands, SystemParam},
+    prelude::*,
+    render::{
+        mesh::MeshVertexBufferLayout,
+        render_resource::{
+            AsBindGroup, BlendComponent, BlendFactor, BlendOperation,
+            BlendState, RenderPipelineDescriptor, ShaderRef,
+            SpecializedMeshPipelineError,
+        },
+    },
+    sprite::{Material2d, Material2dKey, Material2dPlugin},
+};
+
+pub use crate::board::BrushKind;
+
+use crate::{
+    background::BoardBackground,
+    chalk::{ChalkMaterial, GrainSpace},
+    frame::FrameMaterial,
+    keybindings::{
+        action_just_pressed, action_just_released, actions, RegisterAction,
+    },
+    states::RunMode,
+    toggle_component::{toggle_component, Toggle},
+};
+
+pub struct BrushPlugin;
+
+impl Plugin for BrushPlugin {
+    fn build(&self, app: &mut App) {
+        let tab_toggled = || {
+            action_just_pressed(actions::WIREFRAME)
+                .or_else(action_just_released(actions::WIREFRAME))
+        };
+        app.add_plugins((
+            Material2dPlugin::<ChalkMaterial>::default(),
+            Material2dPlugin::<MarkerMaterial>::default(),
+            Material2dPlugin::<HighlighterMaterial>::default(),
+            Material2dPlugin::<PencilMaterial>::default(),
+            Material2dPlugin::<NeonMaterial>::default(),
+        ))
+        .init_resource::<BrushConfig>()
+        .init_resource::<CurrentBrush>()
+        .register_action(actions::NEXT_BRUSH, "Brush: Next kind")
+        .add_systems(
+            Update,
+            (
+                toggle_component::<
+                    Handle<ChalkMaterial>,
+                    Handle<FrameMaterial>,
+                >,
+                toggle_component::<
+                    Handle<MarkerMaterial>,
+                    Handle<FrameMaterial>,
+                >,
+                toggle_component::<
+                    Handle<HighlighterMaterial>,
+                    Handle<FrameMaterial>,
+                >,
+                toggle_component::<
+                    Handle<PencilMaterial>,
+                    Handle<FrameMaterial>,
+                >,
+                toggle_component::<Handle<NeonMaterial>, Handle<FrameMaterial>>,
+            )
+                .run_if(tab_toggled()),
+        )
+        .add_systems(
+            Update,
+            (
+                cycle_brush.run_if(in_state(RunMode::Normal)),
+                next_brush.run_if(action_just_pressed(actions::NEXT_BRUSH)),
+                update_brush_button.run_if(resource_changed::<CurrentBrush>()),
+                update_highlighter_blend
+                    .run_if(resource_changed::<BoardBackground>()),
+            ),
+        );
+    }
+}
+
+/// 新画的线条使用的笔刷
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CurrentBrush(pub BrushKind);
+
+/// 各笔刷的参数，生成线条材质时读取
+#[derive(Resource, Debug, Clone)]
+pub struct BrushConfig {
+    /** 粉笔颗粒的缩放，越大颗粒越细 */
+    pub chalk_grain_scale: f32,
+    /** 粉笔颗粒的浓度，0 为实心 */
+    pub chalk_density: f32,
+    pub chalk_contrast: f32,
+    pub chalk_seed: f32,
+    pub chalk_grain_space: GrainSpace,
+    pub pencil_grain_scale: f32,
+    /** 铅笔笔迹中留下颜色的比例 */
+    pub pencil_density: f32,
+    pub highlighter_opacity: f32,
+    /** 霓虹的亮度倍数 */
+    pub neon_intensity: f32,
+}
+
+impl Default for BrushConfig {
+    fn default() -> Self {
+        BrushConfig {
+            chalk_grain_scale: 1.,
+            chalk_density: 1.,
+            chalk_contrast: 1.,
+            chalk_seed: 0.,
+            chalk_grain_space: GrainSpace::World,
+            pencil_grain_scale: 0.7,
+            pencil_density: 0.6,
+            highlighter_opacity: 0.4,
+            neon_intensity: 1.6,
+        }
+    }
+}
+
+/// 马克笔：实心的颜色
+#[derive(Asset, AsBindGroup, Default, Reflect, Debug, Clone)]
+#[reflect(Default, Debug)]
+pub struct MarkerMaterial {
+    #[uniform(0)]
+    pub material_color: Color,
+}
+
+impl Material2d for MarkerMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/marker.wgsl".into()
+    }
+}
+
+/// 荧光笔：浅色背景上与底下的颜色相乘；深色背景上相乘总是黑色，改为半透明叠加
+#[derive(Asset, AsBindGroup, Default, Reflect, Debug, Clone)]
+#[reflect(Default, Debug)]
+#[bind_group_data(HighlighterKey)]
+pub struct HighlighterMaterial {
+    #[uniform(0)]
+    pub material_color: Color,
+    #[uniform(1)]
+    pub opacity: f32,
+    pub multiply: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HighlighterKey {
+    multiply: bool,
+}
+
+impl From<&HighlighterMaterial> for HighlighterKey {
+    fn from(material: &HighlighterMaterial) -> Self {
+        HighlighterKey {
+            multiply: material.multiply,
+        }
+    }
+}
+
+impl Material2d for HighlighterMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/highlighter.wgsl".into()
+    }
+
+    fn specialize(
+        descriptor: &mut RenderPipelineDescriptor,
+        _layout: &MeshVertexBufferLayout,
+        key: Material2dKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        if !key.bind_group_data.multiply {
+            return Ok(());
+        }
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            fragment.shader_defs.push("HIGHLIGHTER_MULTIPLY".into());
+        }
+        // 目标颜色 = 目标颜色 * 输出颜色，透明度保持不变
+        set_blend(
+            descriptor,
+            BlendState {
+                color: BlendComponent {
+                    src_factor: BlendFactor::Dst,
+                    dst_factor: BlendFactor::Zero,
+                    operation: BlendOperation::Add,
+                },
+                alpha: BlendComponent {
+                    src_factor: BlendFactor::Zero,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                },
+            },
+        );
+        Ok(())
+    }
+}
+
+/// 铅笔：细密的颗粒
+#[derive(Asset, AsBindGroup, Default, Reflect, Debug, Clone)]
+#[reflect(Default, Debug)]
+pub struct PencilMaterial {
+    #[uniform(0)]
+    pub material_color: Color,
+    #[uniform(1)]
+    pub grain_scale: f32,
+    #[uniform(2)]
+    pub density: f32,
+}
+
+impl Material2d for PencilMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/pencil.wgsl".into()
+    }
+}
+
+/// 霓虹：加亮后叠加到底下的颜色上，交叠处更亮
+#[derive(Asset, AsBindGroup, Default, Reflect, Debug, Clone)]
+#[reflect(Default, Debug)]
+pub struct NeonMaterial {
+    #[uniform(0)]
+    pub material_color: Color,
+    #[uniform(1)]
+    pub intensity: f32,
+}
+
+impl Material2d for NeonMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/neon.wgsl".into()
+    }
+
+    fn specialize(
+        descriptor: &mut RenderPipelineDescriptor,
+        _layout: &MeshVertexBufferLayout,
+        _key: Material2dKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        let additive = BlendComponent {
+            src_factor: BlendFactor::SrcAlpha,
+            dst_factor: BlendFactor::One,
+            operation: BlendOperation::Add,
+        };
+        set_blend(
+            descriptor,
+            BlendState {
+                color: additive,
+                alpha: additive,
+            },
+        );
+        Ok(())
+    }
+}
+
+fn set_blend(descriptor: &mut RenderPipelineDescriptor, blend: BlendState) {
+    if let Some(fragment) = descriptor.fragment.as_mut() {
+        for target in fragment.targets.iter_mut().flatten() {
+            target.blend = Some(blend);
+        }
+    }
+}
+
+/// 按笔刷给线条加上材质，Tab 键在材质与线框之间切换
+#[derive(SystemParam)]
+pub struct BrushMaterials<'w> {
+    config: Res<'w, BrushConfig>,
+    background: Res<'w, BoardBackground>,
+    chalk: ResMut<'w, Assets<ChalkMaterial>>,
+    marker: ResMut<'w, Assets<MarkerMaterial>>,
+    highlighter: ResMut<'w, Assets<HighlighterMaterial>>,
+    pencil: ResMut<'w, Assets<PencilMaterial>>,
+    neon: ResMut<'w, Assets<NeonMaterial>>,
+    frame: ResMut<'w, Assets<FrameMaterial>>,
+}
+
+impl BrushMaterials<'_> {
+    pub fn insert(
+        &mut self,
+        entity: &mut EntityCommands,
+        brush: BrushKind,
+        color: Color,
+    ) {
+        let config = self.config.as_ref();
+        let frame = self.frame.add(FrameMaterial::default());
+        match brush {
+            BrushKind::Chalk => {
+                let material = self.chalk.add(
+                    ChalkMaterial {
+                        material_color: color,
+                        grain_scale: config.chalk_grain_scale,
+                        density: config.chalk_density,
+                        contrast: config.chalk_contrast,
+                        seed: config.chalk_seed,
+                        ..default()
+                    }
+                    .with_grain_space(config.chalk_grain_space),
+                );
+                insert_toggle(entity, material, frame);
+            }
+            BrushKind::Marker => {
+                let material = self.marker.add(MarkerMaterial {
+                    material_color: color,
+                });
+                insert_toggle(entity, material, frame);
+            }
+            BrushKind::Highlighter => {
+                let material = self.highlighter.add(HighlighterMaterial {
+                    material_color: color,
+                    opacity: config.highlighter_opacity,
+                    multiply: !self.background.is_dark(),
+                });
+                insert_toggle(entity, material, frame);
+            }
+            BrushKind::Pencil => {
+                let material = self.pencil.add(PencilMaterial {
+                    material_color: color,
+                    grain_scale: config.pencil_grain_scale,
+                    density: config.pencil_density,
+                });
+                insert_toggle(entity, material, frame);
+            }
+            BrushKind::Neon => {
+                let material = self.neon.add(NeonMaterial {
+                    material_color: color,
+                    intensity: config.neon_intensity,
+                });
+                insert_toggle(entity, material, frame);
+            }
+        }
+    }
+}
+
+fn insert_toggle<M: Material2d>(
+    entity: &mut EntityCommands,
+    material: Handle<M>,
+    frame: Handle<FrameMaterial>,
+) {
+    entity.insert((material.clone(), Toggle(material, frame)));
+}
+
+#[derive(Component)]
+struct BrushButton;
+
+/// 在工具栏中生成笔刷切换按钮
+pub fn spawn_brush_button(parent: &mut ChildBuilder) {
+    parent.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 14.,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            width: Val::Px(80.),
+            margin: UiRect::left(Val::Px(8.)),
+            ..default()
+        }),
+        Interaction::None,
+        BrushButton,
+    ));
+}
+
+fn cycle_brush(
+    button: Query<&Interaction, (Changed<Interaction>, With<BrushButton>)>,
+    mut current: ResMut<CurrentBrush>,
+) {
+    for interaction in button.iter() {
+        if *interaction == Interaction::Pressed {
+            current.0 = current.0.next();
+        }
+    }
+}
+
+fn next_brush(mut current: ResMut<CurrentBrush>) {
+    current.0 = current.0.next();
+}
+
+/// 背景在深浅之间切换时，已有的荧光笔线条换用对应的混合方式
+fn update_highlighter_blend(
+    background: Res<BoardBackground>,
+    mut materials: ResMut<Assets<HighlighterMaterial>>,
+) {
+    let multiply = !background.is_dark();
+    let stale: Vec<_> = materials
+        .iter()
+        .filter(|(_, material)| material.multiply != multiply)
+        .map(|(id, _)| id)
+        .collect();
+    for id in stale {
+        if let Some(material) = materials.get_mut(id) {
+            material.multiply = multiply;
+        }
+    }
+}
+
+fn update_brush_button(
+    current: Res<CurrentBrush>,
+    mut button: Query<&mut Text, With<BrushButton>>,
+) {
+    for mut text in button.iter_mut() {
+        text.sections[0].value = current.0.name().to_string();
+    }
+}
@@ -0,0 +1,314 @@
+// 1. 笔刷大小：工具栏滑块、[ ] 键调整、上下限、当前大小的显示
+// 2. 大小按世界坐标（随缩放变化）或屏幕像素（不随缩放变化）计算
+use bevy::{prelude::*, ui::RelativeCursorPosition};
+
+use crate::{
+    cursor::Cursor,
+    keybindings::{actions, ActionInput},
+    projection_2d_control::MainCamera,
+    states::{RunMode, ToolButton},
+    ui::TOOL_BUTTON_FOCUS,
+};
+
+pub struct BrushSizePlugin;
+
+impl Plugin for BrushSizePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BrushSizeConfig>()
+            .init_resource::<BrushScale>()
+            .add_systems(
+                Update,
+                (
+                    step_brush_size.run_if(
+                        in_state(ToolButton::Pen)
+                            .or_else(in_state(ToolButton::Eraser)),
+                    ),
+                    drag_brush_size_slider,
+                    toggle_brush_size_mode,
+                )
+                    .run_if(in_state(RunMode::Normal)),
+            )
+            .add_systems(
+                Update,
+                (
+                    update_brush_scale,
+                    update_brush_size_ui.run_if(
+                        resource_changed::<Cursor>()
+                            .or_else(resource_changed::<BrushSizeConfig>()),
+                    ),
+                ),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BrushSizeMode {
+    /** 世界坐标，缩放画布时笔刷跟着缩放 */
+    #[default]
+    World,
+    /** 屏幕像素，缩放画布时笔刷在屏幕上大小不变 */
+    Screen,
+}
+
+#[derive(Resource)]
+pub struct BrushSizeConfig {
+    pub min: f32,
+    pub max: f32,
+    /** 按住 [ ] 时每秒变化的大小 */
+    pub step_per_second: f32,
+    pub mode: BrushSizeMode,
+}
+
+impl Default for BrushSizeConfig {
+    fn default() -> Self {
+        BrushSizeConfig {
+            min: 1.,
+            max: 128.,
+            step_per_second: 30.,
+            mode: BrushSizeMode::World,
+        }
+    }
+}
+
+/// 笔刷大小换算到世界坐标的倍数
+#[derive(Resource)]
+pub struct BrushScale(pub f32);
+
+impl Default for BrushScale {
+    fn default() -> Self {
+        BrushScale(1.)
+    }
+}
+
+#[derive(Component)]
+struct BrushSizeSlider;
+
+#[derive(Component)]
+struct BrushSizeSliderFill;
+
+#[derive(Component)]
+struct BrushSizeLabel;
+
+#[derive(Component)]
+struct BrushSizeModeLabel;
+
+const SLIDER_WIDTH: f32 = 96.;
+
+/// 在工具栏中生成滑块、大小显示与模式切换
+pub fn spawn_brush_size_controls(parent: &mut ChildBuilder) {
+    let text_style = TextStyle {
+        font_size: 14.,
+        color: Color::WHITE,
+        ..default()
+    };
+    parent
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Px(SLIDER_WIDTH),
+                    padding: UiRect::vertical(Val::Px(8.)),
+                    margin: UiRect::left(Val::Auto),
+                    ..default()
+                },
+                ..default()
+            },
+            Interaction::None,
+            RelativeCursorPosition::default(),
+            BrushSizeSlider,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.),
+                        height: Val::Px(4.),
+                        ..default()
+                    },
+                    background_color: Color::rgb(0.3, 0.3, 0.34).into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(0.),
+                                height: Val::Percent(100.),
+                                ..default()
+                            },
+                            background_color: TOOL_BUTTON_FOCUS.into(),
+                            ..default()
+                        },
+                        BrushSizeSliderFill,
+                    ));
+                });
+        });
+    parent.spawn((
+        TextBundle {
+            text: Text::from_section("", text_style.clone()),
+            style: Style {
+                width: Val::Px(40.),
+                margin: UiRect::left(Val::Px(8.)),
+                ..default()
+            },
+            ..default()
+        },
+        BrushSizeLabel,
+    ));
+    parent.spawn((
+        TextBundle::from_section("", text_style).with_style(Style {
+            margin: UiRect::horizontal(Val::Px(8.)),
+            ..default()
+        }),
+        Interaction::None,
+        BrushSizeModeLabel,
+    ));
+}
+
+/// 同时修改当前画笔与工具栏按钮上的画笔，切换工具后大小不会被重置
+fn set_brush_size(
+    size: f32,
+    cursor: &mut Cursor,
+    tool_cursors: &mut Query<(&ToolButton, &mut Cursor)>,
+    tool: &ToolButton,
+) {
+    // 不是画笔类工具时调整画笔
+    let target = match cursor {
+        Cursor::Touch(_) => tool.clone(),
+        Cursor::Default => ToolButton::Pen,
+    };
+    let buttons = tool_cursors
+        .iter_mut()
+        .filter(|(tool, _)| **tool == target)
+        .map(|(_, cursor)| cursor.into_inner());
+    for cursor in buttons.chain(std::iter::once(cursor)) {
+        if let Cursor::Touch(touch_cursor) = cursor {
+            touch_cursor.size = size;
+        }
+    }
+}
+
+/// 当前工具不是画笔类工具时显示画笔的大小
+fn current_size<'a>(
+    cursor: &Cursor,
+    mut tool_cursors: impl Iterator<Item = (&'a ToolButton, &'a Cursor)>,
+) -> Option<f32> {
+    let cursor = match cursor {
+        Cursor::Touch(_) => cursor,
+        Cursor::Default => tool_cursors
+            .find(|(tool, _)| **tool == ToolButton::Pen)
+            .map(|(_, cursor)| cursor)?,
+    };
+    match cursor {
+        Cursor::Touch(touch_cursor) => Some(touch_cursor.size),
+        Cursor::Default => None,
+    }
+}
+
+fn step_brush_size(
+    action_input: ActionInput,
+    time: Res<Time<Real>>,
+    config: Res<BrushSizeConfig>,
+    tool: Res<State<ToolButton>>,
+    mut cursor: ResMut<Cursor>,
+    mut tool_cursors: Query<(&ToolButton, &mut Cursor)>,
+) {
+    let mut delta = 0.;
+    for (action, sign) in
+        [(actions::BRUSH_SMALLER, -1.), (actions::BRUSH_LARGER, 1.)]
+    {
+        // 从命令面板触发时相当于按住 0.25 秒
+        if action_input.triggered(action) {
+            delta += sign * config.step_per_second * 0.25;
+        } else if action_input.pressed(action) {
+            delta += sign * config.step_per_second * time.delta_seconds();
+        }
+    }
+    if delta == 0. {
+        return;
+    }
+    let Some(size) = current_size(&cursor, tool_cursors.iter()) else {
+        return;
+    };
+    let size = (size + delta).clamp(config.min, config.max);
+    set_brush_size(size, &mut cursor, &mut tool_cursors, tool.get());
+}
+
+fn drag_brush_size_slider(
+    slider: Query<
+        (&Interaction, &RelativeCursorPosition),
+        With<BrushSizeSlider>,
+    >,
+    config: Res<BrushSizeConfig>,
+    tool: Res<State<ToolButton>>,
+    mut cursor: ResMut<Cursor>,
+    mut tool_cursors: Query<(&ToolButton, &mut Cursor)>,
+) {
+    for (interaction, position) in slider.iter() {
+        if let (Interaction::Pressed, Some(normalized)) =
+            (interaction, position.normalized)
+        {
+            let t = normalized.x.clamp(0., 1.);
+            let size = config.min + t * (config.max - config.min);
+            set_brush_size(size, &mut cursor, &mut tool_cursors, tool.get());
+        }
+    }
+}
+
+fn toggle_brush_size_mode(
+    label: Query<
+        &Interaction,
+        (Changed<Interaction>, With<BrushSizeModeLabel>),
+    >,
+    mut config: ResMut<BrushSizeConfig>,
+) {
+    for interaction in label.iter() {
+        if *interaction == Interaction::Pressed {
+            config.mode = match config.mode {
+                BrushSizeMode::World => BrushSizeMode::Screen,
+                BrushSizeMode::Screen => BrushSizeMode::World,
+            };
+        }
+    }
+}
+
+fn update_brush_scale(
+    config: Res<BrushSizeConfig>,
+    q_proj: Query<&OrthographicProjection, With<MainCamera>>,
+    mut brush_scale: ResMut<BrushScale>,
+) {
+    let scale = match config.mode {
+        BrushSizeMode::World => 1.,
+        BrushSizeMode::Screen => q_proj.single().scale,
+    };
+    if brush_scale.0 != scale {
+        brush_scale.0 = scale;
+    }
+}
+
+fn update_brush_size_ui(
+    config: Res<BrushSizeConfig>,
+    cursor: Res<Cursor>,
+    tool_cursors: Query<(&ToolButton, &Cursor)>,
+    mut fill: Query<&mut Style, With<BrushSizeSliderFill>>,
+    mut label: Query<
+        &mut Text,
+        (With<BrushSizeLabel>, Without<BrushSizeModeLabel>),
+    >,
+    mut mode_label: Query<&mut Text, With<BrushSizeModeLabel>>,
+) {
+    if let Some(size) = current_size(&cursor, tool_cursors.iter()) {
+        let t = (size - config.min) / (config.max - config.min);
+        for mut style in fill.iter_mut() {
+            style.width = Val::Percent(t.clamp(0., 1.) * 100.);
+        }
+        for mut text in label.iter_mut() {
+            text.sections[0].value = format!("{:.1}", size);
+        }
+    }
+    for mut text in mode_label.iter_mut() {
+        text.sections[0].value = match config.mode {
+            BrushSizeMode::World => "world".to_string(),
+            BrushSizeMode::Screen => "screen".to_string(),
+        };
+    }
+}
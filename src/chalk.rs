@@ -2,14 +2,54 @@ use bevy::prelude::*;
 use bevy::render::render_resource::{AsBindGroup, ShaderRef};
 use bevy::sprite::Material2d;
 
+/// 粉笔颗粒的采样坐标
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GrainSpace {
+    /** 世界坐标，拖动线条时颗粒不跟着移动 */
+    #[default]
+    World,
+    /** 线条自身的坐标，颗粒随线条一起移动 */
+    Stroke,
+}
+
+/// 颗粒的频率按相机缩放换算到屏幕像素，缩放画布时颗粒在屏幕上的密度不变
 #[derive(Asset, AsBindGroup, Default, Reflect, Debug, Clone)]
 #[reflect(Default, Debug)]
 pub struct ChalkMaterial {
     #[uniform(0)]
     pub material_color: Color,
+    /** 颗粒的缩放，越大颗粒越细 */
+    #[uniform(1)]
+    pub grain_scale: f32,
+    /** 颗粒的浓度，0 为实心 */
+    #[uniform(2)]
+    pub density: f32,
+    /** 颗粒明暗的对比度 */
+    #[uniform(3)]
+    pub contrast: f32,
+    /** 噪声的偏移，不同的种子得到不同的颗粒 */
+    #[uniform(4)]
+    pub seed: f32,
+    /** 0 为世界坐标，1 为线条坐标 */
+    #[uniform(5)]
+    pub grain_space: u32,
+}
+
+impl ChalkMaterial {
+    pub fn with_grain_space(mut self, space: GrainSpace) -> Self {
+        self.grain_space = match space {
+            GrainSpace::World => 0,
+            GrainSpace::Stroke => 1,
+        };
+        self
+    }
 }
 
 impl Material2d for ChalkMaterial {
+    fn vertex_shader() -> ShaderRef {
+        "shaders/chalk.wgsl".into()
+    }
+
     fn fragment_shader() -> ShaderRef {
         "shaders/chalk.wgsl".into()
     }
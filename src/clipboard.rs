@@ -0,0 +1,291 @@
+// 复制、剪切、粘贴、再制与删除选中的对象
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::Page,
+    board_image::{spawn_image, ImageData},
+    board_text::{spawn_text, TextData},
+    board_view::{BoardModel, BoardOpEvent, ObjectId},
+    cursor::WorldTouchCursor,
+    document::StrokeData,
+    draw::LineSpawner,
+    keybindings::{action_just_pressed, actions},
+    recording::{stroke_ops, BoardOp},
+    replay::ReplayState,
+    selected::{Locked, Selected},
+    text_input::is_ime_enabled,
+};
+
+pub struct ClipboardPlugin;
+
+impl Plugin for ClipboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BoardClipboard>().add_systems(
+            Update,
+            (
+                copy_selection.run_if(action_just_pressed(actions::COPY)),
+                (copy_selection, delete_selection)
+                    .chain()
+                    .run_if(action_just_pressed(actions::CUT)),
+                paste_clipboard.run_if(action_just_pressed(actions::PASTE)),
+                duplicate_selection
+                    .run_if(action_just_pressed(actions::DUPLICATE)),
+                delete_selection
+                    .run_if(action_just_pressed(actions::DELETE))
+                    .run_if(not(is_ime_enabled)),
+            )
+                .run_if(in_state(ReplayState::Off)),
+        );
+    }
+}
+
+/// 剪贴板后端，换成系统剪贴板即可在两个进程之间粘贴
+pub trait ClipboardBackend: Send + Sync {
+    fn get_text(&mut self) -> Option<String>;
+    fn set_text(&mut self, text: String);
+}
+
+/// 进程内剪贴板
+#[derive(Default)]
+pub struct MemoryClipboard(Option<String>);
+
+impl ClipboardBackend for MemoryClipboard {
+    fn get_text(&mut self) -> Option<String> {
+        self.0.clone()
+    }
+
+    fn set_text(&mut self, text: String) {
+        self.0 = Some(text);
+    }
+}
+
+pub struct OsClipboard(arboard::Clipboard);
+
+impl ClipboardBackend for OsClipboard {
+    fn get_text(&mut self) -> Option<String> {
+        self.0.get_text().ok()
+    }
+
+    fn set_text(&mut self, text: String) {
+        if let Err(err) = self.0.set_text(text) {
+            warn!("failed to write clipboard: {}", err);
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct BoardClipboard(pub Box<dyn ClipboardBackend>);
+
+impl Default for BoardClipboard {
+    /// 系统剪贴板不可用时退回进程内剪贴板
+    fn default() -> Self {
+        match arboard::Clipboard::new() {
+            Ok(clipboard) => BoardClipboard(Box::new(OsClipboard(clipboard))),
+            Err(err) => {
+                warn!("system clipboard unavailable: {}", err);
+                BoardClipboard(Box::<MemoryClipboard>::default())
+            }
+        }
+    }
+}
+
+const CLIPBOARD_FORMAT: &str = "lines/selection@1";
+
+/// 再制时相对原对象的偏移
+const DUPLICATE_OFFSET: Vec2 = Vec2::new(20., -20.);
+
+/// 剪贴板中的内容，与文档使用相同的对象格式
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct ClipboardPayload {
+    /** 用来识别剪贴板文本是否来自本程序 */
+    pub format: String,
+    #[serde(default)]
+    pub strokes: Vec<StrokeData>,
+    #[serde(default)]
+    pub texts: Vec<TextData>,
+    #[serde(default)]
+    pub images: Vec<ImageData>,
+}
+
+impl ClipboardPayload {
+    /// ids 中的组展开为成员，组本身不复制
+    pub fn from_selection(page: &Page, ids: &[u64]) -> Self {
+        let ids: Vec<u64> =
+            ids.iter().flat_map(|id| page.descendants(*id)).collect();
+        ClipboardPayload {
+            format: CLIPBOARD_FORMAT.to_string(),
+            strokes: ids
+                .iter()
+                .filter_map(|id| page.get(*id))
+                .cloned()
+                .collect(),
+            texts: ids
+                .iter()
+                .filter_map(|id| page.text(*id))
+                .cloned()
+                .collect(),
+            images: ids
+                .iter()
+                .filter_map(|id| page.image(*id))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    pub fn parse(text: &str) -> Option<Self> {
+        serde_json::from_str::<Self>(text)
+            .ok()
+            .filter(|payload| payload.format == CLIPBOARD_FORMAT)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strokes.is_empty()
+            && self.texts.is_empty()
+            && self.images.is_empty()
+    }
+
+    /// 所有对象锚点的包围盒
+    fn bounds(&self) -> Option<Rect> {
+        let stroke_points = self.strokes.iter().flat_map(|stroke| {
+            stroke
+                .points
+                .iter()
+                .map(move |point| *point + stroke.offset)
+        });
+        let text_points = self.texts.iter().map(|text| text.position);
+        let image_points = self.images.iter().map(|image| image.position);
+        stroke_points
+            .chain(text_points)
+            .chain(image_points)
+            .map(|point| Rect::from_corners(point, point))
+            .reduce(|a, b| a.union(b))
+    }
+
+    /// 以新的 id 与层级生成对象，返回生成的实体
+    fn spawn(
+        mut self,
+        delta: Vec2,
+        spawner: &mut LineSpawner,
+        images: &mut Assets<Image>,
+        board_ops: &mut EventWriter<BoardOpEvent>,
+    ) -> Vec<Entity> {
+        let mut entities = vec![];
+        for stroke in self.strokes.iter_mut() {
+            (stroke.id, stroke.layer) = spawner.next();
+            stroke.offset += delta;
+            entities.push(spawner.spawn(stroke));
+            board_ops
+                .send_batch(stroke_ops(stroke).into_iter().map(BoardOpEvent));
+        }
+        for mut text in self.texts {
+            (text.id, text.layer) = spawner.next();
+            text.position += delta;
+            entities.push(spawn_text(&mut spawner.commands, &text));
+            board_ops.send(BoardOpEvent(BoardOp::AddText { text }));
+        }
+        for mut image in self.images {
+            (image.id, image.layer) = spawner.next();
+            image.position += delta;
+            match spawn_image(&mut spawner.commands, images, &image) {
+                Ok(entity) => {
+                    entities.push(entity);
+                    board_ops.send(BoardOpEvent(BoardOp::AddImage { image }));
+                }
+                Err(err) => error!("failed to paste image: {}", err),
+            }
+        }
+        entities
+    }
+}
+
+/// 选中实体对应的对象 id
+fn selected_ids(selected: &Selected, ids: &Query<&ObjectId>) -> Vec<u64> {
+    selected
+        .0
+        .iter()
+        .filter_map(|entity| ids.get(*entity).ok())
+        .map(|&ObjectId(id)| id)
+        .collect()
+}
+
+fn copy_selection(
+    model: Res<BoardModel>,
+    ids: Query<&ObjectId>,
+    selected: Res<Selected>,
+    mut clipboard: ResMut<BoardClipboard>,
+) {
+    let payload = ClipboardPayload::from_selection(
+        model.0.page(),
+        &selected_ids(&selected, &ids),
+    );
+    if payload.is_empty() {
+        return;
+    }
+    match serde_json::to_string(&payload) {
+        Ok(text) => clipboard.0.set_text(text),
+        Err(err) => error!("failed to copy selection: {}", err),
+    }
+}
+
+fn paste_clipboard(
+    mut clipboard: ResMut<BoardClipboard>,
+    world_touch_cursor: Res<WorldTouchCursor>,
+    mut spawner: LineSpawner,
+    mut images: ResMut<Assets<Image>>,
+    mut board_ops: EventWriter<BoardOpEvent>,
+    mut selected: ResMut<Selected>,
+) {
+    let Some(payload) = clipboard
+        .0
+        .get_text()
+        .and_then(|text| ClipboardPayload::parse(&text))
+    else {
+        return;
+    };
+    // 粘贴到指针处
+    let Some(bounds) = payload.bounds() else {
+        return;
+    };
+    let delta = world_touch_cursor.0 - bounds.center();
+    selected.0 =
+        payload.spawn(delta, &mut spawner, &mut images, &mut board_ops);
+}
+
+fn duplicate_selection(
+    model: Res<BoardModel>,
+    ids: Query<&ObjectId>,
+    mut spawner: LineSpawner,
+    mut images: ResMut<Assets<Image>>,
+    mut board_ops: EventWriter<BoardOpEvent>,
+    mut selected: ResMut<Selected>,
+) {
+    let payload = ClipboardPayload::from_selection(
+        model.0.page(),
+        &selected_ids(&selected, &ids),
+    );
+    if payload.is_empty() {
+        return;
+    }
+    selected.0 = payload.spawn(
+        DUPLICATE_OFFSET,
+        &mut spawner,
+        &mut images,
+        &mut board_ops,
+    );
+}
+
+fn delete_selection(
+    mut commands: Commands,
+    mut selected: ResMut<Selected>,
+    objects: Query<&ObjectId, Without<Locked>>,
+    mut board_ops: EventWriter<BoardOpEvent>,
+) {
+    // 删除组时模型一并删除成员
+    for entity in selected.0.drain(..) {
+        if let Ok(&ObjectId(id)) = objects.get(entity) {
+            commands.entity(entity).despawn_recursive();
+            board_ops.send(BoardOpEvent(BoardOp::Erase { id }));
+        }
+    }
+}
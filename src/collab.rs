@@ -0,0 +1,530 @@
+// 1. 局域网协作：连接 lines-server（src/bin/lines-server.rs），发送本地的 BoardOp 并应用其他人的操作
+// 2. 服务器给所有操作排定唯一的顺序。本地操作先生效；收到其他人的操作时，在按服务器顺序得到的白板上
+//    重放还未确认的本地操作，所有客户端最终得到相同的白板
+// 3. 后加入的客户端收到重建白板的操作序列；加入前本地画的对象换成服务器分配的站点内的 id，
+//    加到服务器白板的同序号页上一起上传
+// 4. 显示其他人的指针与名字。消息为一行一个 JSON；其他人修改线条时只更新这些线条，修改其他对象时重建整页
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpStream, ToSocketAddrs},
+    sync::{
+        mpsc::{self, Receiver, TryRecvError},
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use bevy::{prelude::*, sprite::Anchor, window::RequestRedraw};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    board::Board,
+    board_view::{
+        sync_stroke_entities, BoardModel, BoardOpEvent, LineEntities, ShownPage,
+    },
+    cursor::WorldTouchCursor,
+    draw::LineSpawner,
+    projection_2d_control::{BoardWindow, MainCamera},
+    recording::{BoardOp, Recording},
+    replay::ReplayState,
+};
+
+pub const DEFAULT_PORT: u16 = 7878;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub struct CollabPlugin;
+
+impl Plugin for CollabPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CollabConfig>()
+            .init_resource::<Collab>()
+            .add_systems(
+                PreUpdate,
+                (
+                    connect.run_if(resource_changed::<CollabConfig>()),
+                    receive_messages,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                (
+                    sync_remote_cursors,
+                    draw_remote_cursors.run_if(collab_connected),
+                    request_redraw.run_if(collab_connected),
+                ),
+            )
+            .add_systems(PostUpdate, (send_local_ops, send_cursor));
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct CollabConfig {
+    /** 服务器地址，例如 192.168.1.5:7878；None 表示不协作 */
+    pub server: Option<String>,
+    /** 显示在其他人指针旁的名字 */
+    pub name: String,
+    pub color: Color,
+}
+
+/// 默认从环境变量 LINES_SERVER、LINES_NAME 读取
+impl Default for CollabConfig {
+    fn default() -> Self {
+        CollabConfig {
+            server: std::env::var("LINES_SERVER").ok(),
+            name: std::env::var("LINES_NAME")
+                .or_else(|_| std::env::var("USER"))
+                .or_else(|_| std::env::var("USERNAME"))
+                .unwrap_or_else(|_| "guest".to_string()),
+            color: Color::hsl(rand::random::<f32>() * 360., 0.8, 0.6),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Peer {
+    pub client: u64,
+    pub name: String,
+    pub color: Color,
+    /** 指针的世界坐标，离开窗口时为 None */
+    #[serde(default)]
+    pub cursor: Option<Vec2>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Hello { name: String, color: Color },
+    Op { op: BoardOp },
+    Cursor { position: Option<Vec2> },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// 回复 Hello：客户端编号、线条 id 的站点、重建当前白板的操作与已经在线的人
+    Welcome {
+        client: u64,
+        site: u64,
+        ops: Vec<BoardOp>,
+        peers: Vec<Peer>,
+    },
+    Joined {
+        peer: Peer,
+    },
+    Left {
+        client: u64,
+    },
+    /// 按服务器顺序广播给所有人，包括发送者（作为确认）
+    Op {
+        client: u64,
+        op: BoardOp,
+    },
+    Cursor {
+        client: u64,
+        position: Option<Vec2>,
+    },
+}
+
+/// 写入一行 JSON
+pub fn write_message<T: Serialize>(
+    mut stream: &TcpStream,
+    message: &T,
+) -> io::Result<()> {
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    stream.write_all(&line)
+}
+
+/// 逐行读取 JSON 消息，直到连接断开或 handle 返回 false
+pub fn read_messages<T: DeserializeOwned>(
+    stream: TcpStream,
+    mut handle: impl FnMut(T) -> bool,
+) {
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        match serde_json::from_str(&line) {
+            Ok(message) => {
+                if !handle(message) {
+                    return;
+                }
+            }
+            Err(err) => warn!("invalid collaboration message: {}", err),
+        }
+    }
+}
+
+struct Connection {
+    stream: TcpStream,
+    messages: Mutex<Receiver<ServerMessage>>,
+}
+
+impl Connection {
+    fn open(server: &str) -> io::Result<Self> {
+        let address = server.to_socket_addrs()?.next().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "no address")
+        })?;
+        let stream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)?;
+        stream.set_nodelay(true)?;
+        let reader = stream.try_clone()?;
+        let (sender, messages) = mpsc::channel();
+        thread::spawn(move || {
+            read_messages(reader, |message| sender.send(message).is_ok())
+        });
+        Ok(Connection {
+            stream,
+            messages: Mutex::new(messages),
+        })
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Collab {
+    connection: Option<Connection>,
+    /** 服务器分配的编号，收到 Welcome 之后才有 */
+    pub client: Option<u64>,
+    pub peers: BTreeMap<u64, Peer>,
+    /** 按服务器顺序应用所有操作得到的白板 */
+    confirmed: Board,
+    /** 已经发出、服务器还未确认的本地操作 */
+    pending: VecDeque<BoardOp>,
+}
+
+impl Collab {
+    /// 未确认的本地操作数量
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    fn send(&mut self, message: &ClientMessage) {
+        let Some(connection) = &self.connection else {
+            return;
+        };
+        if let Err(err) = write_message(&connection.stream, message) {
+            warn!("lost connection to the collaboration server: {}", err);
+            self.disconnect();
+        }
+    }
+
+    fn disconnect(&mut self) {
+        if let Some(connection) = self.connection.take() {
+            let _ = connection.stream.shutdown(std::net::Shutdown::Both);
+        }
+        self.client = None;
+        self.peers.clear();
+        self.confirmed = Board::default();
+        self.pending.clear();
+    }
+
+    /// 已确认的白板加上未确认的本地操作，冲突的本地操作被丢弃
+    fn rebased(&self) -> Board {
+        let mut board = self.confirmed.clone();
+        for op in &self.pending {
+            let _ = board.apply(op);
+        }
+        board
+    }
+}
+
+pub fn collab_connected(collab: Res<Collab>) -> bool {
+    collab.client.is_some()
+}
+
+fn connect(config: Res<CollabConfig>, mut collab: ResMut<Collab>) {
+    collab.disconnect();
+    let Some(server) = &config.server else {
+        return;
+    };
+    match Connection::open(server) {
+        Ok(connection) => {
+            collab.connection = Some(connection);
+            collab.send(&ClientMessage::Hello {
+                name: config.name.clone(),
+                color: config.color,
+            });
+            info!("connected to {}", server);
+        }
+        Err(err) => error!("failed to connect to {}: {}", server, err),
+    }
+}
+
+fn receive_messages(
+    mut collab: ResMut<Collab>,
+    mut model: ResMut<BoardModel>,
+    mut recording: ResMut<Recording>,
+    mut spawner: LineSpawner,
+    lines: LineEntities,
+    mut shown: ResMut<ShownPage>,
+    replay: Res<State<ReplayState>>,
+    time: Res<Time<Real>>,
+) {
+    let Some(connection) = &collab.connection else {
+        return;
+    };
+    let mut messages = vec![];
+    let disconnected = {
+        let receiver = connection.messages.lock().unwrap();
+        loop {
+            match receiver.try_recv() {
+                Ok(message) => messages.push(message),
+                Err(TryRecvError::Empty) => break false,
+                Err(TryRecvError::Disconnected) => break true,
+            }
+        }
+    };
+
+    let mut dirty = BTreeSet::new();
+    let mut rebuild = false;
+    let mut changed = false;
+    for message in messages {
+        match message {
+            ServerMessage::Welcome {
+                client,
+                site,
+                ops,
+                peers,
+            } => {
+                collab.client = Some(client);
+                spawner.set_site(Some(site));
+                collab.peers =
+                    peers.into_iter().map(|peer| (peer.client, peer)).collect();
+                for op in &ops {
+                    let _ = collab.confirmed.apply(op);
+                }
+                // 加入前的 id 没有站点，可能与其他人的 id 冲突
+                for page in
+                    collab.confirmed.pages().iter().chain(model.0.pages())
+                {
+                    if let Some(id) = page.last_id() {
+                        spawner.reserve_id(id);
+                    }
+                }
+                let local = remap_ids(&model.0, &mut spawner);
+                for op in upload_ops(&local, &collab.confirmed) {
+                    collab.pending.push_back(op.clone());
+                    collab.send(&ClientMessage::Op { op });
+                }
+                rebuild = true;
+                changed = true;
+            }
+            ServerMessage::Joined { peer } => {
+                collab.peers.insert(peer.client, peer);
+            }
+            ServerMessage::Left { client } => {
+                collab.peers.remove(&client);
+            }
+            ServerMessage::Cursor { client, position } => {
+                if let Some(peer) = collab.peers.get_mut(&client) {
+                    peer.cursor = position;
+                }
+            }
+            ServerMessage::Op { client, op } => {
+                let erased = loose_stroke(&collab.confirmed, &op);
+                let _ = collab.confirmed.apply(&op);
+                if collab.client == Some(client) {
+                    collab.pending.pop_front();
+                    continue;
+                }
+                match op.id() {
+                    Some(id)
+                        if erased || loose_stroke(&collab.confirmed, &op) =>
+                    {
+                        dirty.insert(id);
+                    }
+                    _ => rebuild = true,
+                }
+                recording.push(time.elapsed_seconds(), op);
+                changed = true;
+            }
+        }
+    }
+
+    if changed {
+        model.0 = collab.rebased();
+        // 回放结束时会按模型重建整页
+        if rebuild {
+            shown.0 = None;
+        } else if *replay.get() == ReplayState::Off {
+            sync_stroke_entities(model.0.page(), dirty, &mut spawner, &lines);
+        }
+    }
+    if disconnected {
+        warn!("lost connection to the collaboration server");
+        collab.disconnect();
+    }
+}
+
+/// 所有对象换成新分配的 id，组成员随之更新
+fn remap_ids(board: &Board, spawner: &mut LineSpawner) -> Board {
+    let mut ids = BTreeMap::new();
+    let mut remap = |id: &mut u64| {
+        *id = *ids.entry(*id).or_insert_with(|| spawner.next_id());
+    };
+    let mut pages = board.to_data();
+    for page in &mut pages {
+        page.strokes
+            .iter_mut()
+            .for_each(|stroke| remap(&mut stroke.id));
+        page.texts.iter_mut().for_each(|text| remap(&mut text.id));
+        page.images
+            .iter_mut()
+            .for_each(|image| remap(&mut image.id));
+        for group in &mut page.groups {
+            remap(&mut group.id);
+            group.members.iter_mut().for_each(&mut remap);
+        }
+    }
+    Board::from_data(pages, board.current()).0
+}
+
+/// 把本地白板加到服务器的白板上：第 i 页的对象加到服务器的第 i 页，服务器没有的页新建；
+/// 服务器上没有对象时沿用本地的页名与当前页
+fn upload_ops(local: &Board, server: &Board) -> Vec<BoardOp> {
+    let mut ops = vec![];
+    for (index, page) in local.pages().iter().enumerate() {
+        match server.pages().get(index) {
+            None => ops.push(BoardOp::AddPage {
+                name: page.name.clone(),
+            }),
+            Some(shared) => {
+                if server.is_empty() && shared.name != page.name {
+                    ops.push(BoardOp::RenamePage {
+                        index,
+                        name: page.name.clone(),
+                    });
+                }
+                if page.is_empty() {
+                    continue;
+                }
+                ops.push(BoardOp::SelectPage { index });
+            }
+        }
+        ops.extend(page.to_ops());
+    }
+    let current = if server.is_empty() {
+        local.current()
+    } else {
+        server.current()
+    };
+    if !ops.is_empty() {
+        ops.push(BoardOp::SelectPage { index: current });
+    }
+    ops
+}
+
+/// 操作的对象是不在组中的线条
+fn loose_stroke(board: &Board, op: &BoardOp) -> bool {
+    op.id().is_some_and(|id| {
+        board.page().get(id).is_some() && board.page().group_of(id).is_none()
+    })
+}
+
+/// 收到 Welcome 之前的本地操作已经包含在上传的白板中，不再发送
+fn send_local_ops(
+    mut board_ops: EventReader<BoardOpEvent>,
+    mut collab: ResMut<Collab>,
+) {
+    if collab.client.is_none() {
+        board_ops.clear();
+        return;
+    }
+    for BoardOpEvent(op) in board_ops.read() {
+        collab.pending.push_back(op.clone());
+        collab.send(&ClientMessage::Op { op: op.clone() });
+    }
+}
+
+fn send_cursor(
+    mut collab: ResMut<Collab>,
+    world_touch_cursor: Res<WorldTouchCursor>,
+    board_window: BoardWindow,
+    mut last: Local<Option<Vec2>>,
+) {
+    if collab.client.is_none() {
+        *last = None;
+        return;
+    }
+    let position = board_window.cursor_position().map(|_| world_touch_cursor.0);
+    if position != *last {
+        *last = position;
+        collab.send(&ClientMessage::Cursor { position });
+    }
+}
+
+#[derive(Component)]
+pub struct RemoteCursor(pub u64);
+
+/// 名字标签的屏幕偏移
+const LABEL_OFFSET: Vec2 = Vec2::new(10., 10.);
+
+fn sync_remote_cursors(
+    mut commands: Commands,
+    collab: Res<Collab>,
+    mut labels: Query<(Entity, &RemoteCursor, &mut Transform, &mut Visibility)>,
+    q_proj: Query<&OrthographicProjection, With<MainCamera>>,
+) {
+    let scale = q_proj.get_single().map_or(1., |proj| proj.scale);
+    for (entity, &RemoteCursor(client), mut transform, mut visibility) in
+        labels.iter_mut()
+    {
+        let Some(peer) = collab.peers.get(&client) else {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        };
+        match peer.cursor {
+            Some(cursor) => {
+                *transform = Transform::from_translation(
+                    (cursor + LABEL_OFFSET * scale).extend(1.),
+                )
+                .with_scale(Vec3::splat(scale));
+                *visibility = Visibility::Inherited;
+            }
+            None => *visibility = Visibility::Hidden,
+        }
+    }
+    for peer in collab.peers.values() {
+        if labels.iter().any(|(_, cursor, ..)| cursor.0 == peer.client) {
+            continue;
+        }
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    peer.name.clone(),
+                    TextStyle {
+                        font_size: 16.,
+                        color: peer.color,
+                        ..default()
+                    },
+                ),
+                text_anchor: Anchor::BottomLeft,
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            RemoteCursor(peer.client),
+        ));
+    }
+}
+
+fn draw_remote_cursors(
+    mut gizmos: Gizmos,
+    collab: Res<Collab>,
+    q_proj: Query<&OrthographicProjection, With<MainCamera>>,
+) {
+    let scale = q_proj.get_single().map_or(1., |proj| proj.scale);
+    for peer in collab.peers.values() {
+        if let Some(cursor) = peer.cursor {
+            gizmos.circle_2d(cursor, 6. * scale, peer.color);
+            gizmos.circle_2d(cursor, 2. * scale, peer.color);
+        }
+    }
+}
+
+/// 其他人的操作随时会到达，协作时不等待输入事件
+fn request_redraw(mut redraw: EventWriter<RequestRedraw>) {
+    redraw.send(RequestRedraw);
+}
@@ -0,0 +1,210 @@
+// lines-server 的实现：每个连接一个读取线程，所有消息在同一把锁内排序后
+// 放入各客户端的发送队列，因此每个客户端收到的操作顺序相同；
+// 每个客户端有自己的发送线程，不读取的客户端在队列满时被断开，不会拖慢其他人；
+// 服务器保存一份白板，发给后加入的客户端
+use std::{
+    collections::BTreeMap,
+    io,
+    net::{Shutdown, TcpListener, TcpStream},
+    sync::{
+        mpsc::{self, SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use bevy::log::{info, warn};
+
+use crate::{
+    board::Board,
+    collab::{
+        read_messages, write_message, ClientMessage, Peer, ServerMessage,
+    },
+    draw::SITE_STRIDE,
+};
+
+/// 每个客户端最多积压的消息数
+const OUTGOING_LIMIT: usize = 4096;
+
+/// 阻塞地接受连接
+pub fn serve(listener: TcpListener) -> io::Result<()> {
+    let server = Arc::new(Mutex::new(Server::default()));
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                warn!("failed to accept a connection: {}", err);
+                continue;
+            }
+        };
+        let server = server.clone();
+        thread::spawn(move || handle_connection(server, stream));
+    }
+    Ok(())
+}
+
+fn handle_connection(server: Arc<Mutex<Server>>, stream: TcpStream) {
+    let _ = stream.set_nodelay(true);
+    let Ok(writer) = stream.try_clone() else {
+        return;
+    };
+    let Some(client) = server.lock().unwrap().add(writer) else {
+        warn!("refused a connection: every site is in use");
+        let _ = stream.shutdown(Shutdown::Both);
+        return;
+    };
+    read_messages(stream, |message: ClientMessage| {
+        server.lock().unwrap().handle(client, message);
+        true
+    });
+    server.lock().unwrap().remove(client);
+}
+
+struct Client {
+    stream: TcpStream,
+    /** 发送线程的队列 */
+    outgoing: SyncSender<ServerMessage>,
+    /** 线条 id 的站点，在线的客户端互不相同 */
+    site: u64,
+    /** 发送 Hello 之后才有 */
+    peer: Option<Peer>,
+}
+
+#[derive(Default)]
+struct Server {
+    board: Board,
+    clients: BTreeMap<u64, Client>,
+    /** 客户端编号从 1 开始，断开后不复用 */
+    last_client: u64,
+}
+
+impl Server {
+    /// 没有空闲的站点时返回 None
+    fn add(&mut self, stream: TcpStream) -> Option<u64> {
+        let site = (0..SITE_STRIDE).find(|site| {
+            !self.clients.values().any(|client| client.site == *site)
+        })?;
+        let writer = stream.try_clone().ok()?;
+        let (outgoing, messages) = mpsc::sync_channel(OUTGOING_LIMIT);
+        thread::spawn(move || {
+            for message in messages {
+                if write_message(&writer, &message).is_err() {
+                    let _ = writer.shutdown(Shutdown::Both);
+                    return;
+                }
+            }
+        });
+        self.last_client += 1;
+        let client = self.last_client;
+        self.clients.insert(
+            client,
+            Client {
+                stream,
+                outgoing,
+                site,
+                peer: None,
+            },
+        );
+        Some(client)
+    }
+
+    fn remove(&mut self, client: u64) {
+        let Some(removed) = self.clients.remove(&client) else {
+            return;
+        };
+        if let Some(peer) = removed.peer {
+            info!("{} left", peer.name);
+            self.broadcast(&ServerMessage::Left { client }, None);
+        }
+    }
+
+    fn handle(&mut self, client: u64, message: ClientMessage) {
+        match message {
+            ClientMessage::Hello { name, color } => {
+                let Some(site) = self.clients.get(&client).map(|c| c.site)
+                else {
+                    return;
+                };
+                info!("{} joined", name);
+                let peer = Peer {
+                    client,
+                    name,
+                    color,
+                    cursor: None,
+                };
+                let welcome = ServerMessage::Welcome {
+                    client,
+                    site,
+                    ops: self.board.to_ops(),
+                    peers: self
+                        .clients
+                        .values()
+                        .filter_map(|other| other.peer.clone())
+                        .collect(),
+                };
+                self.send(client, &welcome);
+                self.broadcast(
+                    &ServerMessage::Joined { peer: peer.clone() },
+                    Some(client),
+                );
+                if let Some(joined) = self.clients.get_mut(&client) {
+                    joined.peer = Some(peer);
+                }
+            }
+            ClientMessage::Op { op } => {
+                if !self.joined(client) {
+                    return;
+                }
+                // 被拒绝的操作同样广播，每个客户端都会以相同的方式拒绝它
+                let _ = self.board.apply(&op);
+                self.broadcast(&ServerMessage::Op { client, op }, None);
+            }
+            ClientMessage::Cursor { position } => {
+                let Some(peer) = self
+                    .clients
+                    .get_mut(&client)
+                    .and_then(|client| client.peer.as_mut())
+                else {
+                    return;
+                };
+                peer.cursor = position;
+                self.broadcast(
+                    &ServerMessage::Cursor { client, position },
+                    Some(client),
+                );
+            }
+        }
+    }
+
+    fn joined(&self, client: u64) -> bool {
+        self.clients
+            .get(&client)
+            .is_some_and(|client| client.peer.is_some())
+    }
+
+    /// 只放入发送队列；队列满时关闭连接，读取线程随后会移除这个客户端
+    fn send(&self, client: u64, message: &ServerMessage) {
+        let Some(Client {
+            stream, outgoing, ..
+        }) = self.clients.get(&client)
+        else {
+            return;
+        };
+        if let Err(TrySendError::Full(_)) = outgoing.try_send(message.clone()) {
+            warn!("client {} is not reading, disconnecting it", client);
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+
+    /// 发给所有已经加入的客户端
+    fn broadcast(&self, message: &ServerMessage, except: Option<u64>) {
+        for (client, _) in self
+            .clients
+            .iter()
+            .filter(|(client, _)| Some(**client) != except)
+            .filter(|(_, client)| client.peer.is_some())
+        {
+            self.send(*client, message);
+        }
+    }
+}
@@ -0,0 +1,350 @@
+// 1. Ctrl+K 或 Ctrl+Shift+P 打开命令面板，列出注册表中的所有动作
+// 2. 输入文字模糊匹配动作的标题与名称，上下键选择，回车执行，Esc 关闭
+// 3. 选中的动作通过 PendingActions 触发，与按下快捷键的效果相同
+use bevy::{
+    input::{keyboard::KeyboardInput, InputSystem},
+    prelude::*,
+};
+
+use crate::{
+    keybindings::{
+        action_just_pressed, actions, apply_pending_actions, ActionRegistry,
+        KeyBindings, PendingActions,
+    },
+    ui::{TOOL_BUTTON_BACKGROUND, TOOL_BUTTON_FOCUS},
+};
+
+pub struct CommandPalettePlugin;
+
+impl Plugin for CommandPalettePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CommandPalette>()
+            .add_systems(
+                PreUpdate,
+                capture_command_input
+                    .after(InputSystem)
+                    .before(apply_pending_actions)
+                    .run_if(|palette: Res<CommandPalette>| palette.open),
+            )
+            .add_systems(
+                Update,
+                (
+                    open_command_palette
+                        .run_if(action_just_pressed(actions::COMMAND_PALETTE)),
+                    click_command_row,
+                    sync_command_palette_ui
+                        .run_if(resource_changed::<CommandPalette>()),
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// 最多同时显示的动作数
+const MAX_ROWS: usize = 10;
+
+#[derive(Resource, Default, Debug)]
+pub struct CommandPalette {
+    pub open: bool,
+    pub query: String,
+    /** 匹配的动作，按得分从高到低排列 */
+    pub matches: Vec<String>,
+    pub selected: usize,
+}
+
+impl CommandPalette {
+    /// 重新匹配查询，按住才生效的动作与命令面板本身不出现在列表中
+    fn refresh(&mut self, registry: &ActionRegistry) {
+        let mut scored: Vec<(i32, &str)> = registry
+            .0
+            .iter()
+            .filter(|info| !info.hold && info.id != actions::COMMAND_PALETTE)
+            .filter_map(|info| {
+                let score = fuzzy_score(&self.query, &info.title)
+                    .max(fuzzy_score(&self.query, &info.id))?;
+                Some((score, info.id.as_str()))
+            })
+            .collect();
+        // 稳定排序，得分相同时保持注册顺序
+        scored.sort_by_key(|(score, _)| -score);
+        self.matches = scored.into_iter().map(|(_, id)| id.into()).collect();
+        self.selected = 0;
+    }
+
+    fn close(&mut self) {
+        self.open = false;
+        self.query.clear();
+        self.matches.clear();
+    }
+}
+
+/// 查询的字符按顺序出现在文本中即匹配，连续命中与单词开头得分更高
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut last: Option<usize> = None;
+    for c in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = (next..text.len()).find(|&i| text[i] == c)?;
+        score += 1;
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 8;
+        }
+        match last {
+            Some(last) if last + 1 == found => score += 5,
+            Some(last) => score -= (found - last - 1).min(3) as i32,
+            None => score -= found.min(5) as i32,
+        }
+        last = Some(found);
+        next = found + 1;
+    }
+    Some(score)
+}
+
+#[derive(Component)]
+struct CommandPaletteRoot;
+
+/// 鼠标在面板上时不在白板上绘制
+#[derive(Component)]
+pub struct CommandPalettePanel;
+
+#[derive(Component)]
+struct CommandQueryText;
+
+#[derive(Component)]
+struct CommandList;
+
+/// 列表中的一行，对应 matches 中的下标
+#[derive(Component)]
+struct CommandRow(usize);
+
+fn open_command_palette(
+    mut palette: ResMut<CommandPalette>,
+    registry: Res<ActionRegistry>,
+) {
+    palette.open = true;
+    palette.query.clear();
+    palette.refresh(&registry);
+}
+
+/// 面板打开时独占键盘，避免输入的文字触发其他快捷键
+fn capture_command_input(
+    mut palette: ResMut<CommandPalette>,
+    registry: Res<ActionRegistry>,
+    mut pending: ResMut<PendingActions>,
+    mut received_characters: EventReader<ReceivedCharacter>,
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut keyboard_events: ResMut<Events<KeyboardInput>>,
+) {
+    let mut changed = false;
+    for event in received_characters.read() {
+        if !event.char.is_control() {
+            palette.query.push(event.char);
+            changed = true;
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::Back) {
+        changed |= palette.query.pop().is_some();
+    }
+    if changed {
+        palette.refresh(&registry);
+    }
+    let count = palette.matches.len().max(1);
+    if keyboard_input.just_pressed(KeyCode::Down) {
+        palette.selected = (palette.selected + 1) % count;
+    }
+    if keyboard_input.just_pressed(KeyCode::Up) {
+        palette.selected = (palette.selected + count - 1) % count;
+    }
+    if keyboard_input.just_pressed(KeyCode::Return) {
+        if let Some(action) = palette.matches.get(palette.selected) {
+            pending.trigger(action);
+        }
+        palette.close();
+    }
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        palette.close();
+    }
+    keyboard_input.reset_all();
+    keyboard_events.clear();
+}
+
+fn click_command_row(
+    rows: Query<(&Interaction, &CommandRow), Changed<Interaction>>,
+    mut palette: ResMut<CommandPalette>,
+    mut pending: ResMut<PendingActions>,
+) {
+    for (interaction, CommandRow(index)) in rows.iter() {
+        match interaction {
+            Interaction::Pressed => {
+                if let Some(action) = palette.matches.get(*index) {
+                    pending.trigger(action);
+                }
+                palette.close();
+            }
+            // 重建列表后悬停状态会再次变化，选中的行不变时不触发重建
+            Interaction::Hovered if palette.selected != *index => {
+                palette.selected = *index;
+            }
+            Interaction::Hovered | Interaction::None => {}
+        }
+    }
+}
+
+fn sync_command_palette_ui(
+    mut commands: Commands,
+    palette: Res<CommandPalette>,
+    registry: Res<ActionRegistry>,
+    bindings: Res<KeyBindings>,
+    root: Query<Entity, With<CommandPaletteRoot>>,
+    list: Query<Entity, With<CommandList>>,
+    mut query_text: Query<&mut Text, With<CommandQueryText>>,
+) {
+    if !palette.open {
+        for entity in root.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+    let list = match list.get_single() {
+        Ok(list) => list,
+        Err(_) => spawn_command_palette(&mut commands),
+    };
+    let query = format!("> {}", palette.query);
+    for mut text in query_text.iter_mut() {
+        text.sections[0].value = query.clone();
+    }
+
+    // 选中的行始终在可见范围内
+    let start = (palette.selected + 1).saturating_sub(MAX_ROWS);
+    commands.entity(list).despawn_descendants();
+    commands.entity(list).with_children(|parent| {
+        if palette.matches.is_empty() {
+            parent.spawn(TextBundle::from_section(
+                "No matching commands",
+                TextStyle {
+                    font_size: 16.,
+                    color: Color::GRAY,
+                    ..default()
+                },
+            ));
+        }
+        for (index, action) in palette
+            .matches
+            .iter()
+            .enumerate()
+            .skip(start)
+            .take(MAX_ROWS)
+        {
+            let title = registry
+                .get(action)
+                .map_or(action.as_str(), |info| info.title.as_str());
+            let shortcut = bindings
+                .chords(action)
+                .first()
+                .map(ToString::to_string)
+                .unwrap_or_default();
+            let background = if index == palette.selected {
+                TOOL_BUTTON_FOCUS
+            } else {
+                Color::NONE
+            };
+            parent
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            justify_content: JustifyContent::SpaceBetween,
+                            padding: UiRect::axes(Val::Px(8.), Val::Px(4.)),
+                            ..default()
+                        },
+                        background_color: background.into(),
+                        ..default()
+                    },
+                    Interaction::None,
+                    CommandRow(index),
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        title,
+                        TextStyle {
+                            font_size: 16.,
+                            color: Color::WHITE,
+                            ..default()
+                        },
+                    ));
+                    parent.spawn(TextBundle::from_section(
+                        shortcut,
+                        TextStyle {
+                            font_size: 14.,
+                            color: Color::GRAY,
+                            ..default()
+                        },
+                    ));
+                });
+        }
+    });
+}
+
+/// 生成面板并返回列表节点
+fn spawn_command_palette(commands: &mut Commands) -> Entity {
+    let mut list = Entity::PLACEHOLDER;
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    top: Val::Px(96.),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                z_index: ZIndex::Global(1002),
+                ..default()
+            },
+            CommandPaletteRoot,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            width: Val::Px(440.),
+                            flex_direction: FlexDirection::Column,
+                            padding: UiRect::all(Val::Px(8.)),
+                            row_gap: Val::Px(8.),
+                            ..default()
+                        },
+                        background_color: TOOL_BUTTON_BACKGROUND.into(),
+                        ..default()
+                    },
+                    Interaction::None,
+                    CommandPalettePanel,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        TextBundle::from_section(
+                            "> ",
+                            TextStyle {
+                                font_size: 18.,
+                                color: Color::WHITE,
+                                ..default()
+                            },
+                        ),
+                        CommandQueryText,
+                    ));
+                    list = parent
+                        .spawn((
+                            NodeBundle {
+                                style: Style {
+                                    flex_direction: FlexDirection::Column,
+                                    ..default()
+                                },
+                                ..default()
+                            },
+                            CommandList,
+                        ))
+                        .id();
+                });
+        });
+    list
+}
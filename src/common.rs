@@ -1,19 +1,18 @@
 use bevy::ecs::query::ReadOnlyWorldQuery;
 use bevy::prelude::*;
-use bevy::window::PrimaryWindow;
 
-pub fn show_window_cursor(
-    mut q_windows: Query<&mut Window, With<PrimaryWindow>>,
-) {
-    let mut window = q_windows.single_mut();
-    window.cursor.visible = true;
+use crate::projection_2d_control::BoardWindow;
+
+pub fn show_window_cursor(mut board_window: BoardWindow) {
+    if let Some(mut window) = board_window.get_mut() {
+        window.cursor.visible = true;
+    }
 }
 
-pub fn hide_window_cursor(
-    mut q_windows: Query<&mut Window, With<PrimaryWindow>>,
-) {
-    let mut window = q_windows.single_mut();
-    window.cursor.visible = false;
+pub fn hide_window_cursor(mut board_window: BoardWindow) {
+    if let Some(mut window) = board_window.get_mut() {
+        window.cursor.visible = false;
+    }
 }
 
 pub fn clear_with<F: ReadOnlyWorldQuery>(
@@ -24,3 +23,11 @@ pub fn clear_with<F: ReadOnlyWorldQuery>(
         commands.entity(id).despawn();
     }
 }
+
+pub fn alt_pressed(keyboard_input: Res<Input<KeyCode>>) -> bool {
+    keyboard_input.any_pressed([KeyCode::AltLeft, KeyCode::AltRight])
+}
+
+pub fn ctrl_pressed(keyboard_input: Res<Input<KeyCode>>) -> bool {
+    keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+}
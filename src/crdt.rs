@@ -0,0 +1,665 @@
+// 1. 基于操作的 CRDT 白板：每个操作有唯一的 OpId（Lamport 时间 + 站点），因果在后的操作 OpId 一定更大
+// 2. 副本保存收到的全部操作，按 OpId 顺序依次应用得到线条；合并两个副本就是取操作的并集，
+//    与操作到达的顺序无关，所以各自离线编辑的副本合并后结果相同
+// 3. 规则：擦除、拆分后的线条不再接受操作；移动相加；样式与图层按 OpId 后写者胜；追加的点按 OpId 顺序拼接
+// 4. 线条 id 由创建它的 OpId 编码，不同站点生成的 id 不会冲突
+// 5. 应用层的 BoardOp 通过 edit_board_op 转成本站点的编辑，BoardOp 中的线条 id 记为 CRDT 线条的别名
+// 6. 只包含当前页的线条：文字、图片、组与页面的 BoardOp 不产生编辑
+// 7. 文档为每页保存一个副本的操作记录（CrdtLog）；没有经过 edit_board_op 的修改由 sync_page 对比模型补上
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ops::{
+        Bound::{Excluded, Unbounded},
+        Range,
+    },
+};
+
+use bevy::math::Vec2;
+use serde::{Deserialize, Serialize};
+
+use crate::board::{
+    validate_layer, validate_style, Board, BoardError, BoardOp, BrushKind,
+    Page, Rgba, StrokeData, Style,
+};
+
+/// 先比较 Lamport 时间，相同时比较站点
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+pub struct OpId {
+    /** 不超过 2^40 */
+    pub lamport: u64,
+    pub site: u16,
+}
+
+impl OpId {
+    /// 这个操作创建的第 part 条线条的 id
+    pub fn stroke_id(&self, part: u8) -> u64 {
+        (self.lamport << 24) | ((self.site as u64) << 8) | part as u64
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum OpKind {
+    /// 新线条的 id 为 id.stroke_id(0)
+    AddStroke {
+        color: Rgba,
+        width: f32,
+        brush: BrushKind,
+        layer: i8,
+    },
+    /// times、pressures 为空或与 points 一样长
+    AppendPoints {
+        stroke: u64,
+        points: Vec<Vec2>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        times: Vec<f32>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pressures: Vec<f32>,
+    },
+    Erase {
+        stroke: u64,
+    },
+    /// 保留原线条的几段点，第 i 段成为 id 为 id.stroke_id(i + 1) 的新线条
+    Split {
+        stroke: u64,
+        keep: Vec<Range<u32>>,
+    },
+    Move {
+        stroke: u64,
+        delta: Vec2,
+    },
+    Restyle {
+        stroke: u64,
+        color: Option<Rgba>,
+        width: Option<f32>,
+        brush: Option<BrushKind>,
+    },
+    Reorder {
+        stroke: u64,
+        layer: i8,
+    },
+}
+
+impl OpKind {
+    /// 操作的线条，AddStroke 没有
+    pub fn stroke(&self) -> Option<u64> {
+        match self {
+            OpKind::AddStroke { .. } => None,
+            OpKind::AppendPoints { stroke, .. }
+            | OpKind::Erase { stroke }
+            | OpKind::Split { stroke, .. }
+            | OpKind::Move { stroke, .. }
+            | OpKind::Restyle { stroke, .. }
+            | OpKind::Reorder { stroke, .. } => Some(*stroke),
+        }
+    }
+
+    fn validate(&self) -> Result<(), BoardError> {
+        match self {
+            OpKind::AddStroke { width, layer, .. } => {
+                validate_style(*width, *layer)
+            }
+            OpKind::AppendPoints {
+                stroke,
+                points,
+                times,
+                pressures,
+            } => {
+                if !points.iter().all(|point| point.is_finite()) {
+                    return Err(BoardError::InvalidPoint(*stroke));
+                }
+                let matches = |samples: &Vec<f32>| {
+                    samples.is_empty() || samples.len() == points.len()
+                };
+                if !matches(times) || !matches(pressures) {
+                    return Err(BoardError::Samples(*stroke));
+                }
+                Ok(())
+            }
+            OpKind::Erase { .. } => Ok(()),
+            OpKind::Split { stroke, keep } => {
+                if keep.len() > u8::MAX as usize
+                    || keep.iter().any(|range| range.start >= range.end)
+                {
+                    return Err(BoardError::InvalidPoint(*stroke));
+                }
+                Ok(())
+            }
+            OpKind::Move { stroke, delta } => {
+                if !delta.is_finite() {
+                    return Err(BoardError::InvalidPoint(*stroke));
+                }
+                Ok(())
+            }
+            OpKind::Restyle {
+                width: Some(width), ..
+            } => validate_style(*width, 0),
+            OpKind::Restyle { .. } => Ok(()),
+            OpKind::Reorder { layer, .. } => validate_layer(*layer),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CrdtOp {
+    pub id: OpId,
+    pub kind: OpKind,
+}
+
+/// 保存在文档中的副本：写入它的站点、版本、全部操作与线条 id 的别名
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct CrdtLog {
+    pub site: u16,
+    pub version: BTreeMap<u16, u64>,
+    pub ops: Vec<CrdtOp>,
+    #[serde(default)]
+    pub aliases: BTreeMap<u64, u64>,
+}
+
+/// 一个站点上的副本
+#[derive(Debug, Clone)]
+pub struct CrdtBoard {
+    site: u16,
+    /** 见过的最大 Lamport 时间 */
+    clock: u64,
+    ops: BTreeMap<OpId, OpKind>,
+    /** 已经应用到 strokes 的最后一个操作 */
+    applied: Option<OpId>,
+    strokes: BTreeMap<u64, StrokeData>,
+    /** BoardOp 中的线条 id 到 CRDT 线条 id，没有别名的 id 按 CRDT 线条 id 处理 */
+    aliases: BTreeMap<u64, u64>,
+}
+
+impl CrdtBoard {
+    pub fn new(site: u16) -> Self {
+        CrdtBoard {
+            site,
+            clock: 0,
+            ops: BTreeMap::new(),
+            applied: None,
+            strokes: BTreeMap::new(),
+            aliases: BTreeMap::new(),
+        }
+    }
+
+    /// 把已有的白板导入为本站点的操作，线条会得到新的 id，原来的 id 记为别名
+    pub fn from_page(site: u16, page: &Page) -> Self {
+        let mut crdt = CrdtBoard::new(site);
+        for stroke in page.ordered() {
+            if let Ok((id, _)) = crdt.import(stroke) {
+                crdt.aliases.insert(stroke.id, id);
+            }
+        }
+        crdt
+    }
+
+    /// 读取文档中的操作记录，之后的编辑使用 site
+    pub fn from_log(site: u16, log: &CrdtLog) -> Result<Self, BoardError> {
+        let mut crdt = CrdtBoard::new(site);
+        for op in &log.ops {
+            op.kind.validate()?;
+            crdt.insert(op.clone());
+        }
+        crdt.catch_up();
+        crdt.aliases = log.aliases.clone();
+        Ok(crdt)
+    }
+
+    pub fn to_log(&self) -> CrdtLog {
+        CrdtLog {
+            site: self.site,
+            version: self.version(),
+            ops: self.ops().collect(),
+            aliases: self.aliases.clone(),
+        }
+    }
+
+    /// 产生本站点的编辑，使副本的线条与 page 的线条相同；
+    /// 能表示为追加、移动、样式与图层修改的保留原线条，其他修改擦除后重新导入
+    pub fn sync_page(&mut self, page: &Page) -> Result<(), BoardError> {
+        let kept: BTreeSet<u64> = page
+            .strokes()
+            .map(|stroke| self.resolve(stroke.id))
+            .collect();
+        let erased: Vec<u64> = self
+            .strokes
+            .keys()
+            .filter(|id| !kept.contains(id))
+            .copied()
+            .collect();
+        for stroke in erased {
+            self.edit(OpKind::Erase { stroke })?;
+        }
+        for stroke in page.ordered() {
+            let id = self.resolve(stroke.id);
+            if let Some(current) = self.strokes.get(&id).cloned() {
+                for kind in changes(id, &current, stroke) {
+                    self.edit(kind)?;
+                }
+                if same_stroke(&self.strokes[&id], stroke) {
+                    continue;
+                }
+                self.edit(OpKind::Erase { stroke: id })?;
+            }
+            let (new, _) = self.import(stroke)?;
+            self.aliases.insert(stroke.id, new);
+        }
+        Ok(())
+    }
+
+    /// 以本站点的编辑加入一条完整的线条，返回它的新 id 与产生的操作
+    fn import(
+        &mut self,
+        stroke: &StrokeData,
+    ) -> Result<(u64, Vec<CrdtOp>), BoardError> {
+        let Style {
+            color,
+            width,
+            brush,
+        } = stroke.style();
+        let add = self.edit(OpKind::AddStroke {
+            color,
+            width,
+            brush,
+            layer: stroke.layer,
+        })?;
+        let id = add.id.stroke_id(0);
+        let mut ops = vec![add];
+        ops.push(self.edit(OpKind::AppendPoints {
+            stroke: id,
+            points: stroke.points.clone(),
+            times: stroke.times.clone(),
+            pressures: stroke.pressures.clone(),
+        })?);
+        if stroke.offset != Vec2::ZERO {
+            ops.push(self.edit(OpKind::Move {
+                stroke: id,
+                delta: stroke.offset,
+            })?);
+        }
+        Ok((id, ops))
+    }
+
+    /// 应用层操作中的线条 id 对应的 CRDT 线条 id
+    pub fn resolve(&self, id: u64) -> u64 {
+        self.aliases.get(&id).copied().unwrap_or(id)
+    }
+
+    /// 把应用层的操作转成本站点的编辑，返回需要发给其他副本的操作；
+    /// StrokeStart 的 id 成为新线条的别名，之后的操作可以继续使用它
+    pub fn edit_board_op(
+        &mut self,
+        op: &BoardOp,
+    ) -> Result<Vec<CrdtOp>, BoardError> {
+        let kind = match *op {
+            BoardOp::StrokeStart {
+                id,
+                color,
+                width,
+                layer,
+                brush,
+            } => {
+                let add = self.edit(OpKind::AddStroke {
+                    color,
+                    width,
+                    brush,
+                    layer,
+                })?;
+                self.aliases.insert(id, add.id.stroke_id(0));
+                return Ok(vec![add]);
+            }
+            BoardOp::StrokePoint {
+                id,
+                point,
+                time,
+                pressure,
+            } => OpKind::AppendPoints {
+                stroke: self.resolve(id),
+                points: vec![point],
+                times: time.into_iter().collect(),
+                pressures: pressure.into_iter().collect(),
+            },
+            BoardOp::StrokeEnd { .. } => return Ok(vec![]),
+            BoardOp::Erase { id } => OpKind::Erase {
+                stroke: self.resolve(id),
+            },
+            BoardOp::Move { id, delta } => OpKind::Move {
+                stroke: self.resolve(id),
+                delta,
+            },
+            BoardOp::Scale { id, center, factor } => {
+                // 没有缩放操作：擦除原线条，再加入缩放后的线条
+                let stroke = self.resolve(id);
+                let original = self
+                    .get(stroke)
+                    .cloned()
+                    .ok_or(BoardError::UnknownId(id))?;
+                let mut scaled = Board::from_strokes([original])?;
+                scaled.apply(&BoardOp::Scale {
+                    id: stroke,
+                    center,
+                    factor,
+                })?;
+                let mut ops = vec![self.edit(OpKind::Erase { stroke })?];
+                let (new, added) =
+                    self.import(scaled.page().get(stroke).unwrap())?;
+                ops.extend(added);
+                self.aliases.insert(id, new);
+                return Ok(ops);
+            }
+            BoardOp::Reorder { id, layer } => OpKind::Reorder {
+                stroke: self.resolve(id),
+                layer,
+            },
+            BoardOp::Clear => {
+                let strokes: Vec<u64> = self.strokes.keys().copied().collect();
+                return strokes
+                    .into_iter()
+                    .map(|stroke| self.edit(OpKind::Erase { stroke }))
+                    .collect();
+            }
+            BoardOp::AddText { .. }
+            | BoardOp::AddImage { .. }
+            | BoardOp::Lock { .. }
+            | BoardOp::Group { .. }
+            | BoardOp::Ungroup { .. }
+            | BoardOp::AddPage { .. }
+            | BoardOp::SelectPage { .. }
+            | BoardOp::RenamePage { .. } => return Ok(vec![]),
+        };
+        Ok(vec![self.edit(kind)?])
+    }
+
+    pub fn site(&self) -> u16 {
+        self.site
+    }
+
+    pub fn get(&self, id: u64) -> Option<&StrokeData> {
+        self.strokes.get(&id)
+    }
+
+    /// 按 id 顺序
+    pub fn strokes(&self) -> impl Iterator<Item = &StrokeData> {
+        self.strokes.values()
+    }
+
+    pub fn ops(&self) -> impl Iterator<Item = CrdtOp> + '_ {
+        self.ops.iter().map(|(id, kind)| CrdtOp {
+            id: *id,
+            kind: kind.clone(),
+        })
+    }
+
+    /// 每个站点已经收到的最大 Lamport 时间
+    pub fn version(&self) -> BTreeMap<u16, u64> {
+        let mut version = BTreeMap::new();
+        for id in self.ops.keys() {
+            let lamport = version.entry(id.site).or_insert(0);
+            *lamport = id.lamport.max(*lamport);
+        }
+        version
+    }
+
+    /// 对方缺少的操作，version 为对方的 version()；要求对方按顺序收到每个站点的操作
+    pub fn ops_since(&self, version: &BTreeMap<u16, u64>) -> Vec<CrdtOp> {
+        self.ops()
+            .filter(|op| {
+                version
+                    .get(&op.id.site)
+                    .map_or(true, |lamport| op.id.lamport > *lamport)
+            })
+            .collect()
+    }
+
+    /// 本站点的编辑，返回需要发给其他副本的操作
+    pub fn edit(&mut self, kind: OpKind) -> Result<CrdtOp, BoardError> {
+        kind.validate()?;
+        if let Some(stroke) = kind.stroke() {
+            if !self.strokes.contains_key(&stroke) {
+                return Err(BoardError::UnknownId(stroke));
+            }
+        }
+        self.clock += 1;
+        let op = CrdtOp {
+            id: OpId {
+                lamport: self.clock,
+                site: self.site,
+            },
+            kind,
+        };
+        self.receive(op.clone())?;
+        Ok(op)
+    }
+
+    /// 收到其他副本的操作，已经有的返回 false；操作可以按任意顺序到达
+    pub fn receive(&mut self, op: CrdtOp) -> Result<bool, BoardError> {
+        op.kind.validate()?;
+        let added = self.insert(op);
+        self.catch_up();
+        Ok(added)
+    }
+
+    /// 合并另一个副本的全部操作
+    pub fn merge(&mut self, other: &CrdtBoard) {
+        for op in other.ops() {
+            self.insert(op);
+        }
+        self.catch_up();
+    }
+
+    fn insert(&mut self, op: CrdtOp) -> bool {
+        if self.ops.contains_key(&op.id) {
+            return false;
+        }
+        self.clock = self.clock.max(op.id.lamport);
+        if self.applied.is_some_and(|applied| op.id < applied) {
+            // 比已应用的操作更早，需要从头重新应用
+            self.strokes.clear();
+            self.applied = None;
+        }
+        self.ops.insert(op.id, op.kind);
+        true
+    }
+
+    /// 按顺序应用 applied 之后的操作
+    fn catch_up(&mut self) {
+        let ids: Vec<OpId> = match self.applied {
+            Some(applied) => self
+                .ops
+                .range((Excluded(applied), Unbounded))
+                .map(|(id, _)| *id)
+                .collect(),
+            None => self.ops.keys().copied().collect(),
+        };
+        for id in ids {
+            self.apply(id);
+        }
+    }
+
+    /// 转成普通的白板模型，用于显示与导出
+    pub fn to_board(&self) -> Result<Board, BoardError> {
+        Board::from_strokes(self.strokes.values().cloned())
+    }
+
+    fn apply(&mut self, id: OpId) {
+        self.applied = Some(id);
+        let kind = &self.ops[&id];
+        if let OpKind::AddStroke {
+            color,
+            width,
+            brush,
+            layer,
+        } = kind
+        {
+            let stroke = id.stroke_id(0);
+            self.strokes.insert(
+                stroke,
+                StrokeData {
+                    id: stroke,
+                    color: *color,
+                    width: *width,
+                    brush: *brush,
+                    layer: *layer,
+                    ..Default::default()
+                },
+            );
+            return;
+        }
+        let Some(target) = kind.stroke() else {
+            return;
+        };
+        let Some(stroke) = self.strokes.get_mut(&target) else {
+            return;
+        };
+        match kind {
+            OpKind::AddStroke { .. } => {}
+            OpKind::AppendPoints {
+                points,
+                times,
+                pressures,
+                ..
+            } => {
+                // 任何一次追加缺少时间或压感时，整条线都不保留
+                let len = stroke.points.len();
+                for (samples, new) in [
+                    (&mut stroke.times, times),
+                    (&mut stroke.pressures, pressures),
+                ] {
+                    if samples.len() == len && new.len() == points.len() {
+                        samples.extend(new);
+                    } else {
+                        samples.clear();
+                    }
+                }
+                stroke.points.extend(points);
+            }
+            OpKind::Erase { .. } => {
+                self.strokes.remove(&target);
+            }
+            OpKind::Split { keep, .. } => {
+                let original = self.strokes.remove(&target).unwrap();
+                let len = original.points.len();
+                let slice = |samples: &Vec<f32>, range: &Range<usize>| {
+                    if samples.len() == len {
+                        samples[range.clone()].to_vec()
+                    } else {
+                        vec![]
+                    }
+                };
+                for (part, range) in keep.iter().enumerate() {
+                    let range =
+                        range.start as usize..(range.end as usize).min(len);
+                    if range.is_empty() {
+                        continue;
+                    }
+                    let id = id.stroke_id(part as u8 + 1);
+                    self.strokes.insert(
+                        id,
+                        StrokeData {
+                            id,
+                            points: original.points[range.clone()].to_vec(),
+                            times: slice(&original.times, &range),
+                            pressures: slice(&original.pressures, &range),
+                            ..original.clone()
+                        },
+                    );
+                }
+            }
+            OpKind::Move { delta, .. } => stroke.offset += *delta,
+            OpKind::Restyle {
+                color,
+                width,
+                brush,
+                ..
+            } => {
+                stroke.color = color.unwrap_or(stroke.color);
+                stroke.width = width.unwrap_or(stroke.width);
+                stroke.brush = brush.unwrap_or(stroke.brush);
+            }
+            OpKind::Reorder { layer, .. } => stroke.layer = *layer,
+        }
+    }
+}
+
+/// 移动相加的舍入误差
+const OFFSET_EPSILON: f32 = 1e-3;
+
+/// 把 current 改成 target 的操作，点只能在末尾追加
+fn changes(id: u64, current: &StrokeData, target: &StrokeData) -> Vec<OpKind> {
+    let mut kinds = vec![];
+    let len = current.points.len();
+    if target.points.len() > len && target.points.starts_with(&current.points) {
+        let suffix = |samples: &Vec<f32>| {
+            if samples.len() == target.points.len() {
+                samples[len..].to_vec()
+            } else {
+                vec![]
+            }
+        };
+        kinds.push(OpKind::AppendPoints {
+            stroke: id,
+            points: target.points[len..].to_vec(),
+            times: suffix(&target.times),
+            pressures: suffix(&target.pressures),
+        });
+    }
+    if !current.offset.abs_diff_eq(target.offset, OFFSET_EPSILON) {
+        kinds.push(OpKind::Move {
+            stroke: id,
+            delta: target.offset - current.offset,
+        });
+    }
+    if current.style() != target.style() {
+        kinds.push(OpKind::Restyle {
+            stroke: id,
+            color: (current.color != target.color).then_some(target.color),
+            width: (current.width != target.width).then_some(target.width),
+            brush: (current.brush != target.brush).then_some(target.brush),
+        });
+    }
+    if current.layer != target.layer {
+        kinds.push(OpKind::Reorder {
+            stroke: id,
+            layer: target.layer,
+        });
+    }
+    kinds
+}
+
+/// 除 id 外相同
+fn same_stroke(a: &StrokeData, b: &StrokeData) -> bool {
+    a.points == b.points
+        && a.times == b.times
+        && a.pressures == b.pressures
+        && a.style() == b.style()
+        && a.layer == b.layer
+        && a.offset.abs_diff_eq(b.offset, OFFSET_EPSILON)
+}
+
+/// 拆分时保留的点：去掉 remove 中的下标后剩下的连续段
+pub fn keep_ranges(len: usize, remove: &BTreeSet<usize>) -> Vec<Range<u32>> {
+    let mut keep = vec![];
+    let mut start = None;
+    for index in 0..=len {
+        let kept = index < len && !remove.contains(&index);
+        match (kept, start) {
+            (true, None) => start = Some(index),
+            (false, Some(begin)) => {
+                keep.push(begin as u32..index as u32);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    keep
+}
@@ -1,11 +1,13 @@
 use bevy::{
     prelude::*,
     render::render_resource::{AsBindGroup, ShaderRef},
-    window::PrimaryWindow,
 };
 
 use crate::{
-    common::clear_with, projection_2d_control::MainCamera, states::ToolButton,
+    brush_size::BrushScale,
+    common::clear_with,
+    projection_2d_control::{BoardWindow, MainCamera},
+    states::ToolButton,
 };
 
 pub struct TouchCursorPlugin;
@@ -29,7 +31,7 @@ impl Plugin for TouchCursorPlugin {
             .add_systems(Update, update_world_torch_cursor)
             .add_systems(
                 Update,
-                (update_touch_cursor, update_touch_cursor_size).run_if(
+                update_touch_cursor.run_if(
                     in_state(ToolButton::Pen)
                         .or_else(in_state(ToolButton::Eraser)),
                 ),
@@ -75,9 +77,10 @@ impl UiMaterial for TouchCursorUiMaterial {
 }
 
 fn update_touch_cursor(
-    mut q_windows: Query<&mut Window, With<PrimaryWindow>>,
+    board_window: BoardWindow,
     q_proj: Query<&OrthographicProjection, With<MainCamera>>,
     cursor: Res<Cursor>,
+    brush_scale: Res<BrushScale>,
     mut ui_materials: ResMut<Assets<TouchCursorUiMaterial>>,
     mut q_cursor: Query<(
         &mut Style,
@@ -85,15 +88,14 @@ fn update_touch_cursor(
         &mut Handle<TouchCursorUiMaterial>,
     )>,
 ) {
-    let window = q_windows.single_mut();
     if let (Some(cursor_position), Cursor::Touch(touch_cursor)) =
-        (window.cursor_position(), cursor.as_ref())
+        (board_window.cursor_position(), cursor.as_ref())
     {
         let (mut style, mut transform, mut material) = q_cursor.single_mut();
         style.left = Val::Px(cursor_position.x - touch_cursor.size / 2.0);
         style.top = Val::Px(cursor_position.y - touch_cursor.size / 2.0);
-        transform.scale.x = 1. / q_proj.single().scale;
-        transform.scale.y = 1. / q_proj.single().scale;
+        transform.scale.x = brush_scale.0 / q_proj.single().scale;
+        transform.scale.y = brush_scale.0 / q_proj.single().scale;
         style.width = Val::Px(touch_cursor.size);
         style.height = Val::Px(touch_cursor.size);
         *material = ui_materials.add(TouchCursorUiMaterial {
@@ -102,21 +104,6 @@ fn update_touch_cursor(
     }
 }
 
-fn update_touch_cursor_size(
-    mut cursor: ResMut<Cursor>,
-    keyboard_input: Res<Input<KeyCode>>,
-) {
-    if let Cursor::Touch(touch_cursor) = cursor.as_mut() {
-        if keyboard_input.pressed(KeyCode::BracketLeft) {
-            touch_cursor.size -= 0.5;
-        }
-
-        if keyboard_input.pressed(KeyCode::BracketRight) {
-            touch_cursor.size += 0.5;
-        }
-    }
-}
-
 #[derive(Component)]
 struct TouchCursorMark;
 
@@ -162,11 +149,17 @@ fn setup_touch_cursor(
 
 fn update_world_torch_cursor(
     camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    board_window: BoardWindow,
     mut world_touch_cursor: ResMut<WorldTouchCursor>,
     mut cursor_moved_events: EventReader<CursorMoved>,
 ) {
     let (camera, camera_transform) = camera_query.single();
-    for event in cursor_moved_events.read() {
+    let window = board_window.entity();
+    // 只跟随白板所在窗口中的指针
+    for event in cursor_moved_events
+        .read()
+        .filter(|event| Some(event.window) == window)
+    {
         if let Some(point) =
             camera.viewport_to_world_2d(camera_transform, event.position)
         {
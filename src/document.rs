@@ -0,0 +1,377 @@
+use std::{
+    collections::BTreeSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub use crate::board::StrokeData;
+
+use crate::{
+    background::BoardBackground,
+    board::{
+        Board, BoardError, GroupData, ImageData, Page, PageData, TextData,
+    },
+    board_view::{BoardCrdt, BoardModel, ShownPage},
+    crdt::{CrdtBoard, CrdtLog},
+    draw::{Line, LineId, LineSamples, LineStyle},
+    keybindings::{action_just_pressed, actions},
+    layer::Layer,
+    recording::{Recording, TimedOp},
+    replay::ReplayState,
+};
+
+pub struct DocumentPlugin;
+
+impl Plugin for DocumentPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DocumentPath>().add_systems(
+            Update,
+            (
+                save_document.run_if(action_just_pressed(actions::SAVE)),
+                open_document.run_if(action_just_pressed(actions::OPEN)),
+            )
+                .run_if(in_state(ReplayState::Off)),
+        );
+    }
+}
+
+#[derive(Resource)]
+pub struct DocumentPath(pub PathBuf);
+
+impl Default for DocumentPath {
+    fn default() -> Self {
+        DocumentPath(
+            std::env::args()
+                .nth(1)
+                .unwrap_or_else(|| "board.lines.json".to_string())
+                .into(),
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(from = "DocumentRepr")]
+pub struct Document {
+    pub pages: Vec<PageData>,
+    /** 每页线条的操作记录，合并分别编辑的副本时使用；旧文档没有 */
+    pub crdt: Vec<CrdtLog>,
+    pub current_page: usize,
+    pub recording: Vec<TimedOp>,
+    pub background: BoardBackground,
+}
+
+impl Default for Document {
+    fn default() -> Self {
+        Document::from_board(
+            &Board::default(),
+            &mut BoardCrdt::default(),
+            &Recording::default(),
+            &BoardBackground::default(),
+        )
+    }
+}
+
+/// 读取时兼容没有分页的旧版文档：顶层的线条、文字与图片作为第一页
+#[derive(Deserialize)]
+struct DocumentRepr {
+    #[serde(default)]
+    pages: Option<Vec<PageData>>,
+    #[serde(default)]
+    crdt: Vec<CrdtLog>,
+    #[serde(default)]
+    current_page: usize,
+    #[serde(default)]
+    strokes: Vec<StrokeData>,
+    #[serde(default)]
+    texts: Vec<TextData>,
+    #[serde(default)]
+    images: Vec<ImageData>,
+    #[serde(default)]
+    recording: Vec<TimedOp>,
+    #[serde(default)]
+    background: BoardBackground,
+}
+
+impl From<DocumentRepr> for Document {
+    fn from(repr: DocumentRepr) -> Self {
+        let pages = repr.pages.unwrap_or_else(|| {
+            // 旧版的文字与图片没有 id，接在线条之后分配
+            let mut next_id =
+                repr.strokes.iter().map(|stroke| stroke.id + 1).max();
+            let mut next_id = move || {
+                let id = next_id.unwrap_or_default();
+                next_id = Some(id + 1);
+                id
+            };
+            let mut texts = repr.texts;
+            for text in texts.iter_mut() {
+                text.id = next_id();
+            }
+            let mut images = repr.images;
+            for image in images.iter_mut() {
+                image.id = next_id();
+            }
+            vec![PageData {
+                name: Board::default().page().name.clone(),
+                strokes: repr.strokes,
+                texts,
+                images,
+                groups: vec![],
+            }]
+        });
+        Document {
+            pages,
+            crdt: repr.crdt,
+            current_page: repr.current_page,
+            recording: repr.recording,
+            background: repr.background,
+        }
+    }
+}
+
+impl Document {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// 先写临时文件再改名，避免写到一半崩溃时损坏原文件
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, serde_json::to_string(self)?)?;
+        fs::rename(tmp_path, path)
+    }
+
+    /// 合并另一个保存的白板：同序号的页两两合并，线条取两边操作记录的并集，合并后线条使用 CRDT 线条 id；
+    /// 文字与图片按 id 取并集，组保留自己的，多出的页直接加入，录制与背景保留自己的
+    pub fn merge(self, other: Document) -> Result<Document, BoardError> {
+        let mut pages = vec![];
+        let mut crdt = vec![];
+        for index in 0..self.pages.len().max(other.pages.len()) {
+            let site = self.crdt.get(index).map_or(0, |log| log.site);
+            let (page, log) =
+                match (self.pages.get(index), other.pages.get(index)) {
+                    (Some(page), Some(theirs)) => {
+                        let mine = self.page_crdt(index, site)?;
+                        // 合并时补上的编辑不能与自己的 OpId 冲突
+                        let theirs_crdt =
+                            other.page_crdt(index, site.wrapping_add(1))?;
+                        merge_page(page, theirs, mine, &theirs_crdt)?
+                    }
+                    (Some(page), None) => {
+                        (page.clone(), self.page_crdt(index, site)?.to_log())
+                    }
+                    (None, Some(page)) => {
+                        let site =
+                            other.crdt.get(index).map_or(1, |log| log.site);
+                        (page.clone(), other.page_crdt(index, site)?.to_log())
+                    }
+                    (None, None) => unreachable!(),
+                };
+            pages.push(page);
+            crdt.push(log);
+        }
+        Ok(Document {
+            pages,
+            crdt,
+            current_page: self.current_page,
+            recording: self.recording,
+            background: self.background,
+        })
+    }
+
+    /// 第 index 页线条的副本：读取操作记录，再补上记录之外的修改，补上的编辑使用 site
+    fn page_crdt(
+        &self,
+        index: usize,
+        site: u16,
+    ) -> Result<CrdtBoard, BoardError> {
+        let mut crdt = match self.crdt.get(index) {
+            Some(log) => CrdtBoard::from_log(site, log)?,
+            None => CrdtBoard::new(site),
+        };
+        let (page, _) = Page::from_data(self.pages[index].clone());
+        crdt.sync_page(&page)?;
+        Ok(crdt)
+    }
+
+    pub fn from_board(
+        model: &Board,
+        crdt: &mut BoardCrdt,
+        recording: &Recording,
+        background: &BoardBackground,
+    ) -> Self {
+        Document {
+            pages: model.to_data(),
+            crdt: crdt.sync(model),
+            current_page: model.current(),
+            recording: recording.ops.clone(),
+            background: background.clone(),
+        }
+    }
+
+    /// 用文档内容替换当前白板，不满足模型约束的对象会被跳过；实体在下一次重建视图时生成
+    pub fn load_into_board(
+        self,
+        commands: &mut Commands,
+        recording: &mut Recording,
+    ) {
+        let (model, errors) = Board::from_data(self.pages, self.current_page);
+        for err in errors {
+            warn!("skipped object: {}", err);
+        }
+        commands.insert_resource(BoardModel(model));
+        commands.insert_resource(BoardCrdt::load(rand::random(), &self.crdt));
+        commands.insert_resource(ShownPage(None));
+        recording.replace(self.recording);
+        commands.insert_resource(self.background);
+    }
+}
+
+/// 合并同序号的两页，mine 与 theirs 为两边线条的副本
+fn merge_page(
+    page: &PageData,
+    theirs: &PageData,
+    mut mine: CrdtBoard,
+    theirs_crdt: &CrdtBoard,
+) -> Result<(PageData, CrdtLog), BoardError> {
+    // 组成员中的线条换成 CRDT 线条 id
+    let groups: Vec<GroupData> = page
+        .groups
+        .iter()
+        .map(|group| GroupData {
+            id: group.id,
+            members: group.members.iter().map(|id| mine.resolve(*id)).collect(),
+        })
+        .collect();
+    mine.merge(theirs_crdt);
+    let strokes = mine.to_board()?.page().to_strokes();
+    let mut ids: BTreeSet<u64> = strokes
+        .iter()
+        .map(|stroke| stroke.id)
+        .chain(page.texts.iter().map(|text| text.id))
+        .chain(page.images.iter().map(|image| image.id))
+        .chain(page.groups.iter().map(|group| group.id))
+        .collect();
+    let mut texts = page.texts.clone();
+    union(&mut texts, &theirs.texts, &mut ids, |text| &mut text.id);
+    let mut images = page.images.clone();
+    union(&mut images, &theirs.images, &mut ids, |image| &mut image.id);
+    // 合并后已经不存在的成员
+    let groups = groups
+        .into_iter()
+        .filter_map(|mut group| {
+            group.members.retain(|id| ids.contains(id));
+            (!group.members.is_empty()).then_some(group)
+        })
+        .collect();
+
+    // 线条已经使用 CRDT 线条 id，不再需要别名
+    let mut log = mine.to_log();
+    log.aliases.clear();
+    Ok((
+        PageData {
+            name: page.name.clone(),
+            strokes,
+            texts,
+            images,
+            groups,
+        },
+        log,
+    ))
+}
+
+/// 加入 objects 中没有的对象，id 已经被占用时分配新的 id
+fn union<T: Clone + PartialEq>(
+    objects: &mut Vec<T>,
+    others: &[T],
+    ids: &mut BTreeSet<u64>,
+    id: fn(&mut T) -> &mut u64,
+) {
+    for other in others {
+        if objects.contains(other) {
+            continue;
+        }
+        let mut other = other.clone();
+        if !ids.insert(*id(&mut other)) {
+            let next = ids.last().map_or(0, |last| last + 1);
+            *id(&mut other) = next;
+            ids.insert(next);
+        }
+        objects.push(other);
+    }
+}
+
+pub type LineQuery<'w, 's, 'a> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'a LineId,
+        &'a Line,
+        &'a LineStyle,
+        &'a LineSamples,
+        &'a Layer,
+        &'a GlobalTransform,
+    ),
+>;
+
+pub fn stroke_data(lines: &LineQuery, entity: Entity) -> Option<StrokeData> {
+    let (_, &LineId(id), line, style, samples, layer, transform) =
+        lines.get(entity).ok()?;
+    let Layer::Foreground(layer) = *layer;
+    // 组内的线条按世界坐标展开
+    let (scale, _, translation) = transform.to_scale_rotation_translation();
+    Some(StrokeData {
+        id,
+        points: line.0.iter().map(|point| *point * scale.xy()).collect(),
+        color: style.color.into(),
+        width: style.width,
+        layer,
+        offset: translation.xy(),
+        times: samples.times.clone(),
+        pressures: samples.pressures.clone(),
+        brush: style.brush,
+    })
+}
+
+/// 按 id 顺序收集白板上的所有线条
+pub fn collect_strokes(lines: &LineQuery) -> Vec<StrokeData> {
+    let mut strokes: Vec<StrokeData> = lines
+        .iter()
+        .filter_map(|(entity, ..)| stroke_data(lines, entity))
+        .collect();
+    strokes.sort_by_key(|stroke| stroke.id);
+    strokes
+}
+
+fn save_document(
+    path: Res<DocumentPath>,
+    model: Res<BoardModel>,
+    mut crdt: ResMut<BoardCrdt>,
+    recording: Res<Recording>,
+    background: Res<BoardBackground>,
+) {
+    let document =
+        Document::from_board(&model.0, &mut crdt, &recording, &background);
+    match document.save(&path.0) {
+        Ok(()) => info!("saved board to {:?}", path.0),
+        Err(err) => error!("failed to save board to {:?}: {}", path.0, err),
+    }
+}
+
+fn open_document(
+    mut commands: Commands,
+    path: Res<DocumentPath>,
+    mut recording: ResMut<Recording>,
+) {
+    match Document::load(&path.0) {
+        Ok(document) => {
+            document.load_into_board(&mut commands, &mut recording);
+            info!("opened board from {:?}", path.0);
+        }
+        Err(err) => error!("failed to open board {:?}: {}", path.0, err),
+    }
+}
@@ -1,8 +1,5 @@
 use bevy::{
-    ecs::query::ReadOnlyWorldQuery,
-    input::common_conditions::{
-        input_just_pressed, input_just_released, input_pressed,
-    },
+    ecs::system::SystemParam,
     pbr::wireframe::Wireframe,
     prelude::*,
     sprite::{Material2dPlugin, Mesh2dHandle},
@@ -10,23 +7,41 @@ use bevy::{
 use bevy_prototype_lyon::prelude::*;
 
 use crate::{
-    chalk::ChalkMaterial,
-    common::clear_with,
+    background::BackgroundPlugin,
+    board_text::BoardTextPlugin,
+    board_view::{BoardOpEvent, BoardViewPlugin, ObjectId},
+    brush::{BrushKind, BrushMaterials, BrushPlugin, CurrentBrush},
+    brush_size::{BrushScale, BrushSizePlugin},
+    clipboard::ClipboardPlugin,
+    common::alt_pressed,
     cursor::{Cursor, TouchCursorPlugin, WorldTouchCursor},
+    document::{DocumentPlugin, StrokeData},
     double_click::DoubleClickPlugin,
+    eyedropper::EyedropperPlugin,
     focus::MeshFocusPlugin,
     frame::FrameMaterial,
+    group::GroupPlugin,
+    keybindings::{
+        action_just_pressed, action_just_released, action_pressed, actions,
+        KeyChord,
+    },
+    laser::LaserPlugin,
+    layer::Layer,
+    pages::PagesPlugin,
+    recording::{BoardOp, RecordingPlugin},
+    replay::ReplayState,
     selected::SelectedPlugin,
+    snap::{SnapPlugin, Snapper},
     states::{CursorState, RunMode, ToolButton},
     text_input::TextInputPlugin,
-    toggle_component::{self, Toggle},
+    tools::{RegisterTool, ToolDescriptor},
+    ui::is_hover_tool_button_bar,
 };
 
 pub struct DrawPlugin;
 
 impl Plugin for DrawPlugin {
     fn build(&self, app: &mut App) {
-        let clear_condition = input_pressed(KeyCode::C);
         let draw_all_true = |world: &mut World| {
             world.insert_resource(GizmoConfig {
                 aabb: AabbGizmoConfig {
@@ -45,33 +60,68 @@ impl Plugin for DrawPlugin {
                 ..default()
             })
         };
+        // 工具栏按注册顺序排列：选择、画笔、橡皮擦在其他插件的工具之前
         app.add_plugins((
-            Material2dPlugin::<ChalkMaterial>::default(),
+            BrushPlugin,
             Material2dPlugin::<FrameMaterial>::default(),
             TouchCursorPlugin,
             MeshFocusPlugin,
             SelectedPlugin,
+        ))
+        .register_tool(
+            ToolDescriptor::new(
+                ToolButton::Pen,
+                "Tool: Pen",
+                Vec2::new(0., 0.),
+            )
+            .with_action(actions::TOOL_PEN)
+            .with_key(KeyChord::key(KeyCode::Key2))
+            .with_cursor(Cursor::default())
+            .on_update(
+                (update_line, drawing.run_if(in_state(ReplayState::Off)))
+                    .run_if(in_state(RunMode::Normal)),
+            ),
+        )
+        .register_tool(
+            ToolDescriptor::new(
+                ToolButton::Eraser,
+                "Tool: Eraser",
+                Vec2::new(1., 0.),
+            )
+            .with_action(actions::TOOL_ERASER)
+            .with_key(KeyChord::key(KeyCode::Key3))
+            .with_cursor(Cursor::default())
+            .on_update(
+                remove_line
+                    .run_if(resource_changed::<WorldTouchCursor>())
+                    .run_if(in_state(CursorState::Draging))
+                    .run_if(in_state(ReplayState::Off)),
+            ),
+        )
+        .add_plugins((
+            ClipboardPlugin,
+            GroupPlugin,
             DoubleClickPlugin,
             TextInputPlugin,
+            BoardViewPlugin,
+            RecordingPlugin,
+            DocumentPlugin,
+            BoardTextPlugin,
+            EyedropperPlugin,
+            BrushSizePlugin,
+            BackgroundPlugin,
+            SnapPlugin,
+            LaserPlugin,
+            PagesPlugin,
         ))
+        .init_resource::<NextLine>()
         .add_systems(
             Update,
-            toggle_component::toggle_component::<
-                Handle<ChalkMaterial>,
-                Handle<FrameMaterial>,
-            >
-                .run_if(
-                    input_just_pressed(KeyCode::Tab)
-                        .or_else(input_just_released(KeyCode::Tab)),
-                ),
+            draw_all_true.run_if(action_just_pressed(actions::WIREFRAME)),
         )
         .add_systems(
             Update,
-            draw_all_true.run_if(input_just_pressed(KeyCode::Tab)),
-        )
-        .add_systems(
-            Update,
-            draw_all_false.run_if(input_just_released(KeyCode::Tab)),
+            draw_all_false.run_if(action_just_released(actions::WIREFRAME)),
         )
         .add_systems(
             OnEnter(CursorState::Hovering),
@@ -79,38 +129,57 @@ impl Plugin for DrawPlugin {
         )
         .add_systems(
             OnEnter(CursorState::Draging),
-            spawn_focused_line.run_if(in_state(ToolButton::Pen)),
+            spawn_focused_line
+                .run_if(in_state(ToolButton::Pen))
+                .run_if(in_state(ReplayState::Off))
+                .run_if(not(is_hover_tool_button_bar))
+                .run_if(not(alt_pressed)),
         )
-        .add_systems(Update, clear_with::<With<Path>>.run_if(clear_condition))
         .add_systems(
             Update,
-            remove_line
-                .run_if(resource_changed::<WorldTouchCursor>())
-                .run_if(in_state(ToolButton::Eraser))
-                .run_if(in_state(CursorState::Draging)),
+            clear_lines
+                .run_if(action_pressed(actions::CLEAR))
+                .run_if(in_state(ReplayState::Off)),
         )
         .add_systems(
             Update,
-            remove_last_with::<With<Path>>.run_if(undo_condition),
+            undo_last_line
+                .run_if(action_just_pressed(actions::UNDO))
+                .run_if(in_state(ReplayState::Off)),
         )
         .add_systems(
             Update,
-            (
-                crate::layer::update_z_coordinate_based_on_layer,
-                update_line,
-                drawing,
-            )
-                .run_if(in_state(RunMode::Normal))
-                .run_if(in_state(ToolButton::Pen)),
+            // 在其他工具中调整层级后也要立即更新
+            crate::layer::update_z_coordinate_based_on_layer,
         );
     }
 }
 
 #[derive(Component, Default)]
-struct Focused;
+struct Focused {
+    started_at: f32,
+}
 
-#[derive(Component, Default)]
-struct Line(Vec<Vec2>);
+#[derive(Component, Default, Clone)]
+pub struct Line(pub Vec<Vec2>);
+
+/// 线条在文档中的稳定 id，实体 id 在保存/加载后不可复用
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LineId(pub u64);
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct LineStyle {
+    pub color: Color,
+    pub width: f32,
+    pub brush: BrushKind,
+}
+
+/// 与 Line 中的点一一对应的采样数据，没有时为空
+#[derive(Component, Default, Debug, Clone, PartialEq)]
+pub struct LineSamples {
+    pub times: Vec<f32>,
+    pub pressures: Vec<f32>,
+}
 
 impl From<&Line> for Path {
     fn from(value: &Line) -> Self {
@@ -127,119 +196,251 @@ impl From<&Line> for Path {
     }
 }
 
+/// 协作时每个客户端只使用 id % SITE_STRIDE 等于服务器分配的站点的 id，不会与其他人冲突
+pub const SITE_STRIDE: u64 = 1024;
+
+#[derive(Resource, Default)]
+pub struct NextLine {
+    id: u64,
+    layer: i8,
+    /** 协作时服务器分配的站点 */
+    site: Option<u64>,
+}
+
+impl NextLine {
+    /// 之后的线条画在 layer 之上
+    pub fn reserve_layer(&mut self, layer: i8) {
+        if layer >= self.layer {
+            self.layer = (layer + 1) % (i8::MAX - 1);
+        }
+    }
+
+    /// 之后分配的 id 都大于 id
+    pub fn reserve_id(&mut self, id: u64) {
+        self.id = self.id.max(id + 1);
+    }
+}
+
+#[derive(SystemParam)]
+pub struct LineSpawner<'w, 's> {
+    pub commands: Commands<'w, 's>,
+    materials: BrushMaterials<'w>,
+    next_line: ResMut<'w, NextLine>,
+}
+
+impl LineSpawner<'_, '_> {
+    /// 分配下一个对象的 id 与层级
+    pub fn next(&mut self) -> (u64, i8) {
+        (self.next_id(), self.next_layer())
+    }
+
+    /// 只分配 id，用于没有层级的组
+    pub fn next_id(&mut self) -> u64 {
+        let NextLine { mut id, site, .. } = *self.next_line;
+        if let Some(site) = site {
+            id += (site + SITE_STRIDE - id % SITE_STRIDE) % SITE_STRIDE;
+        }
+        self.next_line.id = id + 1;
+        id
+    }
+
+    pub fn next_layer(&mut self) -> i8 {
+        let layer = self.next_line.layer;
+        self.next_line.layer = (layer + 1) % (i8::MAX - 1);
+        layer
+    }
+
+    pub fn reserve_id(&mut self, id: u64) {
+        self.next_line.reserve_id(id);
+    }
+
+    pub fn reserve_layer(&mut self, layer: i8) {
+        self.next_line.reserve_layer(layer);
+    }
+
+    pub fn set_site(&mut self, site: Option<u64>) {
+        self.next_line.site = site.map(|site| site % SITE_STRIDE);
+    }
+
+    pub fn spawn(&mut self, data: &StrokeData) -> Entity {
+        self.next_line.reserve_id(data.id);
+        self.next_line.reserve_layer(data.layer);
+
+        let line = Line(data.points.clone());
+
+        let mut entity = self.commands.spawn((
+            line_stroke(data.width),
+            Path::from(&line),
+            Mesh2dHandle::default(),
+            SpatialBundle::from_transform(Transform::from_translation(
+                data.offset.extend(0.),
+            )),
+            line,
+            LineId(data.id),
+            ObjectId(data.id),
+            LineStyle {
+                color: data.color.into(),
+                width: data.width,
+                brush: data.brush,
+            },
+            LineSamples {
+                times: data.times.clone(),
+                pressures: data.pressures.clone(),
+            },
+            Wireframe,
+            Layer::Foreground(data.layer),
+        ));
+        self.materials
+            .insert(&mut entity, data.brush, data.color.into());
+        entity.id()
+    }
+}
+
+fn line_stroke(width: f32) -> Stroke {
+    let mut stroke = Stroke::new(Color::RED, width);
+    stroke.options.line_join = LineJoin::Round;
+    stroke.options.start_cap = LineCap::Round;
+    stroke.options.end_cap = LineCap::Round;
+    stroke
+}
+
 fn remove_focused_line(
-    focused_line: Query<(Entity, &Line), With<Focused>>,
+    focused_line: Query<(Entity, &Line, &LineId), With<Focused>>,
     mut commands: Commands,
+    mut board_ops: EventWriter<BoardOpEvent>,
 ) {
-    if let Ok((focused_line, line)) = focused_line.get_single() {
+    if let Ok((focused_line, line, &LineId(id))) = focused_line.get_single() {
         if line.0.len() == 0 {
             commands.entity(focused_line).despawn();
+            board_ops.send(BoardOpEvent(BoardOp::Erase { id }));
         } else {
             commands.entity(focused_line).remove::<Focused>();
+            board_ops.send(BoardOpEvent(BoardOp::StrokeEnd { id }));
         }
     }
 }
 
 fn spawn_focused_line(
-    mut commands: Commands,
-    mut materials: ResMut<Assets<ChalkMaterial>>,
-    mut frame_materials: ResMut<Assets<FrameMaterial>>,
-    mut current_layer: Local<i8>,
+    mut spawner: LineSpawner,
     cursor: Res<Cursor>,
+    brush: Res<CurrentBrush>,
+    brush_scale: Res<BrushScale>,
+    time: Res<Time<Real>>,
+    mut board_ops: EventWriter<BoardOpEvent>,
 ) {
     if let Cursor::Touch(touch_cursor) = cursor.as_ref() {
-        let mut stroke = Stroke::new(Color::RED, touch_cursor.size);
-        stroke.options.line_join = LineJoin::Round;
-        stroke.options.start_cap = LineCap::Round;
-        stroke.options.end_cap = LineCap::Round;
-
-        let toggle_material = Toggle(
-            materials.add(ChalkMaterial {
-                material_color: touch_cursor.color,
-            }),
-            frame_materials.add(FrameMaterial::default()),
-        );
-
-        commands.spawn((
-            stroke,
-            Path::default(),
-            Mesh2dHandle::default(),
-            SpatialBundle::default(),
-            toggle_material.0.clone(),
-            toggle_material,
-            Focused,
-            Line(vec![]),
-            Wireframe,
-            crate::layer::Layer::Foreground(*current_layer),
-        ));
-        *current_layer = (*current_layer + 1) % (i8::MAX - 1);
+        let width = touch_cursor.size * brush_scale.0;
+        let (id, layer) = spawner.next();
+        let entity = spawner.spawn(&StrokeData {
+            id,
+            color: touch_cursor.color.into(),
+            width,
+            layer,
+            brush: brush.0,
+            ..default()
+        });
+        spawner.commands.entity(entity).insert(Focused {
+            started_at: time.elapsed_seconds(),
+        });
+        board_ops.send(BoardOpEvent(BoardOp::StrokeStart {
+            id,
+            color: touch_cursor.color.into(),
+            width,
+            layer,
+            brush: brush.0,
+        }));
     }
 }
 
 fn drawing(
-    mut focused_line: Query<&mut Line, With<Focused>>,
+    mut focused_line: Query<(&mut Line, &mut LineSamples, &LineId, &Focused)>,
     world_touch_cursor: Res<WorldTouchCursor>,
+    snapper: Snapper,
+    time: Res<Time<Real>>,
+    mut board_ops: EventWriter<BoardOpEvent>,
 ) {
     if world_touch_cursor.is_changed() {
-        if let Ok(mut focused_line) = focused_line.get_single_mut() {
-            let WorldTouchCursor(point) = *world_touch_cursor;
+        if let Ok((mut focused_line, mut samples, &LineId(id), focused)) =
+            focused_line.get_single_mut()
+        {
+            let point = snapper.snap_pen_point(world_touch_cursor.0);
             let last = if let Some(last) = focused_line.0.iter().last() {
                 last
             } else {
                 &Vec2::ZERO
             };
             if last.distance(point) > 2. {
+                let t = time.elapsed_seconds() - focused.started_at;
                 focused_line.0.push(point);
+                samples.times.push(t);
+                board_ops.send(BoardOpEvent(BoardOp::StrokePoint {
+                    id,
+                    point,
+                    time: Some(t),
+                    pressure: None,
+                }));
             }
         }
     }
 }
 
-fn remove_last_with<F: ReadOnlyWorldQuery>(
+fn clear_lines(
     mut commands: Commands,
-    query: Query<Entity, F>,
+    query: Query<Entity, With<Line>>,
+    mut board_ops: EventWriter<BoardOpEvent>,
 ) {
-    query.iter().last().and_then(|id| {
-        commands.entity(id).despawn();
-        Some(())
-    });
+    if query.is_empty() {
+        return;
+    }
+    for id in query.iter() {
+        commands.entity(id).despawn_recursive();
+    }
+    board_ops.send(BoardOpEvent(BoardOp::Clear));
 }
 
-fn undo_condition(keyboard_input: Res<Input<KeyCode>>) -> bool {
-    keyboard_input.just_pressed(KeyCode::Z)
-        && keyboard_input.pressed(KeyCode::ControlLeft)
+fn undo_last_line(
+    mut commands: Commands,
+    query: Query<(Entity, &LineId)>,
+    mut board_ops: EventWriter<BoardOpEvent>,
+) {
+    if let Some((entity, &LineId(id))) =
+        query.iter().max_by_key(|(_, LineId(id))| *id)
+    {
+        commands.entity(entity).despawn_recursive();
+        board_ops.send(BoardOpEvent(BoardOp::Erase { id }));
+    }
 }
 
 fn update_line(
     focused_line: Query<(Entity, &Line), Changed<Line>>,
-    cursor: Res<Cursor>,
     mut commands: Commands,
 ) {
-    if let Cursor::Touch(touch_cursor) = cursor.as_ref() {
-        let mut stroke = Stroke::new(Color::RED, touch_cursor.size);
-        stroke.options.line_join = LineJoin::Round;
-        stroke.options.start_cap = LineCap::Round;
-        stroke.options.end_cap = LineCap::Round;
-        for (id, line) in focused_line.iter() {
-            commands.entity(id).insert((Path::from(line), stroke));
-        }
+    for (id, line) in focused_line.iter() {
+        commands.entity(id).insert(Path::from(line));
     }
 }
 
 fn remove_line(
     world_touch_cursor: Res<WorldTouchCursor>,
     cursor: Res<Cursor>,
-    focused_line: Query<(Entity, &Line)>,
+    brush_scale: Res<BrushScale>,
+    focused_line: Query<(Entity, &Line, &LineId, &GlobalTransform)>,
     mut commands: Commands,
+    mut board_ops: EventWriter<BoardOpEvent>,
 ) {
     if let Cursor::Touch(touch_cursor) = cursor.as_ref() {
-        for (id, line) in focused_line.iter() {
-            if line
-                .0
-                .iter()
-                .any(|p| p.distance(world_touch_cursor.0) <= touch_cursor.size)
-            {
-                commands.entity(id).despawn();
+        let radius = touch_cursor.size * brush_scale.0;
+        for (entity, line, &LineId(id), transform) in focused_line.iter() {
+            // 移动、缩放或编组后的线条按世界坐标比较
+            if line.0.iter().any(|p| {
+                transform
+                    .transform_point(p.extend(0.))
+                    .xy()
+                    .distance(world_touch_cursor.0)
+                    <= radius
+            }) {
+                commands.entity(entity).despawn_recursive();
+                board_ops.send(BoardOpEvent(BoardOp::Erase { id }));
             }
         }
     }
@@ -0,0 +1,426 @@
+// Excalidraw 场景文件 (.excalidraw) 的导入导出
+// 坐标系：Excalidraw 的 y 轴向下，白板的 y 轴向上
+use std::{
+    f32::consts::PI,
+    fs, io,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{prelude::*, window::FileDragAndDrop};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board_text::{spawn_text, TextData},
+    board_view::{BoardModel, BoardOpEvent},
+    document::{DocumentPath, StrokeData},
+    draw::LineSpawner,
+    keybindings::{action_just_pressed, actions},
+    recording::{stroke_ops, BoardOp},
+    replay::ReplayState,
+};
+
+pub struct ExcalidrawPlugin;
+
+impl Plugin for ExcalidrawPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                import_dropped_scenes,
+                export_scene
+                    .run_if(action_just_pressed(actions::EXPORT_EXCALIDRAW)),
+            )
+                .run_if(in_state(ReplayState::Off)),
+        );
+    }
+}
+
+/// Excalidraw 默认的描边色
+const EXCALIDRAW_STROKE: &str = "#1e1e1e";
+pub(crate) const ELLIPSE_SEGMENTS: usize = 48;
+const ARROWHEAD_LENGTH: f32 = 20.;
+const ARROWHEAD_ANGLE: f32 = PI / 7.;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExcalidrawScene {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub version: u32,
+    #[serde(default)]
+    pub source: String,
+    pub elements: Vec<ExcalidrawElement>,
+    #[serde(default)]
+    pub app_state: serde_json::Value,
+    #[serde(default)]
+    pub files: serde_json::Value,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ExcalidrawElement {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub angle: f32,
+    pub stroke_color: String,
+    pub background_color: String,
+    pub fill_style: String,
+    pub stroke_width: f32,
+    pub stroke_style: String,
+    pub roughness: u32,
+    pub opacity: f32,
+    pub group_ids: Vec<String>,
+    pub frame_id: Option<String>,
+    pub roundness: Option<serde_json::Value>,
+    pub seed: u32,
+    pub version: u32,
+    pub version_nonce: u32,
+    pub is_deleted: bool,
+    pub bound_elements: Option<serde_json::Value>,
+    pub updated: u64,
+    pub link: Option<String>,
+    pub locked: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub points: Option<Vec<[f32; 2]>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pressures: Option<Vec<f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub simulate_pressure: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_arrowhead: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_arrowhead: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original_text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font_size: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font_family: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text_align: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vertical_align: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_height: Option<f32>,
+}
+
+impl Default for ExcalidrawElement {
+    fn default() -> Self {
+        ExcalidrawElement {
+            id: String::new(),
+            kind: String::new(),
+            x: 0.,
+            y: 0.,
+            width: 0.,
+            height: 0.,
+            angle: 0.,
+            stroke_color: EXCALIDRAW_STROKE.to_string(),
+            background_color: "transparent".to_string(),
+            fill_style: "solid".to_string(),
+            stroke_width: 2.,
+            stroke_style: "solid".to_string(),
+            roughness: 1,
+            opacity: 100.,
+            group_ids: vec![],
+            frame_id: None,
+            roundness: None,
+            seed: 1,
+            version: 1,
+            version_nonce: 0,
+            is_deleted: false,
+            bound_elements: None,
+            updated: 0,
+            link: None,
+            locked: false,
+            points: None,
+            pressures: None,
+            simulate_pressure: None,
+            start_arrowhead: None,
+            end_arrowhead: None,
+            text: None,
+            original_text: None,
+            font_size: None,
+            font_family: None,
+            text_align: None,
+            vertical_align: None,
+            line_height: None,
+        }
+    }
+}
+
+impl ExcalidrawScene {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    /// 转换为白板对象，对象的 id 与层级由调用方重新分配
+    pub fn to_board(&self) -> (Vec<StrokeData>, Vec<TextData>) {
+        let mut strokes = vec![];
+        let mut texts = vec![];
+        for element in self.elements.iter().filter(|e| !e.is_deleted) {
+            let color = import_color(&element.stroke_color, element.opacity);
+            if element.kind == "text" {
+                texts.push(TextData {
+                    id: 0,
+                    text: element.text.clone().unwrap_or_default(),
+                    position: Vec2::new(element.x, -element.y),
+                    size: element.font_size.unwrap_or(20.),
+                    color: color.into(),
+                    layer: 0,
+                });
+                continue;
+            }
+            for points in element_polylines(element) {
+                strokes.push(StrokeData {
+                    id: 0,
+                    points: points
+                        .into_iter()
+                        .map(|point| {
+                            rotate(element, point) * Vec2::new(1., -1.)
+                        })
+                        .collect(),
+                    color: color.into(),
+                    width: element.stroke_width,
+                    ..default()
+                });
+            }
+        }
+        (strokes, texts)
+    }
+
+    pub fn from_board(strokes: &[StrokeData], texts: &[TextData]) -> Self {
+        let updated = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_millis() as u64);
+        let mut elements: Vec<ExcalidrawElement> = strokes
+            .iter()
+            .filter(|stroke| !stroke.points.is_empty())
+            .map(|stroke| {
+                let points: Vec<Vec2> = stroke
+                    .points
+                    .iter()
+                    .map(|point| (*point + stroke.offset) * Vec2::new(1., -1.))
+                    .collect();
+                let origin = points[0];
+                let (min, max) = points
+                    .iter()
+                    .fold((origin, origin), |(min, max), point| {
+                        (min.min(*point), max.max(*point))
+                    });
+                let (stroke_color, opacity) = export_color(stroke.color.into());
+                ExcalidrawElement {
+                    id: format!("lines-{}", stroke.id),
+                    kind: "freedraw".to_string(),
+                    x: origin.x,
+                    y: origin.y,
+                    width: max.x - min.x,
+                    height: max.y - min.y,
+                    stroke_color,
+                    opacity,
+                    stroke_width: stroke.width,
+                    seed: rand::random(),
+                    updated,
+                    points: Some(
+                        points
+                            .iter()
+                            .map(|point| (*point - origin).to_array())
+                            .collect(),
+                    ),
+                    pressures: Some(vec![]),
+                    simulate_pressure: Some(true),
+                    ..default()
+                }
+            })
+            .collect();
+        elements.extend(texts.iter().enumerate().map(|(index, text)| {
+            let (stroke_color, opacity) = export_color(text.color.into());
+            let lines = text.text.lines().count().max(1) as f32;
+            let longest =
+                text.text.lines().map(|line| line.chars().count()).max();
+            ExcalidrawElement {
+                id: format!("lines-text-{}", index),
+                kind: "text".to_string(),
+                x: text.position.x,
+                y: -text.position.y,
+                width: longest.unwrap_or(0) as f32 * text.size * 0.6,
+                height: lines * text.size * 1.25,
+                stroke_color,
+                opacity,
+                seed: rand::random(),
+                updated,
+                text: Some(text.text.clone()),
+                original_text: Some(text.text.clone()),
+                font_size: Some(text.size),
+                font_family: Some(1),
+                text_align: Some("left".to_string()),
+                vertical_align: Some("top".to_string()),
+                line_height: Some(1.25),
+                ..default()
+            }
+        }));
+
+        ExcalidrawScene {
+            kind: "excalidraw".to_string(),
+            version: 2,
+            source: "lines".to_string(),
+            elements,
+            app_state: serde_json::json!({ "viewBackgroundColor": "#ffffff" }),
+            files: serde_json::json!({}),
+        }
+    }
+}
+
+/// 元素在 Excalidraw 坐标系下（未旋转）的折线
+fn element_polylines(element: &ExcalidrawElement) -> Vec<Vec<Vec2>> {
+    let origin = Vec2::new(element.x, element.y);
+    let size = Vec2::new(element.width, element.height);
+    let relative_points = || -> Vec<Vec2> {
+        element
+            .points
+            .iter()
+            .flatten()
+            .map(|[x, y]| origin + Vec2::new(*x, *y))
+            .collect()
+    };
+    match element.kind.as_str() {
+        "freedraw" | "line" => vec![relative_points()],
+        "arrow" => {
+            let points = relative_points();
+            let mut polylines = vec![];
+            if let (Some(_), [first, second, ..]) =
+                (&element.start_arrowhead, points.as_slice())
+            {
+                polylines.push(arrowhead(*second, *first));
+            }
+            if let (Some(_), [.., second_last, last]) =
+                (&element.end_arrowhead, points.as_slice())
+            {
+                polylines.push(arrowhead(*second_last, *last));
+            }
+            polylines.insert(0, points);
+            polylines
+        }
+        "rectangle" => vec![vec![
+            origin,
+            origin + Vec2::new(size.x, 0.),
+            origin + size,
+            origin + Vec2::new(0., size.y),
+            origin,
+        ]],
+        "diamond" => vec![vec![
+            origin + Vec2::new(size.x / 2., 0.),
+            origin + Vec2::new(size.x, size.y / 2.),
+            origin + Vec2::new(size.x / 2., size.y),
+            origin + Vec2::new(0., size.y / 2.),
+            origin + Vec2::new(size.x / 2., 0.),
+        ]],
+        "ellipse" => {
+            let center = origin + size / 2.;
+            vec![(0..=ELLIPSE_SEGMENTS)
+                .map(|i| {
+                    let theta = i as f32 / ELLIPSE_SEGMENTS as f32 * 2. * PI;
+                    center + Vec2::new(theta.cos(), theta.sin()) * size / 2.
+                })
+                .collect()]
+        }
+        _ => vec![],
+    }
+}
+
+pub(crate) fn arrowhead(from: Vec2, tip: Vec2) -> Vec<Vec2> {
+    let back = (from - tip).normalize_or_zero() * ARROWHEAD_LENGTH;
+    vec![
+        tip + Vec2::from_angle(ARROWHEAD_ANGLE).rotate(back),
+        tip,
+        tip + Vec2::from_angle(-ARROWHEAD_ANGLE).rotate(back),
+    ]
+}
+
+/// 绕元素中心旋转 angle 弧度
+fn rotate(element: &ExcalidrawElement, point: Vec2) -> Vec2 {
+    if element.angle == 0. {
+        return point;
+    }
+    let center = Vec2::new(
+        element.x + element.width / 2.,
+        element.y + element.height / 2.,
+    );
+    center + Vec2::from_angle(element.angle).rotate(point - center)
+}
+
+/// Excalidraw 默认是白底黑字，黑板则相反，所以默认描边色与白色互换
+fn import_color(stroke_color: &str, opacity: f32) -> Color {
+    let color = match stroke_color {
+        EXCALIDRAW_STROKE | "#000000" => Color::WHITE,
+        _ => Color::hex(stroke_color).unwrap_or(Color::WHITE),
+    };
+    color.with_a(opacity / 100.)
+}
+
+fn export_color(color: Color) -> (String, f32) {
+    let [r, g, b, a] = color.as_rgba_u8();
+    let stroke_color = if [r, g, b] == [255, 255, 255] {
+        EXCALIDRAW_STROKE.to_string()
+    } else {
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    };
+    (stroke_color, a as f32 / 255. * 100.)
+}
+
+fn import_dropped_scenes(
+    mut drop_events: EventReader<FileDragAndDrop>,
+    mut spawner: LineSpawner,
+    mut board_ops: EventWriter<BoardOpEvent>,
+) {
+    for event in drop_events.read() {
+        let FileDragAndDrop::DroppedFile { path_buf, .. } = event else {
+            continue;
+        };
+        if path_buf.extension().map_or(true, |ext| ext != "excalidraw") {
+            continue;
+        }
+        let scene = match ExcalidrawScene::load(path_buf) {
+            Ok(scene) => scene,
+            Err(err) => {
+                error!("failed to import {:?}: {}", path_buf, err);
+                continue;
+            }
+        };
+        let (strokes, texts) = scene.to_board();
+        for mut stroke in strokes {
+            (stroke.id, stroke.layer) = spawner.next();
+            spawner.spawn(&stroke);
+            board_ops
+                .send_batch(stroke_ops(&stroke).into_iter().map(BoardOpEvent));
+        }
+        for mut text in texts {
+            (text.id, text.layer) = spawner.next();
+            spawn_text(&mut spawner.commands, &text);
+            board_ops.send(BoardOpEvent(BoardOp::AddText { text }));
+        }
+        info!("imported {:?}", path_buf);
+    }
+}
+
+fn export_scene(path: Res<DocumentPath>, model: Res<BoardModel>) {
+    let path = path.0.with_extension("excalidraw");
+    let page = model.0.page();
+    let texts: Vec<TextData> = page.texts().cloned().collect();
+    let scene = ExcalidrawScene::from_board(&page.to_strokes(), &texts);
+    match scene.save(&path) {
+        Ok(()) => info!("exported board to {:?}", path),
+        Err(err) => error!("failed to export {:?}: {}", path, err),
+    }
+}
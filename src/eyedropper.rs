@@ -0,0 +1,77 @@
+// 吸管：点击线条取它的颜色与粗细，画笔模式下按住 Alt 点击临时吸取
+use bevy::{input::common_conditions::input_just_pressed, prelude::*};
+
+use crate::{
+    brush_size::BrushScale,
+    common::alt_pressed,
+    cursor::{Cursor, WorldTouchCursor},
+    draw::LineStyle,
+    focus::{find_entity_with_world_cursor_by, NodeQuery},
+    keybindings::{actions, KeyChord},
+    palette::Palette,
+    states::{RunMode, ToolButton},
+    tools::{RegisterTool, ToolDescriptor},
+    ui::is_hover_tool_button_bar,
+};
+
+pub struct EyedropperPlugin;
+
+impl Plugin for EyedropperPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_tool(
+            ToolDescriptor::new(
+                ToolButton::Eyedropper,
+                "Tool: Eyedropper",
+                Vec2::new(3., 0.),
+            )
+            .with_action(actions::TOOL_EYEDROPPER)
+            .with_key(KeyChord::key(KeyCode::Key4)),
+        )
+        .add_systems(
+            Update,
+            sample_stroke
+                .run_if(
+                    in_state(ToolButton::Eyedropper).or_else(
+                        in_state(ToolButton::Pen).and_then(alt_pressed),
+                    ),
+                )
+                .run_if(input_just_pressed(MouseButton::Left))
+                .run_if(not(is_hover_tool_button_bar))
+                .run_if(in_state(RunMode::Normal)),
+        );
+    }
+}
+
+fn sample_stroke(
+    node_query: NodeQuery,
+    styles: Query<&LineStyle>,
+    world_touch_cursor: Res<WorldTouchCursor>,
+    brush_scale: Res<BrushScale>,
+    mut palette: ResMut<Palette>,
+    mut cursor: ResMut<Cursor>,
+    mut tool_cursors: Query<(&ToolButton, &mut Cursor)>,
+) {
+    let Some((entity, _, _)) = find_entity_with_world_cursor_by(
+        &node_query,
+        &world_touch_cursor,
+        |entity| styles.contains(entity),
+    ) else {
+        return;
+    };
+    let Ok(style) = styles.get(entity) else {
+        return;
+    };
+    palette.set(style.color);
+
+    // 切换工具时画笔会从工具栏按钮上复制，所以按钮上的画笔也一起修改
+    let pens = tool_cursors
+        .iter_mut()
+        .filter(|(tool, _)| **tool == ToolButton::Pen)
+        .map(|(_, cursor)| cursor.into_inner());
+    for cursor in pens.chain(std::iter::once(cursor.as_mut())) {
+        if let Cursor::Touch(touch_cursor) = cursor {
+            touch_cursor.color = style.color;
+            touch_cursor.size = style.width / brush_scale.0;
+        }
+    }
+}
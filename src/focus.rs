@@ -1,13 +1,13 @@
 // 1. 点选 包围盒 判定
 // 2. TODO: 然后 二次 Mesh 内判定
 use bevy::{
-    input::common_conditions::input_pressed, prelude::*,
-    render::primitives::Aabb,
+    ecs::system::SystemParam, input::common_conditions::input_pressed,
+    prelude::*, render::primitives::Aabb,
 };
 
 use crate::{
-    cursor::WorldTouchCursor, layer::Layer, selected::Selected,
-    states::ToolButton,
+    cursor::WorldTouchCursor, group::EnteredGroup, layer::Layer,
+    selected::Selected, states::ToolButton,
 };
 
 #[derive(Resource, Default)]
@@ -26,18 +26,45 @@ impl Plugin for MeshFocusPlugin {
     }
 }
 
+pub type NodeQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Aabb,
+        &'static Layer,
+        &'static GlobalTransform,
+    ),
+>;
+
+/// 包围盒换算到世界坐标（忽略旋转）
+pub fn world_rect(aabb: &Aabb, transform: &GlobalTransform) -> Rect {
+    let center = transform.transform_point(aabb.center.into()).xy();
+    let (scale, _, _) = transform.to_scale_rotation_translation();
+    let half_size = (Vec3::from(aabb.half_extents) * scale).xy();
+    Rect::from_center_half_size(center, half_size)
+}
+
 pub fn find_entity_with_world_cursor<'a>(
-    node_query: &'a Query<(Entity, &Aabb, &Layer)>,
-    world_touch_cursor: Res<WorldTouchCursor>,
-) -> Option<(Entity, &'a i8, &'a Aabb)> {
+    node_query: &'a NodeQuery,
+    world_touch_cursor: &WorldTouchCursor,
+) -> Option<(Entity, &'a i8, Rect)> {
+    find_entity_with_world_cursor_by(node_query, world_touch_cursor, |_| true)
+}
+
+/// 只在满足 filter 的对象中查找指针下最上层的对象
+pub fn find_entity_with_world_cursor_by<'a>(
+    node_query: &'a NodeQuery,
+    world_touch_cursor: &WorldTouchCursor,
+    filter: impl Fn(Entity) -> bool,
+) -> Option<(Entity, &'a i8, Rect)> {
     node_query
         .iter()
-        .filter_map(|(entity, aabb, Layer::Foreground(layer))| {
-            let WorldTouchCursor(Vec2 { x, y }) = *world_touch_cursor;
-            if (aabb.center.x - x).abs() <= aabb.half_extents.x
-                && (aabb.center.y - y).abs() <= aabb.half_extents.y
-            {
-                Some((entity, layer, aabb))
+        .filter(|(entity, ..)| filter(*entity))
+        .filter_map(|(entity, aabb, Layer::Foreground(layer), transform)| {
+            let rect = world_rect(aabb, transform);
+            if rect.contains(world_touch_cursor.0) {
+                Some((entity, layer, rect))
             } else {
                 None
             }
@@ -45,36 +72,65 @@ pub fn find_entity_with_world_cursor<'a>(
         .max_by(|(_, l1, _), (_, l2, _)| l1.cmp(l2))
 }
 
+#[derive(SystemParam)]
+pub struct Picker<'w, 's> {
+    nodes: NodeQuery<'w, 's>,
+    parents: Query<'w, 's, &'static Parent>,
+    entered: Res<'w, EnteredGroup>,
+}
+
+impl Picker<'_, '_> {
+    /// 指针下的对象。组内的对象解析为最外层的组，进入组后解析为组的直接成员
+    pub fn pick(
+        &self,
+        world_touch_cursor: &WorldTouchCursor,
+    ) -> Option<Entity> {
+        let (mut entity, _, _) =
+            find_entity_with_world_cursor(&self.nodes, world_touch_cursor)?;
+        while let Ok(parent) = self.parents.get(entity) {
+            if Some(parent.get()) == self.entered.0 {
+                break;
+            }
+            entity = parent.get();
+        }
+        Some(entity)
+    }
+
+    /// 指针下的对象是否属于某个组
+    pub fn is_grouped(&self, world_touch_cursor: &WorldTouchCursor) -> bool {
+        find_entity_with_world_cursor(&self.nodes, world_touch_cursor)
+            .is_some_and(|(entity, _, _)| self.parents.contains(entity))
+    }
+
+    pub fn is_in_entered_group(&self, entity: Entity) -> bool {
+        self.parents.get(entity).map(|parent| parent.get()).ok()
+            == self.entered.0
+    }
+}
+
 fn mesh_focus_system(
-    node_query: Query<(Entity, &Aabb, &Layer)>,
+    picker: Picker,
     world_touch_cursor: Res<WorldTouchCursor>,
     selected: Res<Selected>,
     mut hovered_mesh: ResMut<HoveredMesh>,
 ) {
-    let entity = find_entity_with_world_cursor(&node_query, world_touch_cursor)
-        .and_then(|(entity, _, _)| Some(entity));
-    if let (Some(a), Some(b)) = (selected.0.get(0), entity) {
-        if *a == b {
-            *hovered_mesh = HoveredMesh(None);
-            return;
-        }
+    let entity = picker.pick(&world_touch_cursor);
+    if entity.is_some_and(|entity| selected.0.contains(&entity)) {
+        *hovered_mesh = HoveredMesh(None);
+        return;
     }
     *hovered_mesh = HoveredMesh(entity);
 }
 
 fn draw_focus(
     mut gizmos: Gizmos,
-    node_query: Query<(Entity, &Aabb, &Layer)>,
+    bounds: Query<(&Aabb, &GlobalTransform)>,
     hovered_mesh: Res<HoveredMesh>,
 ) {
     hovered_mesh.0.and_then(|entity| {
-        let _ = node_query.get(entity).and_then(|(_, aabb, _)| {
-            gizmos.rect_2d(
-                aabb.center.xy(),
-                0.,
-                aabb.half_extents.xy() * 2.,
-                Color::BLUE,
-            );
+        let _ = bounds.get(entity).and_then(|(aabb, transform)| {
+            let rect = world_rect(aabb, transform);
+            gizmos.rect_2d(rect.center(), 0., rect.size(), Color::BLUE);
             Ok(())
         });
         Some(())
@@ -0,0 +1,258 @@
+// 1. Ctrl+G 把选中的对象编为一组，Ctrl+Shift+G 取消编组
+// 2. 双击组进入组内编辑成员，点击组外退出
+use bevy::{
+    input::common_conditions::input_just_pressed, prelude::*,
+    render::primitives::Aabb,
+};
+
+use crate::{
+    board_view::{BoardOpEvent, ObjectId},
+    cursor::WorldTouchCursor,
+    double_click::DoubleClickEvent,
+    draw::LineSpawner,
+    focus::{world_rect, Picker},
+    keybindings::{action_just_pressed, actions},
+    recording::BoardOp,
+    replay::ReplayState,
+    selected::{Selected, SelectedPlugin},
+    states::ToolButton,
+};
+
+pub struct GroupPlugin;
+
+impl Plugin for GroupPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EnteredGroup>()
+            .add_systems(
+                Update,
+                (
+                    group_selected.run_if(action_just_pressed(actions::GROUP)),
+                    ungroup_selected
+                        .run_if(action_just_pressed(actions::UNGROUP)),
+                    exit_group
+                        .before(SelectedPlugin)
+                        .run_if(input_just_pressed(MouseButton::Left)),
+                    enter_group.after(SelectedPlugin),
+                    draw_entered_group,
+                )
+                    .run_if(in_state(ToolButton::Cursor))
+                    .run_if(in_state(ReplayState::Off)),
+            )
+            .add_systems(
+                Update,
+                (remove_empty_groups, update_group_aabb).chain(),
+            );
+    }
+}
+
+/// 组本身没有 Layer，不参与点选与层级，只作为成员的父节点
+#[derive(Component, Debug, Default)]
+pub struct Group;
+
+/// 当前进入编辑的组
+#[derive(Resource, Default)]
+pub struct EnteredGroup(pub Option<Entity>);
+
+/// 双击组时进入组，而不是开始输入文字
+pub fn cursor_over_group(
+    picker: Picker,
+    world_touch_cursor: Res<WorldTouchCursor>,
+) -> bool {
+    picker.is_grouped(&world_touch_cursor)
+}
+
+/// 展开组，返回对象及其所有子孙
+pub fn with_descendants(
+    entities: &[Entity],
+    children: &Query<&Children>,
+) -> Vec<Entity> {
+    entities
+        .iter()
+        .flat_map(|entity| {
+            std::iter::once(*entity).chain(children.iter_descendants(*entity))
+        })
+        .collect()
+}
+
+/// 对象在父节点坐标系中的包围盒
+fn local_rect(aabb: &Aabb, transform: &Transform) -> Rect {
+    world_rect(aabb, &GlobalTransform::from(*transform))
+}
+
+fn group_selected(
+    mut spawner: LineSpawner,
+    mut selected: ResMut<Selected>,
+    entered: Res<EnteredGroup>,
+    members: Query<(&Aabb, &Transform, &ObjectId)>,
+    mut board_ops: EventWriter<BoardOpEvent>,
+) {
+    let rects: Vec<(Entity, Transform, Rect, u64)> = selected
+        .0
+        .iter()
+        .filter_map(|entity| {
+            let (aabb, transform, &ObjectId(id)) = members.get(*entity).ok()?;
+            Some((*entity, *transform, local_rect(aabb, transform), id))
+        })
+        .collect();
+    let Some(bounds) = rects
+        .iter()
+        .map(|(_, _, rect, _)| *rect)
+        .reduce(|a, b| a.union(b))
+    else {
+        return;
+    };
+
+    // 组的原点放在包围盒中心，缩放时以中心为基准
+    let center = bounds.center().extend(0.);
+    let id = spawner.next_id();
+    let commands = &mut spawner.commands;
+    let group = commands
+        .spawn((
+            SpatialBundle::from_transform(Transform::from_translation(center)),
+            Group,
+            ObjectId(id),
+        ))
+        .id();
+    if let Some(parent) = entered.0 {
+        commands.entity(parent).add_child(group);
+    }
+    let members = rects.iter().map(|(.., member)| *member).collect();
+    for (entity, mut transform, ..) in rects {
+        transform.translation -= center;
+        commands.entity(entity).insert(transform).set_parent(group);
+    }
+    board_ops.send(BoardOpEvent(BoardOp::Group { id, members }));
+    selected.0 = vec![group];
+}
+
+fn ungroup_selected(
+    mut commands: Commands,
+    mut selected: ResMut<Selected>,
+    entered: Res<EnteredGroup>,
+    groups: Query<(&Transform, &Children, &ObjectId), With<Group>>,
+    transforms: Query<&Transform>,
+    mut board_ops: EventWriter<BoardOpEvent>,
+) {
+    let mut members = vec![];
+    for entity in selected.0.iter() {
+        let Ok((group_transform, children, &ObjectId(id))) =
+            groups.get(*entity)
+        else {
+            members.push(*entity);
+            continue;
+        };
+        board_ops.send(BoardOpEvent(BoardOp::Ungroup { id }));
+        for child in children.iter() {
+            let Ok(transform) = transforms.get(*child) else {
+                continue;
+            };
+            let mut child_commands = commands.entity(*child);
+            child_commands.insert(group_transform.mul_transform(*transform));
+            match entered.0 {
+                Some(parent) => child_commands.set_parent(parent),
+                None => child_commands.remove_parent(),
+            };
+            members.push(*child);
+        }
+        commands.entity(*entity).despawn_recursive();
+    }
+    selected.0 = members;
+}
+
+/// Picker 只读 EnteredGroup，进入与退出都通过 Commands 修改
+fn enter_group(
+    mut commands: Commands,
+    mut double_click_events: EventReader<DoubleClickEvent>,
+    picker: Picker,
+    world_touch_cursor: Res<WorldTouchCursor>,
+    groups: Query<(), With<Group>>,
+    mut selected: ResMut<Selected>,
+) {
+    if double_click_events.is_empty() {
+        return;
+    }
+    double_click_events.clear();
+    if let Some(group) = picker
+        .pick(&world_touch_cursor)
+        .filter(|entity| groups.contains(*entity))
+    {
+        commands.insert_resource(EnteredGroup(Some(group)));
+        selected.0.clear();
+    }
+}
+
+/// 点击组外时退出当前组
+fn exit_group(
+    mut commands: Commands,
+    picker: Picker,
+    world_touch_cursor: Res<WorldTouchCursor>,
+    groups: Query<(), With<Group>>,
+    parents: Query<&Parent>,
+    entered: Res<EnteredGroup>,
+) {
+    let Some(group) = entered.0 else {
+        return;
+    };
+    let inside = groups.contains(group)
+        && picker
+            .pick(&world_touch_cursor)
+            .is_some_and(|entity| picker.is_in_entered_group(entity));
+    if !inside {
+        // 嵌套的组逐层退出
+        let parent = parents
+            .get(group)
+            .ok()
+            .map(|parent| parent.get())
+            .filter(|_| groups.contains(group));
+        commands.insert_resource(EnteredGroup(parent));
+    }
+}
+
+fn draw_entered_group(
+    mut gizmos: Gizmos,
+    entered: Res<EnteredGroup>,
+    bounds: Query<(&Aabb, &GlobalTransform)>,
+) {
+    let Some(Ok((aabb, transform))) = entered.0.map(|e| bounds.get(e)) else {
+        return;
+    };
+    let rect = world_rect(aabb, transform);
+    gizmos.rect_2d(rect.center(), 0., rect.size(), Color::GRAY);
+}
+
+/// 成员都被删除后移除空组
+fn remove_empty_groups(
+    mut commands: Commands,
+    groups: Query<(Entity, Option<&Children>), With<Group>>,
+) {
+    for (entity, children) in groups.iter() {
+        if children.map_or(true, |children| children.is_empty()) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// 组的包围盒为成员包围盒的并集
+fn update_group_aabb(
+    mut commands: Commands,
+    groups: Query<(Entity, &Children, Option<&Aabb>), With<Group>>,
+    members: Query<(&Aabb, &Transform)>,
+) {
+    for (entity, children, aabb) in groups.iter() {
+        let Some(bounds) = children
+            .iter()
+            .filter_map(|child| members.get(*child).ok())
+            .map(|(aabb, transform)| local_rect(aabb, transform))
+            .reduce(|a, b| a.union(b))
+        else {
+            continue;
+        };
+        let new_aabb = Aabb {
+            center: bounds.center().extend(0.).into(),
+            half_extents: bounds.half_size().extend(0.).into(),
+        };
+        if aabb != Some(&new_aabb) {
+            commands.entity(entity).insert(new_aabb);
+        }
+    }
+}
@@ -0,0 +1,417 @@
+// W3C InkML 的 <trace> 与白板线条互转
+// https://www.w3.org/TR/InkML/
+use std::{collections::HashMap, fmt::Write, fs, io, path::Path};
+
+use bevy::{prelude::*, window::FileDragAndDrop};
+
+use crate::{
+    board_view::{BoardModel, BoardOpEvent},
+    document::{DocumentPath, StrokeData},
+    draw::LineSpawner,
+    keybindings::{action_just_pressed, actions},
+    recording::stroke_ops,
+    replay::ReplayState,
+};
+
+pub struct InkmlPlugin;
+
+impl Plugin for InkmlPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                import_dropped_inkml,
+                export_inkml.run_if(action_just_pressed(actions::EXPORT_INKML)),
+            )
+                .run_if(in_state(ReplayState::Off)),
+        );
+    }
+}
+
+const XML_NS: &str = "http://www.w3.org/XML/1998/namespace";
+const DEFAULT_BRUSH_WIDTH: f32 = 4.;
+
+/// 按是否有时间戳、压感选用的 context
+const CONTEXTS: [(&str, &[&str]); 4] = [
+    ("ctxXY", &["X", "Y"]),
+    ("ctxXYT", &["X", "Y", "T"]),
+    ("ctxXYF", &["X", "Y", "F"]),
+    ("ctxXYTF", &["X", "Y", "T", "F"]),
+];
+
+pub fn write_inkml(strokes: &[StrokeData]) -> String {
+    let mut brushes: Vec<(Color, f32)> = vec![];
+    let mut traces = String::new();
+    for stroke in strokes.iter().filter(|stroke| !stroke.points.is_empty()) {
+        let brush = (Color::from(stroke.color), stroke.width);
+        let brush_index = match brushes.iter().position(|b| *b == brush) {
+            Some(index) => index,
+            None => {
+                brushes.push(brush);
+                brushes.len() - 1
+            }
+        };
+        let has_times = stroke.times.len() == stroke.points.len();
+        let has_pressures = stroke.pressures.len() == stroke.points.len();
+        let (context, _) =
+            CONTEXTS[has_times as usize + 2 * has_pressures as usize];
+        let samples: Vec<String> = stroke
+            .points
+            .iter()
+            .enumerate()
+            .map(|(i, point)| {
+                let point = *point + stroke.offset;
+                let mut sample = format!("{} {}", point.x, -point.y);
+                if has_times {
+                    let ms = (stroke.times[i] * 1000.).round() as i64;
+                    let _ = write!(sample, " {}", ms);
+                }
+                if has_pressures {
+                    let _ = write!(sample, " {}", stroke.pressures[i]);
+                }
+                sample
+            })
+            .collect();
+        let _ = writeln!(
+            traces,
+            "  <trace xml:id=\"t{}\" contextRef=\"#{}\" \
+             brushRef=\"#br{}\">{}</trace>",
+            stroke.id,
+            context,
+            brush_index,
+            samples.join(", ")
+        );
+    }
+
+    let mut ink = String::new();
+    let _ = writeln!(ink, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>");
+    let _ = writeln!(ink, "<ink xmlns=\"http://www.w3.org/2003/InkML\">");
+    let _ = writeln!(ink, "  <definitions>");
+    for (id, channels) in CONTEXTS {
+        let _ = writeln!(ink, "    <context xml:id=\"{}\">", id);
+        let _ = writeln!(ink, "      <traceFormat>");
+        for channel in channels {
+            let attributes = match *channel {
+                "T" => "type=\"integer\" units=\"ms\"",
+                "F" => "type=\"decimal\" max=\"1\"",
+                _ => "type=\"decimal\"",
+            };
+            let _ = writeln!(
+                ink,
+                "        <channel name=\"{}\" {}/>",
+                channel, attributes
+            );
+        }
+        let _ = writeln!(ink, "      </traceFormat>");
+        let _ = writeln!(ink, "    </context>");
+    }
+    for (index, (color, width)) in brushes.iter().enumerate() {
+        let [r, g, b, a] = color.as_rgba_u8();
+        let _ = writeln!(ink, "    <brush xml:id=\"br{}\">", index);
+        let _ = writeln!(
+            ink,
+            "      <brushProperty name=\"width\" value=\"{}\"/>",
+            width
+        );
+        let _ = writeln!(
+            ink,
+            "      <brushProperty name=\"color\" \
+             value=\"#{:02X}{:02X}{:02X}\"/>",
+            r, g, b
+        );
+        let _ = writeln!(
+            ink,
+            "      <brushProperty name=\"transparency\" value=\"{}\"/>",
+            255 - a
+        );
+        let _ = writeln!(ink, "    </brush>");
+    }
+    let _ = writeln!(ink, "  </definitions>");
+    ink.push_str(&traces);
+    ink.push_str("</ink>\n");
+    ink
+}
+
+#[derive(Debug, Clone)]
+struct Channel {
+    name: String,
+    /** T 通道换算成秒的系数 */
+    scale: f32,
+    max: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Brush {
+    color: Color,
+    width: f32,
+}
+
+impl Default for Brush {
+    fn default() -> Self {
+        Brush {
+            color: Color::WHITE,
+            width: DEFAULT_BRUSH_WIDTH,
+        }
+    }
+}
+
+fn xml_id(node: roxmltree::Node) -> Option<String> {
+    node.attribute((XML_NS, "id")).map(str::to_string)
+}
+
+/// 引用形如 "#id"，只支持文档内引用
+fn reference<'a>(node: roxmltree::Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.ancestors()
+        .find_map(|ancestor| ancestor.attribute(name))
+        .map(|value| value.trim_start_matches('#'))
+}
+
+fn parse_trace_format(node: roxmltree::Node) -> Vec<Channel> {
+    node.children()
+        .filter(|child| child.has_tag_name("channel"))
+        .filter_map(|channel| {
+            let name = channel.attribute("name")?.to_string();
+            let scale = match channel.attribute("units") {
+                Some("s") => 1.,
+                _ => 0.001,
+            };
+            let max = channel.attribute("max").and_then(|max| max.parse().ok());
+            Some(Channel { name, scale, max })
+        })
+        .collect()
+}
+
+fn parse_brush(node: roxmltree::Node) -> Brush {
+    let mut brush = Brush::default();
+    for property in node
+        .children()
+        .filter(|child| child.has_tag_name("brushProperty"))
+    {
+        let value = property.attribute("value").unwrap_or_default();
+        match property.attribute("name") {
+            Some("width") => {
+                brush.width = value.parse().unwrap_or(brush.width);
+            }
+            Some("color") => {
+                let alpha = brush.color.a();
+                brush.color =
+                    Color::hex(value).unwrap_or(brush.color).with_a(alpha);
+            }
+            Some("transparency") => {
+                let transparency: f32 = value.parse().unwrap_or(0.);
+                brush.color.set_a(1. - transparency / 255.);
+            }
+            _ => {}
+        }
+    }
+    brush
+}
+
+#[derive(Clone, Copy)]
+enum Difference {
+    Explicit,
+    First,
+    Second,
+}
+
+/// 解析 trace 内容，支持 ! ' " 差分前缀
+fn parse_trace(text: &str, channels: usize) -> Vec<Vec<f32>> {
+    let mut modes = vec![Difference::Explicit; channels];
+    let mut values = vec![0.; channels];
+    let mut velocities = vec![0.; channels];
+    let mut samples = vec![];
+    for point in text.split(',') {
+        let mut tokens = vec![];
+        for word in point.split_whitespace() {
+            let mut rest = word;
+            while !rest.is_empty() {
+                let qualifier = rest
+                    .chars()
+                    .next()
+                    .filter(|c| matches!(c, '!' | '\'' | '"'));
+                if qualifier.is_some() {
+                    rest = &rest[1..];
+                }
+                let end = rest.find(['!', '\'', '"']).unwrap_or(rest.len());
+                tokens.push((qualifier, &rest[..end]));
+                rest = &rest[end..];
+            }
+        }
+        if tokens.is_empty() {
+            continue;
+        }
+        for (i, (qualifier, number)) in
+            tokens.into_iter().take(channels).enumerate()
+        {
+            match qualifier {
+                Some('!') => modes[i] = Difference::Explicit,
+                Some('\'') => modes[i] = Difference::First,
+                Some('"') => modes[i] = Difference::Second,
+                _ => {}
+            }
+            let number: f32 = number.parse().unwrap_or(0.);
+            match modes[i] {
+                Difference::Explicit => {
+                    velocities[i] = number - values[i];
+                    values[i] = number;
+                }
+                Difference::First => {
+                    velocities[i] = number;
+                    values[i] += number;
+                }
+                Difference::Second => {
+                    velocities[i] += number;
+                    values[i] += velocities[i];
+                }
+            }
+        }
+        samples.push(values.clone());
+    }
+    samples
+}
+
+/// 读取 InkML 中的所有 trace，线条的 id 与层级由调用方重新分配
+pub fn read_inkml(text: &str) -> Result<Vec<StrokeData>, roxmltree::Error> {
+    let document = roxmltree::Document::parse(text)?;
+
+    let default_format = vec![
+        Channel {
+            name: "X".to_string(),
+            scale: 1.,
+            max: None,
+        },
+        Channel {
+            name: "Y".to_string(),
+            scale: 1.,
+            max: None,
+        },
+    ];
+    let mut formats: HashMap<String, Vec<Channel>> = HashMap::new();
+    let mut ink_format = None;
+    for node in document
+        .descendants()
+        .filter(|node| node.has_tag_name("traceFormat"))
+    {
+        let channels = parse_trace_format(node);
+        if let Some(id) = xml_id(node) {
+            formats.insert(id, channels.clone());
+        }
+        match node.parent_element() {
+            Some(parent) if parent.has_tag_name("context") => {
+                if let Some(id) = xml_id(parent) {
+                    formats.insert(id, channels);
+                }
+            }
+            Some(parent) if parent.has_tag_name("ink") => {
+                ink_format = Some(channels);
+            }
+            _ => {}
+        }
+    }
+    for context in document
+        .descendants()
+        .filter(|node| node.has_tag_name("context"))
+    {
+        if let (Some(id), Some(format_ref)) =
+            (xml_id(context), reference(context, "traceFormatRef"))
+        {
+            if let Some(channels) = formats.get(format_ref).cloned() {
+                formats.entry(id).or_insert(channels);
+            }
+        }
+    }
+    let default_format = ink_format.unwrap_or(default_format);
+
+    let brushes: HashMap<String, Brush> = document
+        .descendants()
+        .filter(|node| node.has_tag_name("brush"))
+        .filter_map(|node| Some((xml_id(node)?, parse_brush(node))))
+        .collect();
+
+    let mut strokes = vec![];
+    for trace in document
+        .descendants()
+        .filter(|node| node.has_tag_name("trace"))
+    {
+        let channels = reference(trace, "contextRef")
+            .and_then(|id| formats.get(id))
+            .unwrap_or(&default_format);
+        let brush = reference(trace, "brushRef")
+            .and_then(|id| brushes.get(id))
+            .copied()
+            .unwrap_or_default();
+        let position = |name: &str| {
+            channels.iter().position(|channel| channel.name == name)
+        };
+        let (Some(x), Some(y)) = (position("X"), position("Y")) else {
+            continue;
+        };
+        let samples =
+            parse_trace(trace.text().unwrap_or_default(), channels.len());
+
+        let mut stroke = StrokeData {
+            color: brush.color.into(),
+            width: brush.width,
+            points: samples
+                .iter()
+                .map(|sample| Vec2::new(sample[x], -sample[y]))
+                .collect(),
+            ..default()
+        };
+        if let Some(t) = position("T") {
+            let start = samples.first().map_or(0., |sample| sample[t]);
+            stroke.times = samples
+                .iter()
+                .map(|sample| (sample[t] - start) * channels[t].scale)
+                .collect();
+        }
+        if let Some(f) = position("F") {
+            let max = channels[f].max.unwrap_or(1.);
+            stroke.pressures =
+                samples.iter().map(|sample| sample[f] / max).collect();
+        }
+        strokes.push(stroke);
+    }
+    Ok(strokes)
+}
+
+fn load_inkml(path: &Path) -> io::Result<Vec<StrokeData>> {
+    read_inkml(&fs::read_to_string(path)?)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+fn import_dropped_inkml(
+    mut drop_events: EventReader<FileDragAndDrop>,
+    mut spawner: LineSpawner,
+    mut board_ops: EventWriter<BoardOpEvent>,
+) {
+    for event in drop_events.read() {
+        let FileDragAndDrop::DroppedFile { path_buf, .. } = event else {
+            continue;
+        };
+        if path_buf.extension().map_or(true, |ext| ext != "inkml") {
+            continue;
+        }
+        match load_inkml(path_buf) {
+            Ok(strokes) => {
+                for mut stroke in strokes {
+                    (stroke.id, stroke.layer) = spawner.next();
+                    spawner.spawn(&stroke);
+                    board_ops.send_batch(
+                        stroke_ops(&stroke).into_iter().map(BoardOpEvent),
+                    );
+                }
+                info!("imported {:?}", path_buf);
+            }
+            Err(err) => error!("failed to import {:?}: {}", path_buf, err),
+        }
+    }
+}
+
+fn export_inkml(path: Res<DocumentPath>, model: Res<BoardModel>) {
+    let path = path.0.with_extension("inkml");
+    match fs::write(&path, write_inkml(&model.0.page().to_strokes())) {
+        Ok(()) => info!("exported board to {:?}", path),
+        Err(err) => error!("failed to export {:?}: {}", path, err),
+    }
+}
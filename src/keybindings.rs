@@ -0,0 +1,535 @@
+// 1. 快捷键集中在一张动作表里：动作名 -> 按键组合（含修饰键）
+// 2. 启动时从 keybindings.json 读取用户的设置覆盖默认值，并检查冲突
+// 3. 各系统通过 action_just_pressed 等运行条件使用
+// 4. 动作注册表记录每个动作的标题，命令面板等也可以直接触发动作
+use std::{
+    collections::BTreeMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use bevy::{ecs::system::SystemParam, input::InputSystem, prelude::*};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+pub struct KeyBindingsPlugin;
+
+impl Plugin for KeyBindingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<KeyBindingsPath>()
+            .init_resource::<KeyBindings>()
+            .init_resource::<ActionRegistry>()
+            .init_resource::<PendingActions>()
+            .init_resource::<TriggeredActions>()
+            .add_systems(PreStartup, load_key_bindings)
+            .add_systems(PreUpdate, apply_pending_actions.after(InputSystem));
+    }
+}
+
+pub mod actions {
+    pub const TOOL_CURSOR: &str = "tool.cursor";
+    pub const TOOL_PEN: &str = "tool.pen";
+    pub const TOOL_ERASER: &str = "tool.eraser";
+    pub const TOOL_EYEDROPPER: &str = "tool.eyedropper";
+    pub const TOOL_LASER: &str = "tool.laser";
+    pub const MOVE_CAMERA: &str = "move_camera";
+    pub const DEBUG_MODE: &str = "debug_mode";
+    pub const WIREFRAME: &str = "wireframe";
+    pub const CLEAR: &str = "clear";
+    pub const UNDO: &str = "undo";
+    pub const BRUSH_SMALLER: &str = "brush.smaller";
+    pub const BRUSH_LARGER: &str = "brush.larger";
+    pub const COPY: &str = "copy";
+    pub const CUT: &str = "cut";
+    pub const PASTE: &str = "paste";
+    pub const DUPLICATE: &str = "duplicate";
+    pub const DELETE: &str = "delete";
+    pub const GROUP: &str = "group";
+    pub const UNGROUP: &str = "ungroup";
+    pub const LOCK: &str = "lock";
+    pub const SAVE: &str = "save";
+    pub const OPEN: &str = "open";
+    pub const EXPORT_EXCALIDRAW: &str = "export.excalidraw";
+    pub const EXPORT_INKML: &str = "export.inkml";
+    pub const NEXT_BACKGROUND: &str = "background.next";
+    pub const REPLAY: &str = "replay.toggle";
+    pub const REPLAY_EXPORT: &str = "replay.export";
+    pub const REPLAY_PAUSE: &str = "replay.pause";
+    pub const REPLAY_SLOWER: &str = "replay.slower";
+    pub const REPLAY_FASTER: &str = "replay.faster";
+    pub const COMMAND_PALETTE: &str = "command_palette";
+    pub const BRING_TO_FRONT: &str = "layer.front";
+    pub const SEND_TO_BACK: &str = "layer.back";
+    pub const ZOOM_IN: &str = "zoom.in";
+    pub const ZOOM_OUT: &str = "zoom.out";
+    pub const ZOOM_RESET: &str = "zoom.reset";
+    pub const TOGGLE_GRID_SNAP: &str = "snap.grid";
+    pub const TOGGLE_PEN_SNAP: &str = "snap.pen";
+    pub const TOGGLE_GUIDES: &str = "snap.guides";
+    pub const NEXT_BRUSH: &str = "brush.next";
+    pub const RECOVERY_RESTORE: &str = "recovery.restore";
+    pub const RECOVERY_DISMISS: &str = "recovery.dismiss";
+    pub const PAGE_NEW: &str = "page.new";
+    pub const PAGE_NEXT: &str = "page.next";
+    pub const PAGE_PREVIOUS: &str = "page.previous";
+    pub const COLORS: [&str; 8] = [
+        "color.1", "color.2", "color.3", "color.4", "color.5", "color.6",
+        "color.7", "color.8",
+    ];
+}
+
+/// 一个按键加上必须同时按下的修饰键，未列出的修饰键必须松开
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct KeyChord {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+    pub key: KeyCode,
+}
+
+impl KeyChord {
+    pub const fn key(key: KeyCode) -> Self {
+        KeyChord {
+            ctrl: false,
+            shift: false,
+            alt: false,
+            key,
+        }
+    }
+
+    pub const fn ctrl(key: KeyCode) -> Self {
+        KeyChord {
+            ctrl: true,
+            ..KeyChord::key(key)
+        }
+    }
+
+    pub const fn shift(key: KeyCode) -> Self {
+        KeyChord {
+            shift: true,
+            ..KeyChord::key(key)
+        }
+    }
+
+    pub const fn ctrl_shift(key: KeyCode) -> Self {
+        KeyChord {
+            ctrl: true,
+            shift: true,
+            ..KeyChord::key(key)
+        }
+    }
+
+    fn modifiers_match(&self, input: &Input<KeyCode>) -> bool {
+        self.ctrl
+            == input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+            && self.shift
+                == input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight])
+            && self.alt
+                == input.any_pressed([KeyCode::AltLeft, KeyCode::AltRight])
+    }
+
+    pub fn just_pressed(&self, input: &Input<KeyCode>) -> bool {
+        input.just_pressed(self.key) && self.modifiers_match(input)
+    }
+
+    pub fn pressed(&self, input: &Input<KeyCode>) -> bool {
+        input.pressed(self.key) && self.modifiers_match(input)
+    }
+
+    /// 松开时不检查修饰键，先松开修饰键也能结束按住的动作
+    pub fn just_released(&self, input: &Input<KeyCode>) -> bool {
+        input.just_released(self.key)
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if self.shift {
+            write!(f, "Shift+")?;
+        }
+        if self.alt {
+            write!(f, "Alt+")?;
+        }
+        write!(f, "{:?}", self.key)
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = String;
+
+    /// 形如 "Ctrl+Shift+G"，按键名与 KeyCode 的变体名一致
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        let key = parts.pop().filter(|key| !key.is_empty());
+        let key = key.ok_or_else(|| format!("missing key in {:?}", s))?;
+        let key: KeyCode =
+            serde_json::from_value(serde_json::Value::String(key.into()))
+                .map_err(|_| format!("unknown key {:?} in {:?}", key, s))?;
+        let mut chord = KeyChord::key(key);
+        for modifier in parts {
+            match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => chord.ctrl = true,
+                "shift" => chord.shift = true,
+                "alt" => chord.alt = true,
+                _ => {
+                    return Err(format!(
+                        "unknown modifier {:?} in {:?}",
+                        modifier, s
+                    ))
+                }
+            }
+        }
+        Ok(chord)
+    }
+}
+
+impl Serialize for KeyChord {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyChord {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// 用户设置文件的位置
+#[derive(Resource)]
+pub struct KeyBindingsPath(pub PathBuf);
+
+impl Default for KeyBindingsPath {
+    fn default() -> Self {
+        KeyBindingsPath("keybindings.json".into())
+    }
+}
+
+/// 动作表，一个动作可以绑定多个按键组合，空列表表示不绑定
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct KeyBindings(pub BTreeMap<String, Vec<KeyChord>>);
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        use actions::*;
+        use KeyCode::*;
+        let mut bindings: Vec<(&str, Vec<KeyChord>)> = vec![
+            (MOVE_CAMERA, vec![KeyChord::key(Space)]),
+            (DEBUG_MODE, vec![KeyChord::key(Escape)]),
+            (WIREFRAME, vec![KeyChord::key(Tab)]),
+            (CLEAR, vec![KeyChord::key(C)]),
+            (UNDO, vec![KeyChord::ctrl(Z)]),
+            (BRUSH_SMALLER, vec![KeyChord::key(BracketLeft)]),
+            (BRUSH_LARGER, vec![KeyChord::key(BracketRight)]),
+            (COPY, vec![KeyChord::ctrl(C)]),
+            (CUT, vec![KeyChord::ctrl(X)]),
+            (PASTE, vec![KeyChord::ctrl(V)]),
+            (DUPLICATE, vec![KeyChord::ctrl(D)]),
+            (DELETE, vec![KeyChord::key(Delete), KeyChord::key(Back)]),
+            (GROUP, vec![KeyChord::ctrl(G)]),
+            (UNGROUP, vec![KeyChord::ctrl_shift(G)]),
+            (LOCK, vec![KeyChord::ctrl(L)]),
+            (SAVE, vec![KeyChord::ctrl(S)]),
+            (OPEN, vec![KeyChord::ctrl(O)]),
+            (EXPORT_EXCALIDRAW, vec![KeyChord::ctrl_shift(E)]),
+            (EXPORT_INKML, vec![KeyChord::ctrl_shift(I)]),
+            (NEXT_BACKGROUND, vec![KeyChord::ctrl(B)]),
+            (REPLAY, vec![KeyChord::key(F5)]),
+            (REPLAY_EXPORT, vec![KeyChord::key(F6)]),
+            (REPLAY_PAUSE, vec![KeyChord::key(P)]),
+            (REPLAY_SLOWER, vec![KeyChord::key(Minus)]),
+            (REPLAY_FASTER, vec![KeyChord::key(Equals)]),
+            (
+                COMMAND_PALETTE,
+                vec![KeyChord::ctrl(K), KeyChord::ctrl_shift(P)],
+            ),
+        ];
+        let digits = [Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8];
+        for (action, key) in COLORS.iter().zip(digits) {
+            bindings.push((action, vec![KeyChord::shift(key)]));
+        }
+        KeyBindings(
+            bindings
+                .into_iter()
+                .map(|(action, chords)| (action.to_string(), chords))
+                .collect(),
+        )
+    }
+}
+
+impl KeyBindings {
+    /// 读取用户设置，文件中出现的动作覆盖当前绑定
+    pub fn load(&mut self, path: &Path) -> io::Result<()> {
+        let overrides: BTreeMap<String, Vec<KeyChord>> =
+            serde_json::from_str(&fs::read_to_string(path)?)?;
+        for (action, chords) in overrides {
+            if !self.0.contains_key(&action) {
+                warn!("unknown action {:?} in {:?}", action, path);
+            }
+            self.0.insert(action, chords);
+        }
+        Ok(())
+    }
+
+    pub fn chords(&self, action: &str) -> &[KeyChord] {
+        self.0.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn just_pressed(&self, action: &str, input: &Input<KeyCode>) -> bool {
+        self.chords(action)
+            .iter()
+            .any(|chord| chord.just_pressed(input))
+    }
+
+    pub fn pressed(&self, action: &str, input: &Input<KeyCode>) -> bool {
+        self.chords(action).iter().any(|chord| chord.pressed(input))
+    }
+
+    pub fn just_released(&self, action: &str, input: &Input<KeyCode>) -> bool {
+        self.chords(action)
+            .iter()
+            .any(|chord| chord.just_released(input))
+    }
+
+    /// 绑定到多个动作的按键组合
+    pub fn conflicts(&self) -> Vec<(KeyChord, Vec<&str>)> {
+        let mut by_chord: BTreeMap<KeyChord, Vec<&str>> = BTreeMap::new();
+        for (action, chords) in self.0.iter() {
+            for chord in chords {
+                by_chord.entry(*chord).or_default().push(action);
+            }
+        }
+        by_chord
+            .into_iter()
+            .filter(|(_, actions)| actions.len() > 1)
+            .collect()
+    }
+}
+
+fn load_key_bindings(
+    path: Res<KeyBindingsPath>,
+    mut bindings: ResMut<KeyBindings>,
+) {
+    if path.0.exists() {
+        if let Err(err) = bindings.load(&path.0) {
+            error!("failed to load {:?}: {}", path.0, err);
+        }
+    }
+    for (chord, actions) in bindings.conflicts() {
+        warn!("{} is bound to more than one action: {:?}", chord, actions);
+    }
+}
+
+/// 注册表中的一个动作
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActionInfo {
+    pub id: String,
+    pub title: String,
+    /** 需要按住的动作，不能从命令面板执行 */
+    pub hold: bool,
+}
+
+/// 所有可以执行的动作，按注册顺序排列
+#[derive(Resource, Debug, Clone)]
+pub struct ActionRegistry(pub Vec<ActionInfo>);
+
+impl Default for ActionRegistry {
+    fn default() -> Self {
+        use actions::*;
+        let mut titles = vec![
+            (MOVE_CAMERA, "Move camera (hold)"),
+            (DEBUG_MODE, "Toggle debug mode"),
+            (WIREFRAME, "Toggle wireframe (hold)"),
+            (CLEAR, "Clear board"),
+            (UNDO, "Undo"),
+            (BRUSH_SMALLER, "Brush: Smaller"),
+            (BRUSH_LARGER, "Brush: Larger"),
+            (COPY, "Copy"),
+            (CUT, "Cut"),
+            (PASTE, "Paste"),
+            (DUPLICATE, "Duplicate"),
+            (DELETE, "Delete selection"),
+            (GROUP, "Group"),
+            (UNGROUP, "Ungroup"),
+            (LOCK, "Lock / unlock selection"),
+            (SAVE, "Save board"),
+            (OPEN, "Open board"),
+            (EXPORT_EXCALIDRAW, "Export: Excalidraw"),
+            (EXPORT_INKML, "Export: InkML"),
+            (NEXT_BACKGROUND, "Background: Next pattern"),
+            (REPLAY, "Replay: Start / stop"),
+            (REPLAY_EXPORT, "Replay: Export frames"),
+            (REPLAY_PAUSE, "Replay: Pause"),
+            (REPLAY_SLOWER, "Replay: Slower"),
+            (REPLAY_FASTER, "Replay: Faster"),
+            (COMMAND_PALETTE, "Command palette"),
+        ];
+        let color_titles: Vec<String> = (1..=COLORS.len())
+            .map(|i| format!("Color: Preset {}", i))
+            .collect();
+        for (action, title) in COLORS.iter().zip(color_titles.iter()) {
+            titles.push((*action, title.as_str()));
+        }
+        ActionRegistry(
+            titles
+                .into_iter()
+                .map(|(id, title)| ActionInfo {
+                    id: id.into(),
+                    title: title.into(),
+                    hold: [MOVE_CAMERA, WIREFRAME].contains(&id),
+                })
+                .collect(),
+        )
+    }
+}
+
+impl ActionRegistry {
+    pub fn get(&self, id: &str) -> Option<&ActionInfo> {
+        self.0.iter().find(|info| info.id == id)
+    }
+
+    /// 重复注册时只更新标题
+    pub fn register(&mut self, id: &str, title: &str) {
+        match self.0.iter_mut().find(|info| info.id == id) {
+            Some(info) => info.title = title.into(),
+            None => self.0.push(ActionInfo {
+                id: id.into(),
+                title: title.into(),
+                hold: false,
+            }),
+        }
+    }
+}
+
+/// 插件通过 App 注册自己的动作
+pub trait RegisterAction {
+    fn register_action(&mut self, id: &str, title: &str) -> &mut Self;
+
+    /// 同时提供默认按键，已有绑定时保持不变
+    fn register_action_with_keys(
+        &mut self,
+        id: &str,
+        title: &str,
+        chords: Vec<KeyChord>,
+    ) -> &mut Self;
+}
+
+impl RegisterAction for App {
+    fn register_action(&mut self, id: &str, title: &str) -> &mut Self {
+        self.register_action_with_keys(id, title, vec![])
+    }
+
+    fn register_action_with_keys(
+        &mut self,
+        id: &str,
+        title: &str,
+        chords: Vec<KeyChord>,
+    ) -> &mut Self {
+        self.world
+            .get_resource_or_insert_with(ActionRegistry::default)
+            .register(id, title);
+        self.world
+            .get_resource_or_insert_with(KeyBindings::default)
+            .0
+            .entry(id.into())
+            .or_insert(chords);
+        self
+    }
+}
+
+/// 等待执行的动作，下一帧开始时生效
+#[derive(Resource, Default, Debug)]
+pub struct PendingActions(pub Vec<String>);
+
+impl PendingActions {
+    pub fn trigger(&mut self, action: &str) {
+        self.0.push(action.into());
+    }
+}
+
+/// 本帧被直接触发的动作，视为按下了一次绑定的按键
+#[derive(Resource, Default, Debug)]
+pub struct TriggeredActions(pub Vec<String>);
+
+impl TriggeredActions {
+    pub fn contains(&self, action: &str) -> bool {
+        self.0.iter().any(|triggered| triggered == action)
+    }
+}
+
+pub fn apply_pending_actions(
+    mut pending: ResMut<PendingActions>,
+    mut triggered: ResMut<TriggeredActions>,
+) {
+    triggered.0 = std::mem::take(&mut pending.0);
+}
+
+/// 同时检查按键与直接触发的动作
+#[derive(SystemParam)]
+pub struct ActionInput<'w> {
+    input: Res<'w, Input<KeyCode>>,
+    bindings: Res<'w, KeyBindings>,
+    triggered: Res<'w, TriggeredActions>,
+}
+
+impl ActionInput<'_> {
+    /// 只检查直接触发，不检查按键
+    pub fn triggered(&self, action: &str) -> bool {
+        self.triggered.contains(action)
+    }
+
+    pub fn just_pressed(&self, action: &str) -> bool {
+        self.triggered.contains(action)
+            || self.bindings.just_pressed(action, &self.input)
+    }
+
+    pub fn pressed(&self, action: &str) -> bool {
+        self.triggered.contains(action)
+            || self.bindings.pressed(action, &self.input)
+    }
+
+    pub fn just_released(&self, action: &str) -> bool {
+        self.bindings.just_released(action, &self.input)
+    }
+}
+
+pub fn action_just_pressed(
+    action: &'static str,
+) -> impl Fn(ActionInput) -> bool + Clone {
+    move |input: ActionInput| input.just_pressed(action)
+}
+
+pub fn action_pressed(
+    action: &'static str,
+) -> impl Fn(ActionInput) -> bool + Clone {
+    move |input: ActionInput| input.pressed(action)
+}
+
+pub fn action_just_released(
+    action: &'static str,
+) -> impl Fn(ActionInput) -> bool + Clone {
+    move |input: ActionInput| input.just_released(action)
+}
+
+/// 与 input_toggle_active 相同，每次按下动作时切换
+pub fn action_toggle_active(
+    default: bool,
+    action: &'static str,
+) -> impl FnMut(ActionInput, Local<bool>) -> bool {
+    move |input: ActionInput, mut toggled: Local<bool>| {
+        if input.just_pressed(action) {
+            *toggled = !*toggled;
+        }
+        default ^ *toggled
+    }
+}
@@ -0,0 +1,134 @@
+// 激光笔：跟随指针留下会渐渐消失的发光轨迹，不生成线条，也不记录操作
+use bevy::{prelude::*, window::RequestRedraw};
+
+use crate::{
+    cursor::WorldTouchCursor,
+    keybindings::{actions, KeyChord},
+    projection_2d_control::MainCamera,
+    states::{RunMode, ToolButton},
+    tools::{RegisterTool, ToolDescriptor},
+    ui::is_hover_tool_button_bar,
+};
+
+pub struct LaserPlugin;
+
+impl Plugin for LaserPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LaserConfig>()
+            .init_resource::<LaserTrail>()
+            .register_tool(
+                ToolDescriptor::new(
+                    ToolButton::Laser,
+                    "Tool: Laser pointer",
+                    Vec2::new(4., 0.),
+                )
+                .with_action(actions::TOOL_LASER)
+                .with_key(KeyChord::key(KeyCode::Key5))
+                .on_update(
+                    record_laser_point
+                        .before(fade_laser_trail)
+                        .run_if(in_state(RunMode::Normal))
+                        .run_if(not(is_hover_tool_button_bar)),
+                )
+                .on_exit(clear_laser_trail),
+            )
+            .add_systems(
+                Update,
+                (
+                    fade_laser_trail,
+                    draw_laser_trail,
+                    request_redraw.run_if(laser_trail_visible),
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct LaserConfig {
+    /** 轨迹保留的秒数 */
+    pub duration: f32,
+    pub color: Color,
+    /** 光点的半径（屏幕像素） */
+    pub radius: f32,
+}
+
+impl Default for LaserConfig {
+    fn default() -> Self {
+        LaserConfig {
+            duration: 1.,
+            color: Color::rgb(1., 0.15, 0.1),
+            radius: 5.,
+        }
+    }
+}
+
+/// 轨迹上的点与记录时间
+#[derive(Resource, Default)]
+struct LaserTrail(Vec<(Vec2, f32)>);
+
+fn record_laser_point(
+    world_touch_cursor: Res<WorldTouchCursor>,
+    time: Res<Time<Real>>,
+    mut trail: ResMut<LaserTrail>,
+) {
+    let point = world_touch_cursor.0;
+    let now = time.elapsed_seconds();
+    match trail.0.last_mut() {
+        // 指针不动时只刷新时间，光点保持明亮
+        Some((last, t)) if *last == point => *t = now,
+        _ => trail.0.push((point, now)),
+    }
+}
+
+fn fade_laser_trail(
+    time: Res<Time<Real>>,
+    config: Res<LaserConfig>,
+    mut trail: ResMut<LaserTrail>,
+) {
+    let now = time.elapsed_seconds();
+    trail.0.retain(|(_, t)| now - *t < config.duration);
+}
+
+fn draw_laser_trail(
+    mut gizmos: Gizmos,
+    trail: Res<LaserTrail>,
+    config: Res<LaserConfig>,
+    time: Res<Time<Real>>,
+    q_proj: Query<&OrthographicProjection, With<MainCamera>>,
+) {
+    let Some(&(head, head_time)) = trail.0.last() else {
+        return;
+    };
+    let now = time.elapsed_seconds();
+    let alpha = |t: f32| (1. - (now - t) / config.duration).clamp(0., 1.);
+    gizmos.linestrip_gradient_2d(
+        trail
+            .0
+            .iter()
+            .map(|(point, t)| (*point, config.color.with_a(alpha(*t)))),
+    );
+
+    // 光点外圈逐渐变淡，模拟光晕
+    let scale = q_proj.get_single().map_or(1., |proj| proj.scale);
+    let head_alpha = alpha(head_time);
+    for ring in 0..4 {
+        let radius = config.radius * scale * (1. + ring as f32 * 0.6);
+        let color = config.color.with_a(head_alpha / (1. + ring as f32 * 2.));
+        gizmos.circle_2d(head, radius, color);
+    }
+    gizmos.circle_2d(head, config.radius * scale * 0.4, Color::WHITE);
+}
+
+fn laser_trail_visible(trail: Res<LaserTrail>) -> bool {
+    !trail.0.is_empty()
+}
+
+/// 指针停下后轨迹仍要继续变淡，不等待输入事件
+fn request_redraw(mut redraw: EventWriter<RequestRedraw>) {
+    redraw.send(RequestRedraw);
+}
+
+fn clear_laser_trail(mut trail: ResMut<LaserTrail>) {
+    trail.0.clear();
+}
@@ -6,11 +6,9 @@ pub enum Layer {
     Foreground(i8),
 }
 
-pub fn update_z_coordinate_based_on_layer(
-    mut query: Query<(&mut Transform, &Layer), Changed<Layer>>,
-) {
-    for (mut transform, layer) in query.iter_mut() {
-        transform.translation.z = match layer {
+impl Layer {
+    pub fn z(&self) -> f32 {
+        match self {
             // Layer::Background(order_in_layer) => -1. + *order_in_layer as f32 / 1000.,
             Layer::Foreground(order_in_layer) => {
                 0. + *order_in_layer as f32 / 1000.
@@ -18,3 +16,11 @@ pub fn update_z_coordinate_based_on_layer(
         }
     }
 }
+
+pub fn update_z_coordinate_based_on_layer(
+    mut query: Query<(&mut Transform, &Layer), Changed<Layer>>,
+) {
+    for (mut transform, layer) in query.iter_mut() {
+        transform.translation.z = layer.z();
+    }
+}
@@ -15,4 +15,33 @@ pub mod focus;
 pub mod shapes;
 pub mod selected;
 pub mod double_click;
-pub mod text_input;
\ No newline at end of file
+pub mod text_input;
+pub mod document;
+pub mod recording;
+pub mod replay;
+pub mod autosave;
+pub mod board_text;
+pub mod excalidraw;
+pub mod inkml;
+pub mod board_image;
+pub mod clipboard;
+pub mod group;
+pub mod palette;
+pub mod eyedropper;
+pub mod brush_size;
+pub mod brush;
+pub mod background;
+pub mod snap;
+pub mod laser;
+pub mod keybindings;
+pub mod command_palette;
+pub mod tools;
+pub mod plugins;
+pub mod board;
+pub mod board_view;
+pub mod collab;
+pub mod collab_server;
+pub mod crdt;
+pub mod api;
+pub mod viewer;
+pub mod pages;
@@ -0,0 +1,58 @@
+// 多页白板：新建页面，切换到上一页或下一页；切换后按模型重建视图（见 board_view.rs）
+use bevy::prelude::*;
+
+use crate::{
+    board_view::{BoardModel, BoardOpEvent},
+    keybindings::{action_just_pressed, actions, KeyChord, RegisterAction},
+    recording::BoardOp,
+    replay::ReplayState,
+};
+
+pub struct PagesPlugin;
+
+impl Plugin for PagesPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_action_with_keys(
+            actions::PAGE_NEW,
+            "Page: New",
+            vec![KeyChord::ctrl_shift(KeyCode::N)],
+        )
+        .register_action_with_keys(
+            actions::PAGE_NEXT,
+            "Page: Next",
+            vec![KeyChord::key(KeyCode::PageDown)],
+        )
+        .register_action_with_keys(
+            actions::PAGE_PREVIOUS,
+            "Page: Previous",
+            vec![KeyChord::key(KeyCode::PageUp)],
+        )
+        .add_systems(
+            Update,
+            (
+                new_page.run_if(action_just_pressed(actions::PAGE_NEW)),
+                select_page(1).run_if(action_just_pressed(actions::PAGE_NEXT)),
+                select_page(-1)
+                    .run_if(action_just_pressed(actions::PAGE_PREVIOUS)),
+            )
+                .run_if(in_state(ReplayState::Off)),
+        );
+    }
+}
+
+fn new_page(model: Res<BoardModel>, mut board_ops: EventWriter<BoardOpEvent>) {
+    let name = format!("Page {}", model.0.pages().len() + 1);
+    board_ops.send(BoardOpEvent(BoardOp::AddPage { name }));
+}
+
+/// 在第一页与最后一页停住
+fn select_page(
+    step: isize,
+) -> impl FnMut(Res<BoardModel>, EventWriter<BoardOpEvent>) {
+    move |model, mut board_ops| {
+        let index = model.0.current().saturating_add_signed(step);
+        if index < model.0.pages().len() && index != model.0.current() {
+            board_ops.send(BoardOpEvent(BoardOp::SelectPage { index }));
+        }
+    }
+}
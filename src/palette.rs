@@ -0,0 +1,526 @@
+// 1. 工具栏上的调色板：预设粉笔色、HSV 选色、十六进制输入、最近使用的颜色
+// 2. Shift + 数字键选择预设颜色，不与切换工具的数字键冲突
+use bevy::{
+    input::{keyboard::KeyboardInput, InputSystem},
+    prelude::*,
+    render::render_resource::{AsBindGroup, ShaderRef},
+    ui::RelativeCursorPosition,
+};
+
+use crate::{
+    board_view::BoardOpEvent,
+    cursor::Cursor,
+    keybindings::{actions, ActionInput},
+    recording::BoardOp,
+    states::{RunMode, ToolButton},
+    ui::{TOOL_BUTTON_BACKGROUND, TOOL_BUTTON_FOCUS},
+};
+
+pub struct PalettePlugin;
+
+impl Plugin for PalettePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(UiMaterialPlugin::<HsvUiMaterial>::default())
+            .init_resource::<Palette>()
+            .init_resource::<HexInput>()
+            .add_systems(
+                PreUpdate,
+                capture_hex_input
+                    .after(InputSystem)
+                    .run_if(|hex_input: Res<HexInput>| hex_input.editing),
+            )
+            .add_systems(
+                Update,
+                (
+                    toggle_popover,
+                    pick_swatch,
+                    pick_saturation_value,
+                    pick_hue,
+                    focus_hex_input,
+                    select_preset_by_key,
+                )
+                    .run_if(in_state(RunMode::Normal)),
+            )
+            .add_systems(
+                Update,
+                (
+                    remember_stroke_colors,
+                    apply_palette_color
+                        .run_if(in_state(ToolButton::Pen))
+                        .run_if(
+                            resource_changed::<Palette>()
+                                .or_else(resource_changed::<Cursor>()),
+                        ),
+                    update_palette_ui.run_if(
+                        resource_changed::<Palette>()
+                            .or_else(resource_changed::<HexInput>()),
+                    ),
+                ),
+            );
+    }
+}
+
+/// 黑板上常用的粉笔色
+pub const CHALK_COLORS: [Color; 8] = [
+    Color::rgb(0.95, 0.95, 0.92),
+    Color::rgb(0.98, 0.89, 0.45),
+    Color::rgb(0.96, 0.6, 0.72),
+    Color::rgb(0.55, 0.75, 0.95),
+    Color::rgb(0.6, 0.88, 0.6),
+    Color::rgb(0.98, 0.68, 0.4),
+    Color::rgb(0.93, 0.4, 0.4),
+    Color::rgb(0.75, 0.62, 0.92),
+];
+
+const RECENT_LEN: usize = 8;
+
+#[derive(Resource)]
+pub struct Palette {
+    /** 新笔画使用的颜色 */
+    pub current: Color,
+    /** 选色器的色相，饱和度为 0 时颜色本身无法保留色相 */
+    pub hue: f32,
+    /** 最近画过的颜色，最新的在前 */
+    pub recent: Vec<Color>,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette {
+            current: Color::WHITE,
+            hue: 0.,
+            recent: vec![],
+        }
+    }
+}
+
+impl Palette {
+    pub fn set(&mut self, color: Color) {
+        let hsv = rgb_to_hsv(color);
+        if hsv.y > 0. {
+            self.hue = hsv.x;
+        }
+        self.current = color;
+    }
+
+    pub fn set_hsv(&mut self, hue: f32, saturation: f32, value: f32) {
+        self.hue = hue;
+        self.current = hsv_to_rgb(Vec3::new(hue, saturation, value));
+    }
+
+    pub fn remember(&mut self, color: Color) {
+        if self.recent.first() == Some(&color) {
+            return;
+        }
+        self.recent.retain(|recent| *recent != color);
+        self.recent.insert(0, color);
+        self.recent.truncate(RECENT_LEN);
+    }
+}
+
+/// 色相、饱和度、明度均在 0..1 之间
+pub fn rgb_to_hsv(color: Color) -> Vec3 {
+    let [r, g, b, _] = color.as_rgba_f32();
+    let max = r.max(g).max(b);
+    let delta = max - r.min(g).min(b);
+    let hue = if delta == 0. {
+        0.
+    } else if max == r {
+        ((g - b) / delta).rem_euclid(6.) / 6.
+    } else if max == g {
+        ((b - r) / delta + 2.) / 6.
+    } else {
+        ((r - g) / delta + 4.) / 6.
+    };
+    let saturation = if max == 0. { 0. } else { delta / max };
+    Vec3::new(hue, saturation, max)
+}
+
+pub fn hsv_to_rgb(hsv: Vec3) -> Color {
+    let channel = |n: f32| {
+        let k = (n + hsv.x * 6.) % 6.;
+        hsv.z - hsv.z * hsv.y * k.min(4. - k).clamp(0., 1.)
+    };
+    Color::rgb(channel(5.), channel(3.), channel(1.))
+}
+
+pub fn color_to_hex(color: Color) -> String {
+    let [r, g, b, _] = color.as_rgba_u8();
+    format!("{:02X}{:02X}{:02X}", r, g, b)
+}
+
+#[derive(Resource, Default)]
+struct HexInput {
+    editing: bool,
+    text: String,
+}
+
+#[derive(AsBindGroup, Asset, TypePath, Debug, Clone)]
+struct HsvUiMaterial {
+    #[uniform(0)]
+    hue: f32,
+    /** 0 为饱和度-明度方块，1 为色相条 */
+    #[uniform(1)]
+    mode: u32,
+}
+
+impl UiMaterial for HsvUiMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/hsv_picker.wgsl".into()
+    }
+}
+
+/// 工具栏上显示当前颜色的按钮
+#[derive(Component)]
+pub struct PaletteButton;
+
+#[derive(Component)]
+pub struct PalettePopover;
+
+#[derive(Component)]
+struct PalettePopoverRoot;
+
+#[derive(Component)]
+struct ColorSwatch(Color);
+
+#[derive(Component)]
+struct SaturationValueArea;
+
+#[derive(Component)]
+struct HueStrip;
+
+#[derive(Component)]
+struct HexInputField;
+
+#[derive(Component)]
+struct HexText;
+
+#[derive(Component)]
+struct RecentColors;
+
+fn swatch(color: Color) -> impl Bundle {
+    (
+        NodeBundle {
+            style: Style {
+                width: Val::Px(24.),
+                height: Val::Px(24.),
+                ..default()
+            },
+            background_color: color.into(),
+            ..default()
+        },
+        Interaction::None,
+        ColorSwatch(color),
+    )
+}
+
+fn spawn_popover(
+    commands: &mut Commands,
+    materials: &mut Assets<HsvUiMaterial>,
+    palette: &Palette,
+) {
+    let row = || NodeBundle {
+        style: Style {
+            column_gap: Val::Px(8.),
+            ..default()
+        },
+        ..default()
+    };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    top: Val::Px(68.),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                z_index: ZIndex::Global(1001),
+                ..default()
+            },
+            PalettePopoverRoot,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Column,
+                            padding: UiRect::all(Val::Px(12.)),
+                            row_gap: Val::Px(8.),
+                            ..default()
+                        },
+                        background_color: TOOL_BUTTON_BACKGROUND.into(),
+                        ..default()
+                    },
+                    Interaction::None,
+                    PalettePopover,
+                ))
+                .with_children(|parent| {
+                    parent.spawn(row()).with_children(|parent| {
+                        for color in CHALK_COLORS {
+                            parent.spawn(swatch(color));
+                        }
+                    });
+                    parent.spawn((
+                        MaterialNodeBundle {
+                            style: Style {
+                                width: Val::Px(248.),
+                                height: Val::Px(160.),
+                                ..default()
+                            },
+                            material: materials.add(HsvUiMaterial {
+                                hue: palette.hue,
+                                mode: 0,
+                            }),
+                            ..default()
+                        },
+                        Interaction::None,
+                        RelativeCursorPosition::default(),
+                        SaturationValueArea,
+                    ));
+                    parent.spawn((
+                        MaterialNodeBundle {
+                            style: Style {
+                                width: Val::Px(248.),
+                                height: Val::Px(16.),
+                                ..default()
+                            },
+                            material: materials
+                                .add(HsvUiMaterial { hue: 0., mode: 1 }),
+                            ..default()
+                        },
+                        Interaction::None,
+                        RelativeCursorPosition::default(),
+                        HueStrip,
+                    ));
+                    parent
+                        .spawn((
+                            NodeBundle {
+                                style: Style {
+                                    padding: UiRect::all(Val::Px(4.)),
+                                    ..default()
+                                },
+                                ..default()
+                            },
+                            Interaction::None,
+                            HexInputField,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn((
+                                TextBundle::from_section(
+                                    format!(
+                                        "#{}",
+                                        color_to_hex(palette.current)
+                                    ),
+                                    TextStyle {
+                                        font_size: 16.,
+                                        color: Color::WHITE,
+                                        ..default()
+                                    },
+                                ),
+                                HexText,
+                            ));
+                        });
+                    parent.spawn((row(), RecentColors)).with_children(
+                        |parent| {
+                            for color in palette.recent.iter() {
+                                parent.spawn(swatch(*color));
+                            }
+                        },
+                    );
+                });
+        });
+}
+
+fn toggle_popover(
+    mut commands: Commands,
+    button: Query<&Interaction, (Changed<Interaction>, With<PaletteButton>)>,
+    popover: Query<Entity, With<PalettePopoverRoot>>,
+    mut materials: ResMut<Assets<HsvUiMaterial>>,
+    palette: Res<Palette>,
+    mut hex_input: ResMut<HexInput>,
+) {
+    if !button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+    if popover.is_empty() {
+        spawn_popover(&mut commands, &mut materials, &palette);
+    } else {
+        for entity in popover.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        hex_input.editing = false;
+    }
+}
+
+fn pick_swatch(
+    swatches: Query<(&Interaction, &ColorSwatch), Changed<Interaction>>,
+    mut palette: ResMut<Palette>,
+) {
+    for (interaction, ColorSwatch(color)) in swatches.iter() {
+        if *interaction == Interaction::Pressed {
+            palette.set(*color);
+        }
+    }
+}
+
+fn pick_saturation_value(
+    area: Query<
+        (&Interaction, &RelativeCursorPosition),
+        With<SaturationValueArea>,
+    >,
+    mut palette: ResMut<Palette>,
+) {
+    for (interaction, position) in area.iter() {
+        if let (Interaction::Pressed, Some(normalized)) =
+            (interaction, position.normalized)
+        {
+            let normalized = normalized.clamp(Vec2::ZERO, Vec2::ONE);
+            let hue = palette.hue;
+            palette.set_hsv(hue, normalized.x, 1. - normalized.y);
+        }
+    }
+}
+
+fn pick_hue(
+    strip: Query<(&Interaction, &RelativeCursorPosition), With<HueStrip>>,
+    mut palette: ResMut<Palette>,
+) {
+    for (interaction, position) in strip.iter() {
+        if let (Interaction::Pressed, Some(normalized)) =
+            (interaction, position.normalized)
+        {
+            let hsv = rgb_to_hsv(palette.current);
+            palette.set_hsv(normalized.x.clamp(0., 1.), hsv.y, hsv.z);
+        }
+    }
+}
+
+fn focus_hex_input(
+    field: Query<&Interaction, (Changed<Interaction>, With<HexInputField>)>,
+    mut hex_input: ResMut<HexInput>,
+) {
+    for interaction in field.iter() {
+        if *interaction == Interaction::Pressed && !hex_input.editing {
+            hex_input.editing = true;
+            hex_input.text.clear();
+        }
+    }
+}
+
+/// 输入十六进制颜色时独占键盘，避免字母与数字触发其他快捷键
+fn capture_hex_input(
+    mut hex_input: ResMut<HexInput>,
+    mut palette: ResMut<Palette>,
+    mut received_characters: EventReader<ReceivedCharacter>,
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut keyboard_events: ResMut<Events<KeyboardInput>>,
+) {
+    for event in received_characters.read() {
+        if event.char.is_ascii_hexdigit() && hex_input.text.len() < 6 {
+            hex_input.text.push(event.char.to_ascii_uppercase());
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::Back) {
+        hex_input.text.pop();
+    }
+    if keyboard_input.just_pressed(KeyCode::Return) {
+        match Color::hex(&hex_input.text) {
+            Ok(color) => palette.set(color),
+            Err(err) => warn!("invalid color {:?}: {:?}", hex_input.text, err),
+        }
+        hex_input.editing = false;
+    }
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        hex_input.editing = false;
+    }
+    keyboard_input.reset_all();
+    keyboard_events.clear();
+}
+
+fn select_preset_by_key(
+    action_input: ActionInput,
+    mut palette: ResMut<Palette>,
+) {
+    for (action, color) in actions::COLORS.iter().zip(CHALK_COLORS) {
+        if action_input.just_pressed(action) {
+            palette.set(color);
+        }
+    }
+}
+
+fn remember_stroke_colors(
+    mut board_ops: EventReader<BoardOpEvent>,
+    mut palette: ResMut<Palette>,
+) {
+    for BoardOpEvent(op) in board_ops.read() {
+        if let BoardOp::StrokeStart { color, .. } = op {
+            palette.remember((*color).into());
+        }
+    }
+}
+
+/// 把调色板的颜色同步到画笔，切换工具时画笔会被重置
+fn apply_palette_color(palette: Res<Palette>, mut cursor: ResMut<Cursor>) {
+    if let Cursor::Touch(touch_cursor) = cursor.as_ref() {
+        if touch_cursor.color == palette.current {
+            return;
+        }
+    }
+    if let Cursor::Touch(touch_cursor) = cursor.as_mut() {
+        touch_cursor.color = palette.current;
+    }
+}
+
+fn update_palette_ui(
+    mut commands: Commands,
+    palette: Res<Palette>,
+    hex_input: Res<HexInput>,
+    mut materials: ResMut<Assets<HsvUiMaterial>>,
+    mut button: Query<&mut BackgroundColor, With<PaletteButton>>,
+    mut hex_field: Query<
+        &mut BackgroundColor,
+        (With<HexInputField>, Without<PaletteButton>),
+    >,
+    mut hex_text: Query<&mut Text, With<HexText>>,
+    sv_area: Query<&Handle<HsvUiMaterial>, With<SaturationValueArea>>,
+    recent_colors: Query<Entity, With<RecentColors>>,
+) {
+    for mut background_color in button.iter_mut() {
+        *background_color = palette.current.into();
+    }
+    for mut background_color in hex_field.iter_mut() {
+        *background_color = if hex_input.editing {
+            TOOL_BUTTON_FOCUS.into()
+        } else {
+            Color::NONE.into()
+        };
+    }
+    for mut text in hex_text.iter_mut() {
+        text.sections[0].value = if hex_input.editing {
+            format!("#{}_", hex_input.text)
+        } else {
+            format!("#{}", color_to_hex(palette.current))
+        };
+    }
+    for handle in sv_area.iter() {
+        if let Some(material) = materials.get_mut(handle) {
+            material.hue = palette.hue;
+        }
+    }
+    for entity in recent_colors.iter() {
+        commands
+            .entity(entity)
+            .despawn_descendants()
+            .with_children(|parent| {
+                for color in palette.recent.iter() {
+                    parent.spawn(swatch(*color));
+                }
+            });
+    }
+}
@@ -0,0 +1,189 @@
+// 1. LinesPlugins 把白板作为插件组嵌入其他 Bevy 程序
+// 2. 不设置窗口、Msaa 等全局资源；可以指定已有的相机，白板只使用该相机渲染到的窗口
+// 3. 通过 LinesConfig 选择显示哪些工具、是否显示工具栏与调试检查器、初始背景
+// 4. 自动保存、协作与本机接口、回放、导入导出是插件组中单独的插件：
+//    可以通过 LinesConfig 关闭，也可以用 PluginGroupBuilder::disable 单独关闭
+use bevy::{app::PluginGroupBuilder, prelude::*};
+use bevy_inspector_egui::quick::WorldInspectorPlugin;
+use bevy_prototype_lyon::prelude::ShapePlugin;
+
+use crate::{
+    api::ApiPlugin,
+    autosave::AutosavePlugin,
+    background::BoardBackground,
+    board_image::BoardImagePlugin,
+    collab::CollabPlugin,
+    draw::DrawPlugin,
+    excalidraw::ExcalidrawPlugin,
+    inkml::InkmlPlugin,
+    keybindings::ActionRegistry,
+    projection_2d_control::Projection2dControlPlugin,
+    replay::ReplayPlugin,
+    states::{RunMode, StatesPlugin, ToolButton},
+    tools::ToolRegistry,
+    ui::UIPlugin,
+    viewer::ViewerPlugin,
+};
+
+#[derive(Resource, Debug, Clone)]
+pub struct LinesConfig {
+    /** 可以使用的工具，None 表示全部已注册的工具 */
+    pub tools: Option<Vec<ToolButton>>,
+    /** 显示工具栏，隐藏时仍可通过快捷键切换工具 */
+    pub toolbar: bool,
+    /** 调试模式下显示 World Inspector */
+    pub inspector: bool,
+    /** 初始背景，None 表示默认背景 */
+    pub background: Option<BoardBackground>,
+    /** 白板使用的相机，None 表示由白板生成 */
+    pub camera: Option<Entity>,
+    /** 定期把白板保存到 recovery 目录，并在异常退出后提示恢复 */
+    pub autosave: bool,
+    /** 协作、本机控制接口与网页直播，关闭时不读取 LINES_* 环境变量 */
+    pub network: bool,
+}
+
+impl Default for LinesConfig {
+    fn default() -> Self {
+        LinesConfig {
+            tools: None,
+            toolbar: true,
+            inspector: false,
+            background: None,
+            camera: None,
+            autosave: true,
+            network: true,
+        }
+    }
+}
+
+/// 白板的全部插件
+///
+/// ```ignore
+/// let camera = app.world.spawn(Camera2dBundle::default()).id();
+/// app.add_plugins(
+///     LinesPlugins::default()
+///         .with_camera(camera)
+///         .with_tools(vec![ToolButton::Cursor, ToolButton::Pen])
+///         .with_toolbar(false),
+/// );
+/// ```
+#[derive(Default)]
+pub struct LinesPlugins {
+    pub config: LinesConfig,
+}
+
+impl LinesPlugins {
+    pub fn with_tools(mut self, tools: Vec<ToolButton>) -> Self {
+        self.config.tools = Some(tools);
+        self
+    }
+
+    pub fn with_toolbar(mut self, toolbar: bool) -> Self {
+        self.config.toolbar = toolbar;
+        self
+    }
+
+    pub fn with_inspector(mut self, inspector: bool) -> Self {
+        self.config.inspector = inspector;
+        self
+    }
+
+    pub fn with_background(mut self, background: BoardBackground) -> Self {
+        self.config.background = Some(background);
+        self
+    }
+
+    pub fn with_camera(mut self, camera: Entity) -> Self {
+        self.config.camera = Some(camera);
+        self
+    }
+
+    pub fn with_autosave(mut self, autosave: bool) -> Self {
+        self.config.autosave = autosave;
+        self
+    }
+
+    pub fn with_network(mut self, network: bool) -> Self {
+        self.config.network = network;
+        self
+    }
+}
+
+impl PluginGroup for LinesPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        let LinesConfig {
+            inspector,
+            autosave,
+            network,
+            ..
+        } = self.config;
+        let mut group = PluginGroupBuilder::start::<Self>()
+            .add(LinesConfigPlugin(self.config))
+            .add(StatesPlugin)
+            .add(Projection2dControlPlugin)
+            .add(DrawPlugin)
+            .add(UIPlugin)
+            .add(ReplayPlugin)
+            .add(ExcalidrawPlugin)
+            .add(InkmlPlugin)
+            .add(BoardImagePlugin)
+            .add(AutosavePlugin)
+            .add(CollabPlugin)
+            .add(ApiPlugin)
+            .add(ViewerPlugin);
+        if !autosave {
+            group = group.disable::<AutosavePlugin>();
+        }
+        if !network {
+            group = group
+                .disable::<CollabPlugin>()
+                .disable::<ApiPlugin>()
+                .disable::<ViewerPlugin>();
+        }
+        if inspector {
+            group.add(
+                WorldInspectorPlugin::default()
+                    .run_if(in_state(RunMode::Debug)),
+            )
+        } else {
+            group
+        }
+    }
+}
+
+/// 在其他插件之前写入配置，其他插件只在缺少配置时使用默认值
+struct LinesConfigPlugin(LinesConfig);
+
+impl Plugin for LinesConfigPlugin {
+    fn build(&self, app: &mut App) {
+        // 宿主程序可能已经添加了 ShapePlugin
+        if !app.is_plugin_added::<ShapePlugin>() {
+            app.add_plugins(ShapePlugin);
+        }
+        if let Some(background) = &self.0.background {
+            app.insert_resource(background.clone());
+        }
+        app.insert_resource(self.0.clone())
+            .add_systems(PreStartup, retain_configured_tools);
+    }
+}
+
+/// 去掉没有选择的工具，它们不出现在工具栏与命令面板中
+fn retain_configured_tools(
+    config: Res<LinesConfig>,
+    mut tools: ResMut<ToolRegistry>,
+    mut actions: ResMut<ActionRegistry>,
+) {
+    let Some(enabled) = &config.tools else {
+        return;
+    };
+    let (kept, removed): (Vec<_>, Vec<_>) = tools
+        .0
+        .drain(..)
+        .partition(|info| enabled.contains(&info.tool));
+    tools.0 = kept;
+    actions
+        .0
+        .retain(|action| !removed.iter().any(|info| info.action == action.id));
+}
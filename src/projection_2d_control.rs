@@ -1,22 +1,49 @@
-use bevy::{input::mouse::MouseWheel, prelude::*, window::PrimaryWindow};
+use bevy::{
+    ecs::system::SystemParam,
+    input::mouse::MouseWheel,
+    prelude::*,
+    render::camera::RenderTarget,
+    window::{PrimaryWindow, WindowRef},
+};
 
-use crate::states::{CursorState, RunMode, ToolButton};
+use crate::{
+    keybindings::{action_just_pressed, actions, KeyChord, RegisterAction},
+    plugins::LinesConfig,
+    states::{CursorState, RunMode, ToolButton},
+};
 
 pub struct Projection2dControlPlugin;
 
 impl Plugin for Projection2dControlPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<StateChangePositionBegin>()
+        app.init_resource::<LinesConfig>()
+            .init_resource::<StateChangePositionBegin>()
             .init_resource::<CameraBeginTransform>()
+            .register_action_with_keys(
+                actions::ZOOM_IN,
+                "Zoom in",
+                vec![KeyChord::ctrl(KeyCode::Equals)],
+            )
+            .register_action_with_keys(
+                actions::ZOOM_OUT,
+                "Zoom out",
+                vec![KeyChord::ctrl(KeyCode::Minus)],
+            )
+            .register_action_with_keys(
+                actions::ZOOM_RESET,
+                "Zoom: Reset to 100%",
+                vec![KeyChord::ctrl(KeyCode::Key0)],
+            )
             .add_systems(Startup, spawn_camera)
             .add_systems(
                 OnEnter(CursorState::Draging),
-                (|q_windows: Query<&Window, With<PrimaryWindow>>,
+                (|board_window: BoardWindow,
                   mut state_change_position_begin: ResMut<
                     StateChangePositionBegin,
                 >| {
-                    let window = q_windows.single();
-                    if let Some(cursor_position) = window.cursor_position() {
+                    if let Some(cursor_position) =
+                        board_window.cursor_position()
+                    {
                         *state_change_position_begin =
                             StateChangePositionBegin(Some(cursor_position));
                     }
@@ -40,6 +67,17 @@ impl Plugin for Projection2dControlPlugin {
                 control_proj
                     .run_if(in_state(CursorState::Hovering))
                     .run_if(in_state(RunMode::Normal)),
+            )
+            .add_systems(
+                Update,
+                (
+                    zoom_camera(Some(1. / ZOOM_STEP))
+                        .run_if(action_just_pressed(actions::ZOOM_IN)),
+                    zoom_camera(Some(ZOOM_STEP))
+                        .run_if(action_just_pressed(actions::ZOOM_OUT)),
+                    zoom_camera(None)
+                        .run_if(action_just_pressed(actions::ZOOM_RESET)),
+                ),
             );
     }
 }
@@ -47,8 +85,49 @@ impl Plugin for Projection2dControlPlugin {
 #[derive(Component)]
 pub struct MainCamera;
 
-fn spawn_camera(mut commands: Commands) {
-    commands.spawn((Camera2dBundle::default(), MainCamera));
+/// 嵌入其他程序时可以指定已有的相机，否则生成一个
+fn spawn_camera(mut commands: Commands, config: Res<LinesConfig>) {
+    match config.camera {
+        Some(camera) => {
+            commands.entity(camera).insert(MainCamera);
+        }
+        None => {
+            commands.spawn((Camera2dBundle::default(), MainCamera));
+        }
+    }
+}
+
+/// 主相机渲染到的窗口，嵌入其他程序时不一定是主窗口
+#[derive(SystemParam)]
+pub struct BoardWindow<'w, 's> {
+    camera: Query<'w, 's, &'static Camera, With<MainCamera>>,
+    primary: Query<'w, 's, Entity, With<PrimaryWindow>>,
+    windows: Query<'w, 's, &'static mut Window>,
+}
+
+impl BoardWindow<'_, '_> {
+    pub fn entity(&self) -> Option<Entity> {
+        match self.camera.get_single().ok()?.target {
+            RenderTarget::Window(WindowRef::Primary) => {
+                self.primary.get_single().ok()
+            }
+            RenderTarget::Window(WindowRef::Entity(entity)) => Some(entity),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self) -> Option<&Window> {
+        self.windows.get(self.entity()?).ok()
+    }
+
+    pub fn get_mut(&mut self) -> Option<Mut<Window>> {
+        let entity = self.entity()?;
+        self.windows.get_mut(entity).ok()
+    }
+
+    pub fn cursor_position(&self) -> Option<Vec2> {
+        self.get()?.cursor_position()
+    }
 }
 
 #[derive(Resource, Default)]
@@ -66,7 +145,7 @@ fn set_camera_begin_transform(
 }
 
 fn update_camera(
-    q_windows: Query<&Window, With<PrimaryWindow>>,
+    board_window: BoardWindow,
     mut camera_query: Query<
         (&OrthographicProjection, &mut Transform),
         With<MainCamera>,
@@ -76,8 +155,7 @@ fn update_camera(
 ) {
     let mut window_point = Vec2::default();
     let (proj, mut transform) = camera_query.single_mut();
-    let window = q_windows.single();
-    if let Some(cursor_position) = window.cursor_position() {
+    if let Some(cursor_position) = board_window.cursor_position() {
         window_point = cursor_position;
     }
 
@@ -97,6 +175,11 @@ fn control_proj(
     mut mouse_wheel_events: EventReader<MouseWheel>,
     keyboard_input: Res<Input<KeyCode>>,
 ) {
+    // Alt + 滚轮用于缩放选中的图片
+    if keyboard_input.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]) {
+        mouse_wheel_events.clear();
+        return;
+    }
     let (mut transform, mut proj) = camera_query.single_mut();
     for event in mouse_wheel_events.read() {
         if keyboard_input.pressed(KeyCode::ControlLeft) {
@@ -106,3 +189,16 @@ fn control_proj(
         }
     }
 }
+
+const ZOOM_STEP: f32 = 1.25;
+
+/// 按倍数缩放画布，None 表示恢复原始大小
+fn zoom_camera(
+    factor: Option<f32>,
+) -> impl FnMut(Query<&mut OrthographicProjection, With<MainCamera>>) {
+    move |mut camera_query| {
+        for mut proj in camera_query.iter_mut() {
+            proj.scale = factor.map_or(1., |factor| proj.scale * factor);
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub use crate::board::{stroke_ops, BoardOp};
+use crate::board_view::BoardOpEvent;
+
+pub struct RecordingPlugin;
+
+impl Plugin for RecordingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Recording>()
+            .add_systems(PostUpdate, record_board_ops);
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TimedOp {
+    /** 距离录制开始的秒数 */
+    pub t: f32,
+    pub op: BoardOp,
+}
+
+/// 超过这个时长的停顿在回放时会被压缩
+const MAX_IDLE_GAP: f32 = 2.0;
+
+#[derive(Resource, Default)]
+pub struct Recording {
+    pub ops: Vec<TimedOp>,
+    last_real_time: Option<f32>,
+}
+
+impl Recording {
+    pub fn duration(&self) -> f32 {
+        self.ops.last().map_or(0., |timed| timed.t)
+    }
+
+    pub fn replace(&mut self, ops: Vec<TimedOp>) {
+        self.ops = ops;
+        self.last_real_time = None;
+    }
+
+    /// now 为真实时间的秒数，过长的停顿会被压缩
+    pub fn push(&mut self, now: f32, op: BoardOp) {
+        let gap = self
+            .last_real_time
+            .map_or(0., |last| (now - last).min(MAX_IDLE_GAP));
+        let t = self.duration() + gap;
+        self.last_real_time = Some(now);
+        self.ops.push(TimedOp { t, op });
+    }
+}
+
+fn record_board_ops(
+    mut board_ops: EventReader<BoardOpEvent>,
+    mut recording: ResMut<Recording>,
+    time: Res<Time<Real>>,
+) {
+    for BoardOpEvent(op) in board_ops.read() {
+        recording.push(time.elapsed_seconds(), op.clone());
+    }
+}
@@ -0,0 +1,431 @@
+use std::collections::{BTreeSet, HashMap};
+
+use bevy::{
+    prelude::*, render::view::screenshot::ScreenshotManager,
+    ui::RelativeCursorPosition, window::RequestRedraw,
+};
+use bevy_prototype_lyon::prelude::*;
+
+use crate::{
+    board::Board,
+    board_view::ShownPage,
+    document::LineQuery,
+    draw::{Line, LineSpawner},
+    keybindings::{action_just_pressed, actions, ActionInput},
+    projection_2d_control::BoardWindow,
+    recording::{BoardOp, Recording},
+    ui::{TOOL_BUTTON_BACKGROUND, TOOL_BUTTON_FOCUS},
+};
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Replay>()
+            .add_systems(OnEnter(ReplayState::Off), end_replay)
+            .add_systems(
+                Update,
+                (
+                    start_replay
+                        .run_if(action_just_pressed(actions::REPLAY))
+                        .run_if(in_state(ReplayState::Off)),
+                    stop_replay
+                        .run_if(action_just_pressed(actions::REPLAY))
+                        .run_if(not(in_state(ReplayState::Off))),
+                    start_export
+                        .run_if(action_just_pressed(actions::REPLAY_EXPORT)),
+                    request_redraw.run_if(
+                        in_state(ReplayState::Playing)
+                            .or_else(in_state(ReplayState::Exporting)),
+                    ),
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    replay_controls,
+                    scrub_timeline,
+                    advance_playhead.run_if(in_state(ReplayState::Playing)),
+                    step_export.run_if(in_state(ReplayState::Exporting)),
+                    sync_replay_board,
+                    update_timeline,
+                    capture_frame.run_if(in_state(ReplayState::Exporting)),
+                )
+                    .chain()
+                    .run_if(not(in_state(ReplayState::Off))),
+            );
+    }
+}
+
+#[derive(States, Default, Debug, Hash, PartialEq, Eq, Clone)]
+pub enum ReplayState {
+    #[default]
+    Off,
+    Playing,
+    Paused,
+    Exporting,
+}
+
+/// 导出帧序列时每帧前进的录制时长
+const EXPORT_FRAME_STEP: f32 = 1. / 30.;
+const EXPORT_DIR: &str = "replay_frames";
+
+const MIN_SPEED: f32 = 0.125;
+const MAX_SPEED: f32 = 16.;
+
+#[derive(Resource)]
+pub struct Replay {
+    pub playhead: f32,
+    pub speed: f32,
+    applied: usize,
+    board: Board,
+    entities: HashMap<u64, Entity>,
+    /** 回放中，退出回放时按模型恢复白板 */
+    active: bool,
+    frame: u32,
+}
+
+impl Default for Replay {
+    fn default() -> Self {
+        Replay {
+            playhead: 0.,
+            speed: 1.,
+            applied: 0,
+            board: Board::default(),
+            entities: HashMap::new(),
+            active: false,
+            frame: 0,
+        }
+    }
+}
+
+impl Replay {
+    fn rewind(&mut self) {
+        self.playhead = 0.;
+        self.applied = 0;
+        self.frame = 0;
+    }
+}
+
+#[derive(Component)]
+struct ReplayTimeline;
+
+#[derive(Component)]
+struct ReplayTimelineBar;
+
+#[derive(Component)]
+struct ReplayTimelineProgress;
+
+#[derive(Component)]
+struct ReplayTimelineLabel;
+
+fn begin_replay(
+    replay: &mut Replay,
+    lines: &LineQuery,
+    commands: &mut Commands,
+) {
+    replay.active = true;
+    replay.board = Board::default();
+    replay.entities.clear();
+    replay.rewind();
+    for (entity, ..) in lines.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    spawn_timeline(commands);
+}
+
+fn start_replay(
+    mut commands: Commands,
+    mut replay: ResMut<Replay>,
+    mut next_state: ResMut<NextState<ReplayState>>,
+    lines: LineQuery,
+) {
+    begin_replay(&mut replay, &lines, &mut commands);
+    next_state.set(ReplayState::Playing);
+}
+
+fn stop_replay(mut next_state: ResMut<NextState<ReplayState>>) {
+    next_state.set(ReplayState::Off);
+}
+
+fn start_export(
+    mut commands: Commands,
+    mut replay: ResMut<Replay>,
+    mut next_state: ResMut<NextState<ReplayState>>,
+    state: Res<State<ReplayState>>,
+    lines: LineQuery,
+) {
+    if let Err(err) = std::fs::create_dir_all(EXPORT_DIR) {
+        error!("failed to create {}: {}", EXPORT_DIR, err);
+        return;
+    }
+    if *state.get() == ReplayState::Off {
+        begin_replay(&mut replay, &lines, &mut commands);
+    } else {
+        replay.rewind();
+    }
+    next_state.set(ReplayState::Exporting);
+}
+
+/// 回放的线条在重建视图时一并移除
+fn end_replay(
+    mut commands: Commands,
+    mut replay: ResMut<Replay>,
+    mut shown: ResMut<ShownPage>,
+    timeline: Query<Entity, With<ReplayTimeline>>,
+) {
+    if replay.active {
+        replay.active = false;
+        shown.0 = None;
+        replay.board = Board::default();
+        replay.entities.clear();
+        for entity in timeline.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn replay_controls(
+    action_input: ActionInput,
+    state: Res<State<ReplayState>>,
+    mut next_state: ResMut<NextState<ReplayState>>,
+    mut replay: ResMut<Replay>,
+    recording: Res<Recording>,
+) {
+    if action_input.just_pressed(actions::REPLAY_PAUSE) {
+        match state.get() {
+            ReplayState::Playing => next_state.set(ReplayState::Paused),
+            ReplayState::Paused => {
+                if replay.playhead >= recording.duration() {
+                    replay.playhead = 0.;
+                }
+                next_state.set(ReplayState::Playing);
+            }
+            _ => {}
+        }
+    }
+    if action_input.just_pressed(actions::REPLAY_SLOWER) {
+        replay.speed = (replay.speed / 2.).max(MIN_SPEED);
+    }
+    if action_input.just_pressed(actions::REPLAY_FASTER) {
+        replay.speed = (replay.speed * 2.).min(MAX_SPEED);
+    }
+}
+
+fn scrub_timeline(
+    bar_query: Query<
+        (&Interaction, &RelativeCursorPosition),
+        With<ReplayTimelineBar>,
+    >,
+    mut replay: ResMut<Replay>,
+    recording: Res<Recording>,
+) {
+    for (interaction, relative_cursor_position) in bar_query.iter() {
+        if let (Interaction::Pressed, Some(position)) =
+            (interaction, relative_cursor_position.normalized)
+        {
+            replay.playhead = position.x.clamp(0., 1.) * recording.duration();
+        }
+    }
+}
+
+fn advance_playhead(
+    time: Res<Time<Real>>,
+    mut replay: ResMut<Replay>,
+    recording: Res<Recording>,
+    mut next_state: ResMut<NextState<ReplayState>>,
+) {
+    let duration = recording.duration();
+    replay.playhead =
+        (replay.playhead + time.delta_seconds() * replay.speed).min(duration);
+    if replay.playhead >= duration {
+        next_state.set(ReplayState::Paused);
+    }
+}
+
+/// 播放与导出按帧推进，不等待输入事件
+fn request_redraw(mut redraw: EventWriter<RequestRedraw>) {
+    redraw.send(RequestRedraw);
+}
+
+fn step_export(
+    mut replay: ResMut<Replay>,
+    recording: Res<Recording>,
+    mut next_state: ResMut<NextState<ReplayState>>,
+) {
+    if replay.frame == 0 {
+        return;
+    }
+    let duration = recording.duration();
+    if replay.playhead >= duration {
+        info!("exported {} frames to {}", replay.frame, EXPORT_DIR);
+        next_state.set(ReplayState::Off);
+        return;
+    }
+    replay.playhead =
+        (replay.playhead + EXPORT_FRAME_STEP * replay.speed).min(duration);
+}
+
+fn capture_frame(
+    mut replay: ResMut<Replay>,
+    mut screenshot_manager: ResMut<ScreenshotManager>,
+    board_window: BoardWindow,
+) {
+    let Some(window) = board_window.entity() else {
+        return;
+    };
+    let path = format!("{}/frame_{:05}.png", EXPORT_DIR, replay.frame);
+    match screenshot_manager.save_screenshot_to_disk(window, path) {
+        Ok(()) => replay.frame += 1,
+        Err(err) => warn!("skipped replay frame: {}", err),
+    }
+}
+
+/// 录制中模型不接受的操作（例如旧版本留下的）直接跳过
+/// 组与页面的操作可能改变整页的线条
+fn apply_op(board: &mut Board, op: &BoardOp, dirty: &mut BTreeSet<u64>) {
+    let whole = op.id().map_or(true, |id| board.page().group(id).is_some());
+    if whole {
+        dirty.extend(board.page().strokes().map(|stroke| stroke.id));
+    } else {
+        dirty.extend(op.id());
+    }
+    if let Err(err) = board.apply(op) {
+        debug!("skipped replay op {:?}: {}", op, err);
+    }
+    if whole {
+        dirty.extend(board.page().strokes().map(|stroke| stroke.id));
+    }
+}
+
+fn sync_replay_board(
+    mut replay: ResMut<Replay>,
+    recording: Res<Recording>,
+    mut spawner: LineSpawner,
+    mut transforms: Query<&mut Transform, With<Line>>,
+) {
+    let Replay {
+        playhead,
+        applied,
+        board,
+        entities,
+        ..
+    } = replay.as_mut();
+    let mut dirty = BTreeSet::new();
+
+    // 回拖时间轴时从头重建
+    if *applied > 0 && recording.ops[*applied - 1].t > *playhead {
+        dirty.extend(board.page().strokes().map(|stroke| stroke.id));
+        *board = Board::default();
+        *applied = 0;
+    }
+    while let Some(timed) = recording.ops.get(*applied) {
+        if timed.t > *playhead {
+            break;
+        }
+        apply_op(board, &timed.op, &mut dirty);
+        *applied += 1;
+    }
+
+    for id in dirty {
+        match (board.page().get(id), entities.get(&id).copied()) {
+            (Some(stroke), Some(entity)) => {
+                let line = Line(stroke.points.clone());
+                spawner
+                    .commands
+                    .entity(entity)
+                    .insert((Path::from(&line), line));
+                if let Ok(mut transform) = transforms.get_mut(entity) {
+                    transform.translation.x = stroke.offset.x;
+                    transform.translation.y = stroke.offset.y;
+                }
+            }
+            (Some(stroke), None) => {
+                entities.insert(id, spawner.spawn(stroke));
+            }
+            (None, Some(entity)) => {
+                spawner.commands.entity(entity).despawn_recursive();
+                entities.remove(&id);
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+fn spawn_timeline(commands: &mut Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    bottom: Val::Px(16.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(4.),
+                    ..default()
+                },
+                z_index: ZIndex::Global(1000),
+                ..default()
+            },
+            ReplayTimeline,
+        ))
+        .with_children(|parent| {
+            parent.spawn((TextBundle::default(), ReplayTimelineLabel));
+            parent
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            width: Val::Px(550.),
+                            height: Val::Px(12.),
+                            ..default()
+                        },
+                        background_color: TOOL_BUTTON_BACKGROUND.into(),
+                        ..default()
+                    },
+                    Interaction::None,
+                    RelativeCursorPosition::default(),
+                    ReplayTimelineBar,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(0.),
+                                height: Val::Percent(100.),
+                                ..default()
+                            },
+                            background_color: TOOL_BUTTON_FOCUS.into(),
+                            ..default()
+                        },
+                        ReplayTimelineProgress,
+                    ));
+                });
+        });
+}
+
+fn update_timeline(
+    replay: Res<Replay>,
+    recording: Res<Recording>,
+    mut progress_query: Query<&mut Style, With<ReplayTimelineProgress>>,
+    mut label_query: Query<&mut Text, With<ReplayTimelineLabel>>,
+) {
+    let duration = recording.duration();
+    let progress = if duration > 0. {
+        replay.playhead / duration
+    } else {
+        1.
+    };
+    for mut style in progress_query.iter_mut() {
+        style.width = Val::Percent(progress * 100.);
+    }
+    for mut text in label_query.iter_mut() {
+        *text = Text::from_section(
+            format!(
+                "{:.1}s / {:.1}s  x{}",
+                replay.playhead, duration, replay.speed
+            ),
+            TextStyle::default(),
+        );
+    }
+}
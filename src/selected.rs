@@ -1,14 +1,36 @@
-// 1. 点选
+// 1. 点选，按住 Shift 多选
 // 2. 框选
+// 3. 拖动、缩放、锁定选中的对象
+// 4. 把选中的对象移到最上层或最下层
+use std::collections::{BTreeSet, HashMap};
+
 use bevy::{
-    input::common_conditions::{input_just_released, input_pressed},
+    input::{
+        common_conditions::{
+            input_just_pressed, input_just_released, input_pressed,
+        },
+        mouse::MouseWheel,
+    },
     prelude::*,
     render::primitives::Aabb,
 };
 
 use crate::{
-    cursor::WorldTouchCursor, focus::find_entity_with_world_cursor,
-    layer::Layer, states::ToolButton,
+    board::LAYER_MAX,
+    board_image::BoardImage,
+    board_view::{BoardOpEvent, ObjectId},
+    common::alt_pressed,
+    cursor::{Cursor, WorldTouchCursor},
+    draw::NextLine,
+    focus::{world_rect, NodeQuery, Picker},
+    group::{with_descendants, Group},
+    keybindings::{action_just_pressed, actions, KeyChord, RegisterAction},
+    layer::Layer,
+    recording::BoardOp,
+    replay::ReplayState,
+    snap::{SmartGuides, Snapper},
+    states::{CursorState, ToolButton},
+    tools::{RegisterTool, ToolDescriptor},
 };
 
 #[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
@@ -16,46 +38,132 @@ pub struct SelectedPlugin;
 
 impl Plugin for SelectedPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<Selected>().add_systems(
-            Update,
-            (
-                selected
-                    .in_set(SelectedPlugin)
-                    .run_if(in_state(ToolButton::Cursor))
-                    .run_if(input_pressed(MouseButton::Left)),
-                draw_selected.after(SelectedPlugin),
-                cancel_selected_with_cursor
-                    .in_set(SelectedPlugin)
+        app.init_resource::<Selected>()
+            .init_resource::<SelectionDrag>()
+            .register_action_with_keys(
+                actions::BRING_TO_FRONT,
+                "Layer: Bring to front",
+                vec![KeyChord::ctrl(KeyCode::BracketRight)],
+            )
+            .register_action_with_keys(
+                actions::SEND_TO_BACK,
+                "Layer: Send to back",
+                vec![KeyChord::ctrl(KeyCode::BracketLeft)],
+            )
+            .register_tool(
+                ToolDescriptor::new(
+                    ToolButton::Cursor,
+                    "Tool: Cursor",
+                    Vec2::new(2., 0.),
+                )
+                .with_action(actions::TOOL_CURSOR)
+                .with_key(KeyChord::key(KeyCode::Key1))
+                .with_cursor(Cursor::Default)
+                .on_update((
+                    selected
+                        .in_set(SelectedPlugin)
+                        .run_if(input_pressed(MouseButton::Left))
+                        .run_if(not(shift_pressed)),
+                    toggle_selected
+                        .in_set(SelectedPlugin)
+                        .run_if(input_just_pressed(MouseButton::Left))
+                        .run_if(shift_pressed),
+                    cancel_selected_with_cursor
+                        .in_set(SelectedPlugin)
+                        .run_if(input_just_released(MouseButton::Left))
+                        .run_if(not(shift_pressed)),
+                ))
+                .on_update(
+                    (
+                        drag_selected
+                            .after(SelectedPlugin)
+                            .run_if(in_state(CursorState::Draging)),
+                        scale_selected.run_if(alt_pressed),
+                        toggle_locked
+                            .run_if(action_just_pressed(actions::LOCK)),
+                    )
+                        .run_if(in_state(ReplayState::Off)),
+                ),
+            )
+            .add_systems(Update, draw_selected.after(SelectedPlugin))
+            .add_systems(
+                OnEnter(CursorState::Draging),
+                begin_drag
                     .run_if(in_state(ToolButton::Cursor))
-                    .run_if(input_just_released(MouseButton::Left)),
-            ),
-        );
+                    .run_if(in_state(ReplayState::Off)),
+            )
+            .add_systems(OnEnter(CursorState::Hovering), end_drag)
+            .add_systems(
+                Update,
+                (
+                    reorder_selected(true)
+                        .run_if(action_just_pressed(actions::BRING_TO_FRONT)),
+                    reorder_selected(false)
+                        .run_if(action_just_pressed(actions::SEND_TO_BACK)),
+                )
+                    .run_if(in_state(ReplayState::Off)),
+            );
     }
 }
 
 #[derive(Resource, Default)]
 pub struct Selected(pub Vec<Entity>);
 
+/// 锁定的对象可以选中，但不能拖动或缩放
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Locked;
+
+#[derive(Resource, Default)]
+struct SelectionDrag {
+    start: Vec2,
+    /** 拖动的对象、起始位置与父节点缩放的倒数 */
+    origins: Vec<(Entity, Vec3, Vec2)>,
+    /** 拖动对象的包围盒，用于吸附 */
+    bounds: Option<Rect>,
+    /** 其他对象的包围盒，用于对齐 */
+    targets: Vec<Rect>,
+    /** 吸附后的位移 */
+    delta: Vec2,
+}
+
+const SCALE_STEP: f32 = 1.1;
+
+fn shift_pressed(keyboard_input: Res<Input<KeyCode>>) -> bool {
+    keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight])
+}
+
 fn selected(
-    node_query: Query<(Entity, &Aabb, &Layer)>,
+    picker: Picker,
+    world_touch_cursor: Res<WorldTouchCursor>,
+    mut selected: ResMut<Selected>,
+) {
+    if let Some(entity) = picker.pick(&world_touch_cursor) {
+        if !selected.0.contains(&entity) {
+            *selected = Selected(vec![entity])
+        }
+    }
+}
+
+fn toggle_selected(
+    picker: Picker,
     world_touch_cursor: Res<WorldTouchCursor>,
     mut selected: ResMut<Selected>,
 ) {
-    if let Some((entity, _, _)) =
-        find_entity_with_world_cursor(&node_query, world_touch_cursor)
-    {
-        *selected = Selected(vec![entity])
+    if let Some(entity) = picker.pick(&world_touch_cursor) {
+        if let Some(index) = selected.0.iter().position(|e| *e == entity) {
+            selected.0.remove(index);
+        } else {
+            selected.0.push(entity);
+        }
     }
 }
 
 fn cancel_selected_with_cursor(
     mut selected: ResMut<Selected>,
-    node_query: Query<(Entity, &Aabb, &Layer)>,
+    picker: Picker,
     world_touch_cursor: Res<WorldTouchCursor>,
 ) {
-    if let Some((entity, _, _)) =
-        find_entity_with_world_cursor(&node_query, world_touch_cursor)
-    {
+    if let Some(entity) = picker.pick(&world_touch_cursor) {
         if selected.0.contains(&entity) {
             return;
         }
@@ -65,19 +173,217 @@ fn cancel_selected_with_cursor(
 
 fn draw_selected(
     mut gizmos: Gizmos,
-    node_query: Query<(Entity, &Aabb, &Layer)>,
+    bounds: Query<(&Aabb, &GlobalTransform, Has<Locked>)>,
     selected: Res<Selected>,
 ) {
-    selected.0.get(0).and_then(|entity| {
-        let _ = node_query.get(*entity).and_then(|(_, aabb, _)| {
-            gizmos.rect_2d(
-                aabb.center.xy(),
-                0.,
-                aabb.half_extents.xy() * 2.,
-                Color::RED,
-            );
-            Ok(())
-        });
-        Some(())
-    });
+    for entity in selected.0.iter() {
+        if let Ok((aabb, transform, locked)) = bounds.get(*entity) {
+            let rect = world_rect(aabb, transform);
+            let color = if locked { Color::ORANGE } else { Color::RED };
+            gizmos.rect_2d(rect.center(), 0., rect.size(), color);
+        }
+    }
+}
+
+fn begin_drag(
+    mut drag: ResMut<SelectionDrag>,
+    selected: Res<Selected>,
+    picker: Picker,
+    world_touch_cursor: Res<WorldTouchCursor>,
+    transforms: Query<(&Transform, Option<&Parent>), Without<Locked>>,
+    global_transforms: Query<&GlobalTransform>,
+    bounds: Query<(&Aabb, &GlobalTransform)>,
+    nodes: NodeQuery,
+    children: Query<&Children>,
+) {
+    drag.origins.clear();
+    drag.delta = Vec2::ZERO;
+    match picker.pick(&world_touch_cursor) {
+        Some(entity) if selected.0.contains(&entity) => {}
+        _ => return,
+    }
+    drag.start = world_touch_cursor.0;
+    drag.origins = selected
+        .0
+        .iter()
+        .filter_map(|entity| {
+            let (transform, parent) = transforms.get(*entity).ok()?;
+            // 组内的对象按父节点的缩放换算位移
+            let parent_scale = parent
+                .and_then(|parent| global_transforms.get(parent.get()).ok())
+                .map_or(Vec2::ONE, |parent| {
+                    parent.to_scale_rotation_translation().0.xy()
+                });
+            Some((*entity, transform.translation, parent_scale.recip()))
+        })
+        .collect();
+
+    let dragged: Vec<Entity> =
+        drag.origins.iter().map(|(entity, ..)| *entity).collect();
+    drag.bounds = dragged
+        .iter()
+        .filter_map(|entity| bounds.get(*entity).ok())
+        .map(|(aabb, transform)| world_rect(aabb, transform))
+        .reduce(|a, b| a.union(b));
+    let excluded = with_descendants(&dragged, &children);
+    drag.targets = nodes
+        .iter()
+        .filter(|(entity, ..)| !excluded.contains(entity))
+        .map(|(_, aabb, _, transform)| world_rect(aabb, transform))
+        .collect();
+}
+
+fn drag_selected(
+    mut drag: ResMut<SelectionDrag>,
+    world_touch_cursor: Res<WorldTouchCursor>,
+    snapper: Snapper,
+    mut guides: ResMut<SmartGuides>,
+    mut transforms: Query<&mut Transform>,
+) {
+    let mut delta = world_touch_cursor.0 - drag.start;
+    if let Some(bounds) = drag.bounds {
+        (delta, guides.0) = snapper.snap_move(bounds, delta, &drag.targets);
+    }
+    drag.delta = delta;
+    for (entity, origin, inverse_scale) in drag.origins.iter() {
+        if let Ok(mut transform) = transforms.get_mut(*entity) {
+            transform.translation =
+                *origin + (delta * *inverse_scale).extend(0.);
+        }
+    }
+}
+
+/// 移动组时模型移动其中的每个对象
+fn end_drag(
+    mut drag: ResMut<SelectionDrag>,
+    mut guides: ResMut<SmartGuides>,
+    ids: Query<&ObjectId>,
+    mut board_ops: EventWriter<BoardOpEvent>,
+) {
+    let delta = drag.delta;
+    if delta != Vec2::ZERO {
+        for (entity, ..) in drag.origins.iter() {
+            if let Ok(&ObjectId(id)) = ids.get(*entity) {
+                board_ops.send(BoardOpEvent(BoardOp::Move { id, delta }));
+            }
+        }
+    }
+    drag.origins.clear();
+    drag.bounds = None;
+    drag.targets.clear();
+    guides.0.clear();
+}
+
+/// 组以原点为中心缩放，发送 Scale 保持模型一致
+fn scale_selected(
+    mut mouse_wheel_events: EventReader<MouseWheel>,
+    selected: Res<Selected>,
+    mut scalable: Query<
+        (&mut Transform, &GlobalTransform, &ObjectId),
+        (Or<(With<BoardImage>, With<Group>)>, Without<Locked>),
+    >,
+    mut board_ops: EventWriter<BoardOpEvent>,
+) {
+    for event in mouse_wheel_events.read() {
+        let factor = SCALE_STEP.powf(event.y);
+        for entity in selected.0.iter() {
+            let Ok((mut transform, global, &ObjectId(id))) =
+                scalable.get_mut(*entity)
+            else {
+                continue;
+            };
+            transform.scale.x *= factor;
+            transform.scale.y *= factor;
+            board_ops.send(BoardOpEvent(BoardOp::Scale {
+                id,
+                center: global.translation().xy(),
+                factor,
+            }));
+        }
+    }
+}
+
+fn toggle_locked(
+    mut commands: Commands,
+    selected: Res<Selected>,
+    images: Query<(&ObjectId, Has<Locked>), With<BoardImage>>,
+    mut board_ops: EventWriter<BoardOpEvent>,
+) {
+    for entity in selected.0.iter() {
+        let Ok((&ObjectId(id), locked)) = images.get(*entity) else {
+            continue;
+        };
+        if locked {
+            commands.entity(*entity).remove::<Locked>();
+        } else {
+            commands.entity(*entity).insert(Locked);
+        }
+        board_ops.send(BoardOpEvent(BoardOp::Lock {
+            id,
+            locked: !locked,
+        }));
+    }
+}
+
+/// 选中的对象（含组内成员）统一放到其他对象之上或之下
+/// 其他对象已经占用最上层或最下层时重新编号，保持它们之间的顺序
+fn reorder_selected(
+    to_front: bool,
+) -> impl FnMut(
+    Res<Selected>,
+    Query<&Children>,
+    Query<(Entity, &mut Layer, Option<&ObjectId>)>,
+    ResMut<NextLine>,
+    EventWriter<BoardOpEvent>,
+) {
+    move |selected, children, mut layers, mut next_line, mut board_ops| {
+        if selected.0.is_empty() {
+            return;
+        }
+        let targets = with_descendants(&selected.0, &children);
+        let others: BTreeSet<i8> = layers
+            .iter()
+            .filter(|(entity, ..)| !targets.contains(entity))
+            .map(|(_, &Layer::Foreground(layer), _)| layer)
+            .collect();
+        let renumber = if to_front {
+            others.last() == Some(&LAYER_MAX)
+        } else {
+            others.first() == Some(&0)
+        };
+        let first = if to_front { 0 } else { 1 };
+        let ranks: HashMap<i8, i8> = others
+            .iter()
+            .enumerate()
+            .map(|(rank, &layer)| {
+                (layer, (first + rank).min(LAYER_MAX as usize) as i8)
+            })
+            .collect();
+        let layer = match (to_front, renumber) {
+            (true, false) => others.last().map_or(0, |layer| layer + 1),
+            (true, true) => others.len().min(LAYER_MAX as usize) as i8,
+            (false, false) => others.first().map_or(0, |layer| layer - 1),
+            (false, true) => 0,
+        };
+        for (entity, mut current, object_id) in layers.iter_mut() {
+            let Layer::Foreground(old) = *current;
+            let new = if targets.contains(&entity) {
+                layer
+            } else if renumber {
+                ranks[&old]
+            } else {
+                old
+            };
+            // 之后画的线条在所有对象之上
+            next_line.reserve_layer(new);
+            if new == old {
+                continue;
+            }
+            *current = Layer::Foreground(new);
+            if let Some(&ObjectId(id)) = object_id {
+                board_ops
+                    .send(BoardOpEvent(BoardOp::Reorder { id, layer: new }));
+            }
+        }
+    }
 }
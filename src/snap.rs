@@ -0,0 +1,209 @@
+// 1. 背景为方格或点阵时，移动的对象与画笔的点吸附到网格交点，按住 Alt 暂时关闭
+// 2. 移动选中的对象时，边缘或中心与其他对象对齐会吸附并显示参考线
+// 3. 三种吸附可以分别通过动作开关
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use crate::{
+    background::{BackgroundPattern, BoardBackground},
+    keybindings::{action_just_pressed, actions, RegisterAction},
+    projection_2d_control::MainCamera,
+};
+
+pub struct SnapPlugin;
+
+impl Plugin for SnapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SnapConfig>()
+            .init_resource::<SmartGuides>()
+            .register_action(actions::TOGGLE_GRID_SNAP, "Snap: Toggle grid")
+            .register_action(actions::TOGGLE_PEN_SNAP, "Snap: Toggle pen")
+            .register_action(actions::TOGGLE_GUIDES, "Snap: Toggle guides")
+            .add_systems(
+                Update,
+                (
+                    toggle_snap(|config| &mut config.grid)
+                        .run_if(action_just_pressed(actions::TOGGLE_GRID_SNAP)),
+                    toggle_snap(|config| &mut config.pen)
+                        .run_if(action_just_pressed(actions::TOGGLE_PEN_SNAP)),
+                    toggle_snap(|config| &mut config.guides)
+                        .run_if(action_just_pressed(actions::TOGGLE_GUIDES)),
+                    draw_smart_guides,
+                ),
+            );
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct SnapConfig {
+    /** 吸附到网格 */
+    pub grid: bool,
+    /** 画笔的点也吸附到网格 */
+    pub pen: bool,
+    /** 对齐参考线 */
+    pub guides: bool,
+    /** 对齐的距离（屏幕像素） */
+    pub guide_threshold: f32,
+}
+
+impl Default for SnapConfig {
+    fn default() -> Self {
+        SnapConfig {
+            grid: true,
+            pen: false,
+            guides: true,
+            guide_threshold: 6.,
+        }
+    }
+}
+
+/// 当前显示的对齐参考线（世界坐标的线段）
+#[derive(Resource, Default)]
+pub struct SmartGuides(pub Vec<(Vec2, Vec2)>);
+
+#[derive(SystemParam)]
+pub struct Snapper<'w, 's> {
+    config: Res<'w, SnapConfig>,
+    background: Res<'w, BoardBackground>,
+    keyboard_input: Res<'w, Input<KeyCode>>,
+    proj: Query<'w, 's, &'static OrthographicProjection, With<MainCamera>>,
+}
+
+impl Snapper<'_, '_> {
+    fn disabled(&self) -> bool {
+        self.keyboard_input
+            .any_pressed([KeyCode::AltLeft, KeyCode::AltRight])
+    }
+
+    /// 背景有网格时的网格间距
+    pub fn grid(&self) -> Option<f32> {
+        let has_grid = matches!(
+            self.background.pattern,
+            BackgroundPattern::Grid | BackgroundPattern::Dots
+        );
+        (self.config.grid && has_grid && !self.disabled())
+            .then_some(self.background.spacing)
+    }
+
+    pub fn snap_point(&self, point: Vec2) -> Vec2 {
+        match self.grid() {
+            Some(spacing) => snap_to_grid(point, spacing),
+            None => point,
+        }
+    }
+
+    /// 画笔的点只在打开 pen 时吸附
+    pub fn snap_pen_point(&self, point: Vec2) -> Vec2 {
+        if self.config.pen {
+            self.snap_point(point)
+        } else {
+            point
+        }
+    }
+
+    /// 移动包围盒时吸附后的位移与显示的参考线
+    pub fn snap_move(
+        &self,
+        bounds: Rect,
+        delta: Vec2,
+        others: &[Rect],
+    ) -> (Vec2, Vec<(Vec2, Vec2)>) {
+        if self.disabled() {
+            return (delta, vec![]);
+        }
+        let scale = self.proj.get_single().map_or(1., |proj| proj.scale);
+        let threshold = self.config.guide_threshold * scale;
+        let moved = offset_rect(bounds, delta);
+        let mut guide_offset = (None, None);
+        if self.config.guides {
+            guide_offset = align_offset(moved, others, threshold);
+        }
+        let grid_offset = self.grid().map_or(Vec2::ZERO, |spacing| {
+            snap_to_grid(moved.min, spacing) - moved.min
+        });
+        let offset = Vec2::new(
+            guide_offset.0.unwrap_or(grid_offset.x),
+            guide_offset.1.unwrap_or(grid_offset.y),
+        );
+        let delta = delta + offset;
+        let guides = guide_lines(offset_rect(bounds, delta), others);
+        (delta, guides)
+    }
+}
+
+pub fn snap_to_grid(point: Vec2, spacing: f32) -> Vec2 {
+    (point / spacing).round() * spacing
+}
+
+fn offset_rect(rect: Rect, delta: Vec2) -> Rect {
+    Rect::from_corners(rect.min + delta, rect.max + delta)
+}
+
+/// 左、中、右与下、中、上
+fn anchors(rect: Rect) -> ([f32; 3], [f32; 3]) {
+    let center = rect.center();
+    (
+        [rect.min.x, center.x, rect.max.x],
+        [rect.min.y, center.y, rect.max.y],
+    )
+}
+
+/// 每个轴上距离最近且在阈值内的对齐修正
+fn align_offset(
+    moved: Rect,
+    others: &[Rect],
+    threshold: f32,
+) -> (Option<f32>, Option<f32>) {
+    let (xs, ys) = anchors(moved);
+    let nearest = |mine: [f32; 3], theirs: &dyn Fn(Rect) -> [f32; 3]| {
+        others
+            .iter()
+            .flat_map(|other| theirs(*other))
+            .flat_map(|target| mine.map(|value| target - value))
+            .filter(|offset| offset.abs() <= threshold)
+            .min_by(|a, b| a.abs().total_cmp(&b.abs()))
+    };
+    (
+        nearest(xs, &|rect| anchors(rect).0),
+        nearest(ys, &|rect| anchors(rect).1),
+    )
+}
+
+/// 对齐的位置上画一条贯穿两个包围盒的线
+fn guide_lines(moved: Rect, others: &[Rect]) -> Vec<(Vec2, Vec2)> {
+    const EPSILON: f32 = 0.01;
+    let (xs, ys) = anchors(moved);
+    let mut lines = vec![];
+    for other in others {
+        let (other_xs, other_ys) = anchors(*other);
+        for x in xs {
+            if other_xs.iter().any(|v| (v - x).abs() < EPSILON) {
+                let bottom = moved.min.y.min(other.min.y);
+                let top = moved.max.y.max(other.max.y);
+                lines.push((Vec2::new(x, bottom), Vec2::new(x, top)));
+            }
+        }
+        for y in ys {
+            if other_ys.iter().any(|v| (v - y).abs() < EPSILON) {
+                let left = moved.min.x.min(other.min.x);
+                let right = moved.max.x.max(other.max.x);
+                lines.push((Vec2::new(left, y), Vec2::new(right, y)));
+            }
+        }
+    }
+    lines
+}
+
+fn draw_smart_guides(mut gizmos: Gizmos, guides: Res<SmartGuides>) {
+    for (start, end) in guides.0.iter() {
+        gizmos.line_2d(*start, *end, Color::FUCHSIA);
+    }
+}
+
+fn toggle_snap(
+    field: fn(&mut SnapConfig) -> &mut bool,
+) -> impl FnMut(ResMut<SnapConfig>) {
+    move |mut config| {
+        let enabled = field(&mut config);
+        *enabled = !*enabled;
+    }
+}
@@ -1,10 +1,14 @@
 use bevy::{
-    input::common_conditions::{
-        input_just_released, input_pressed, input_toggle_active,
-    },
+    input::common_conditions::{input_just_released, input_pressed},
     prelude::*,
 };
 
+use crate::keybindings::{
+    action_just_released, action_pressed, action_toggle_active, actions,
+    KeyBindingsPlugin,
+};
+use crate::{replay::ReplayState, tools::ToolRegistry};
+
 #[derive(States, Default, Debug, Hash, PartialEq, Eq, Clone)]
 pub enum CursorState {
     #[default]
@@ -18,8 +22,12 @@ pub enum ToolButton {
     Pen,
     Cursor,
     Eraser,
+    Eyedropper,
+    Laser,
     MoveCamera,
     TextInput,
+    /** 其他插件通过工具注册表添加的工具 */
+    Custom(&'static str),
 }
 
 #[derive(States, Default, Debug, Hash, PartialEq, Eq, Clone)]
@@ -33,7 +41,8 @@ pub struct StatesPlugin;
 
 impl Plugin for StatesPlugin {
     fn build(&self, app: &mut App) {
-        let run_mode_condition = || input_toggle_active(true, KeyCode::Escape);
+        let run_mode_condition =
+            || action_toggle_active(true, actions::DEBUG_MODE);
         let to_hovering = to_state(CursorState::Hovering)
             .run_if(run_mode_condition())
             .run_if(input_just_released(MouseButton::Left))
@@ -47,10 +56,10 @@ impl Plugin for StatesPlugin {
         );
 
         let to_normal_mode = to_state(RunMode::Normal)
-            .run_if(input_toggle_active(true, KeyCode::Escape));
+            .run_if(action_toggle_active(true, actions::DEBUG_MODE));
 
         let to_debug_mode = to_state(RunMode::Debug)
-            .run_if(input_toggle_active(false, KeyCode::Escape));
+            .run_if(action_toggle_active(false, actions::DEBUG_MODE));
 
         let in_normal_mode = (to_next_state_in_hovering, to_hovering)
             .run_if(in_state(RunMode::Normal));
@@ -58,15 +67,19 @@ impl Plugin for StatesPlugin {
         let (to_move_camera, back_from_move_camera) =
             state_stack(ToolButton::MoveCamera);
         let to_move_camera = to_move_camera
-            .run_if(input_pressed(KeyCode::Space))
+            .run_if(action_pressed(actions::MOVE_CAMERA))
             .run_if(not(in_state(ToolButton::MoveCamera)));
         let back_from_move_camera = back_from_move_camera
-            .run_if(input_just_released(KeyCode::Space))
+            .run_if(action_just_released(actions::MOVE_CAMERA))
             .run_if(in_state(ToolButton::MoveCamera));
 
-        app.add_state::<CursorState>()
+        app.add_plugins(KeyBindingsPlugin)
+            .add_state::<CursorState>()
             .add_state::<RunMode>()
             .add_state::<ToolButton>()
+            // 回放插件可以关闭，其他插件仍然按回放状态运行
+            .add_state::<ReplayState>()
+            .init_resource::<ToolRegistry>()
             .init_resource::<StateStack<ToolButton>>()
             .add_systems(
                 Update,
@@ -2,7 +2,8 @@ use bevy::{input::common_conditions::input_just_pressed, prelude::*};
 
 use crate::{
     common::clear_with, cursor::WorldTouchCursor,
-    double_click::on_double_click, states::ToolButton,
+    double_click::on_double_click, group::cursor_over_group,
+    projection_2d_control::BoardWindow, states::ToolButton,
 };
 
 pub struct TextInputPlugin;
@@ -14,7 +15,8 @@ impl Plugin for TextInputPlugin {
             (
                 (enter_text_input, spawn_text_cursor)
                     .run_if(in_state(ToolButton::Cursor))
-                    .run_if(on_double_click),
+                    .run_if(on_double_click)
+                    .run_if(not(cursor_over_group)),
                 (exit_text_input, clear_with::<With<TextCursor>>)
                     .before(enter_text_input)
                     .run_if(in_state(ToolButton::Cursor))
@@ -25,19 +27,22 @@ impl Plugin for TextInputPlugin {
     }
 }
 
-fn is_ime_enabled(windows: Query<&Window>) -> bool {
-    windows.single().ime_enabled
+pub(crate) fn is_ime_enabled(board_window: BoardWindow) -> bool {
+    board_window.get().is_some_and(|window| window.ime_enabled)
 }
 
-fn enter_text_input(mut windows: Query<&mut Window>) {
-    let mut window = windows.single_mut();
+fn enter_text_input(mut board_window: BoardWindow) {
+    let Some(mut window) = board_window.get_mut() else {
+        return;
+    };
     window.ime_position = window.cursor_position().unwrap();
     window.ime_enabled = true;
 }
 
-fn exit_text_input(mut windows: Query<&mut Window>) {
-    let mut window = windows.single_mut();
-    window.ime_enabled = false;
+fn exit_text_input(mut board_window: BoardWindow) {
+    if let Some(mut window) = board_window.get_mut() {
+        window.ime_enabled = false;
+    }
 }
 #[derive(Component)]
 struct TextCursor;
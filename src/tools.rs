@@ -0,0 +1,145 @@
+// 1. 工具注册表：工具栏按注册顺序为每个工具生成按钮
+// 2. 工具描述包含图标在 ui.png 中的位置、快捷键、鼠标指针，以及进入、离开、使用中运行的系统
+// 3. 嵌入 lines 的程序可以用 ToolButton::Custom 注册自己的工具，不需要修改 ui.rs 与 states.rs
+use bevy::{ecs::schedule::SystemConfigs, prelude::*};
+
+use crate::{
+    cursor::Cursor,
+    keybindings::{KeyChord, RegisterAction},
+    states::ToolButton,
+};
+
+/// 注册后的工具信息，工具栏据此生成按钮
+#[derive(Clone)]
+pub struct ToolInfo {
+    pub tool: ToolButton,
+    pub title: String,
+    /** 图标在 ui.png 中的位置（以 16 像素为单位） */
+    pub icon: Vec2,
+    /** 切换到该工具的动作 */
+    pub action: String,
+    /** 选中工具时的鼠标指针 */
+    pub cursor: Cursor,
+}
+
+/// 内置工具也通过 App::register_tool 注册
+#[derive(Resource, Clone, Default)]
+pub struct ToolRegistry(pub Vec<ToolInfo>);
+
+impl ToolRegistry {
+    pub fn get(&self, tool: &ToolButton) -> Option<&ToolInfo> {
+        self.0.iter().find(|info| info.tool == *tool)
+    }
+
+    /// 重复注册同一个工具时替换原来的信息，位置不变
+    pub fn register(&mut self, info: ToolInfo) {
+        match self.0.iter_mut().find(|current| current.tool == info.tool) {
+            Some(current) => *current = info,
+            None => self.0.push(info),
+        }
+    }
+}
+
+/// 工具的描述，通过 App::register_tool 注册
+pub struct ToolDescriptor {
+    info: ToolInfo,
+    keys: Vec<KeyChord>,
+    systems: Vec<(ToolSchedule, SystemConfigs)>,
+}
+
+enum ToolSchedule {
+    Enter,
+    Exit,
+    Update,
+}
+
+impl ToolDescriptor {
+    /// 默认使用系统鼠标指针，动作名为 tool.<名称>
+    pub fn new(tool: ToolButton, title: &str, icon: Vec2) -> Self {
+        let action = match &tool {
+            ToolButton::Custom(name) => format!("tool.{}", name),
+            other => format!("tool.{:?}", other).to_lowercase(),
+        };
+        ToolDescriptor {
+            info: ToolInfo {
+                tool,
+                title: title.into(),
+                icon,
+                action,
+                cursor: Cursor::Default,
+            },
+            keys: vec![],
+            systems: vec![],
+        }
+    }
+
+    pub fn with_action(mut self, action: &str) -> Self {
+        self.info.action = action.into();
+        self
+    }
+
+    pub fn with_key(mut self, key: KeyChord) -> Self {
+        self.keys.push(key);
+        self
+    }
+
+    pub fn with_cursor(mut self, cursor: Cursor) -> Self {
+        self.info.cursor = cursor;
+        self
+    }
+
+    /// 切换到该工具时运行一次
+    pub fn on_enter<M>(mut self, systems: impl IntoSystemConfigs<M>) -> Self {
+        self.systems
+            .push((ToolSchedule::Enter, systems.into_configs()));
+        self
+    }
+
+    /// 离开该工具时运行一次
+    pub fn on_exit<M>(mut self, systems: impl IntoSystemConfigs<M>) -> Self {
+        self.systems
+            .push((ToolSchedule::Exit, systems.into_configs()));
+        self
+    }
+
+    /// 该工具被选中时每帧运行
+    pub fn on_update<M>(mut self, systems: impl IntoSystemConfigs<M>) -> Self {
+        self.systems
+            .push((ToolSchedule::Update, systems.into_configs()));
+        self
+    }
+}
+
+pub trait RegisterTool {
+    fn register_tool(&mut self, descriptor: ToolDescriptor) -> &mut Self;
+}
+
+impl RegisterTool for App {
+    fn register_tool(&mut self, descriptor: ToolDescriptor) -> &mut Self {
+        let ToolDescriptor {
+            info,
+            keys,
+            systems,
+        } = descriptor;
+        self.register_action_with_keys(&info.action, &info.title, keys);
+        let tool = info.tool.clone();
+        self.world
+            .get_resource_or_insert_with(ToolRegistry::default)
+            .register(info);
+        for (schedule, systems) in systems {
+            match schedule {
+                ToolSchedule::Enter => {
+                    self.add_systems(OnEnter(tool.clone()), systems)
+                }
+                ToolSchedule::Exit => {
+                    self.add_systems(OnExit(tool.clone()), systems)
+                }
+                ToolSchedule::Update => self.add_systems(
+                    Update,
+                    systems.run_if(in_state(tool.clone())),
+                ),
+            };
+        }
+        self
+    }
+}
@@ -1,48 +1,59 @@
 use bevy::{
-    input::{keyboard::KeyboardInput, ButtonState},
     prelude::*,
     render::render_resource::{AsBindGroup, ShaderRef},
 };
 
 use crate::{
+    brush::spawn_brush_button,
+    brush_size::spawn_brush_size_controls,
+    command_palette::{CommandPalettePanel, CommandPalettePlugin},
     common::{hide_window_cursor, show_window_cursor},
     cursor::Cursor,
+    keybindings::ActionInput,
+    palette::{PaletteButton, PalettePlugin, PalettePopover},
+    plugins::LinesConfig,
     states::{RunMode, ToolButton},
+    tools::ToolRegistry,
 };
 
 pub struct UIPlugin;
 
 impl Plugin for UIPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(UiMaterialPlugin::<IconsUiMaterial>::default())
-            .add_systems(Startup, setup_ui)
-            .add_systems(
-                Update,
-                show_window_cursor.run_if(
-                    is_hover_tool_button_bar
-                        .or_else(resource_equals(Cursor::Default))
-                        .or_else(in_state(RunMode::Debug)),
-                ),
-            )
-            .add_systems(
-                Update,
-                hide_window_cursor
-                    .run_if(in_state(RunMode::Normal))
-                    .run_if(not(is_hover_tool_button_bar))
-                    .run_if(not(resource_equals(Cursor::Default))),
-            )
-            .add_systems(
-                Update,
-                (update_tool_button_background, focused_tool_by_key_code)
-                    .run_if(in_state(RunMode::Normal)),
-            );
+        app.add_plugins((
+            UiMaterialPlugin::<IconsUiMaterial>::default(),
+            PalettePlugin,
+            CommandPalettePlugin,
+        ))
+        .init_resource::<LinesConfig>()
+        .add_systems(Startup, setup_ui)
+        .add_systems(
+            Update,
+            show_window_cursor.run_if(
+                is_hover_tool_button_bar
+                    .or_else(resource_equals(Cursor::Default))
+                    .or_else(in_state(RunMode::Debug)),
+            ),
+        )
+        .add_systems(
+            Update,
+            hide_window_cursor
+                .run_if(in_state(RunMode::Normal))
+                .run_if(not(is_hover_tool_button_bar))
+                .run_if(not(resource_equals(Cursor::Default))),
+        )
+        .add_systems(
+            Update,
+            (update_tool_button_background, focused_tool_by_action)
+                .run_if(in_state(RunMode::Normal)),
+        );
     }
 }
 
-const TOOL_BUTTON_BACKGROUND: Color = Color::rgb(0.16, 0.16, 0.18);
+pub(crate) const TOOL_BUTTON_BACKGROUND: Color = Color::rgb(0.16, 0.16, 0.18);
 
 const TOOL_BUTTON_HOVER: Color = Color::rgb(0.2, 0.2, 0.24);
-const TOOL_BUTTON_FOCUS: Color = Color::rgb(0.26, 0.25, 0.41);
+pub(crate) const TOOL_BUTTON_FOCUS: Color = Color::rgb(0.26, 0.25, 0.41);
 
 #[derive(AsBindGroup, Asset, TypePath, Debug, Clone)]
 struct IconsUiMaterial {
@@ -65,17 +76,27 @@ impl UiMaterial for IconsUiMaterial {
 #[derive(Component)]
 struct ToolButtonBar;
 
+/// 切换到该工具的快捷键动作
 #[derive(Component)]
-struct ToolButtonKeyCode(KeyCode);
+struct ToolButtonAction(String);
 
 fn setup_ui(
     mut commands: Commands,
     mut ui_materials: ResMut<Assets<IconsUiMaterial>>,
     asset_server: Res<AssetServer>,
+    tool_registry: Res<ToolRegistry>,
+    config: Res<LinesConfig>,
 ) {
+    // 隐藏工具栏时仍然生成按钮，各工具的画笔设置保存在按钮上
+    let display = if config.toolbar {
+        Display::Flex
+    } else {
+        Display::None
+    };
     commands
         .spawn((NodeBundle {
             style: Style {
+                display,
                 width: Val::Percent(100.),
                 height: Val::Percent(100.),
                 justify_content: JustifyContent::Center,
@@ -89,7 +110,6 @@ fn setup_ui(
                 .spawn((
                     NodeBundle {
                         style: Style {
-                            width: Val::Px(550.),
                             height: Val::Px(44.),
                             margin: UiRect::top(Val::Px(16.)),
                             padding: UiRect::horizontal(Val::Px(12.)),
@@ -117,23 +137,7 @@ fn setup_ui(
                         }),
                         ..default()
                     };
-                    struct ToolButtonConfig {
-                        /** 工具类型 */
-                        tool: ToolButton,
-                        /** 图标纹理位置 */
-                        pos: Vec2,
-                        /** 按键绑定 */
-                        key_code: KeyCode,
-                        /** 鼠标指针 */
-                        cursor: Cursor,
-                    }
-                    let mut tool_btn = |config: ToolButtonConfig| {
-                        let ToolButtonConfig {
-                            tool,
-                            pos,
-                            key_code,
-                            cursor,
-                        } = config;
+                    for info in tool_registry.0.iter() {
                         parent
                             .spawn((
                                 NodeBundle {
@@ -144,48 +148,52 @@ fn setup_ui(
                                     ..default()
                                 },
                                 bevy::ui::Interaction::None,
-                                tool,
-                                cursor,
-                                ToolButtonKeyCode(key_code),
+                                info.tool.clone(),
+                                info.cursor.clone(),
+                                ToolButtonAction(info.action.clone()),
                             ))
                             .with_children(|parent| {
-                                parent.spawn(icon(pos));
+                                parent.spawn(icon(info.icon));
                             });
-                    };
-                    tool_btn(ToolButtonConfig {
-                        tool: ToolButton::Cursor,
-                        pos: Vec2::new(2., 0.),
-                        key_code: KeyCode::Key1,
-                        cursor: Cursor::Default,
-                    });
-                    tool_btn(ToolButtonConfig {
-                        tool: ToolButton::Pen,
-                        pos: Vec2::new(0., 0.),
-                        key_code: KeyCode::Key2,
-                        cursor: Cursor::default(),
-                    });
-                    tool_btn(ToolButtonConfig {
-                        tool: ToolButton::Eraser,
-                        pos: Vec2::new(1., 0.),
-                        key_code: KeyCode::Key3,
-                        cursor: Cursor::default(),
-                    });
+                    }
+                    spawn_brush_button(parent);
+                    spawn_brush_size_controls(parent);
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Px(20.),
+                                height: Val::Px(20.),
+                                margin: UiRect::left(Val::Px(4.)),
+                                ..default()
+                            },
+                            background_color: Color::WHITE.into(),
+                            ..default()
+                        },
+                        bevy::ui::Interaction::None,
+                        PaletteButton,
+                    ));
                 });
         });
 }
 
-fn is_hover_tool_button_bar(
-    interaction_query: Query<&Interaction, With<ToolButtonBar>>,
+/// 指针在工具栏或调色板上时不绘制
+pub(crate) fn is_hover_tool_button_bar(
+    interaction_query: Query<
+        &Interaction,
+        Or<(
+            With<ToolButtonBar>,
+            With<PalettePopover>,
+            With<CommandPalettePanel>,
+        )>,
+    >,
 ) -> bool {
-    if let Ok(interaction) = interaction_query.get_single() {
-        match interaction {
+    interaction_query
+        .iter()
+        .any(|interaction| match interaction {
             Interaction::Pressed => true,
             Interaction::Hovered => true,
             _ => false,
-        }
-    } else {
-        false
-    }
+        })
 }
 
 fn update_tool_button_background(
@@ -220,24 +228,16 @@ fn update_tool_button_background(
     }
 }
 
-fn focused_tool_by_key_code(
-    mut keyboard_input_events: EventReader<KeyboardInput>,
-    tool_button_query: Query<(&ToolButtonKeyCode, &ToolButton, &Cursor)>,
+fn focused_tool_by_action(
+    action_input: ActionInput,
+    tool_button_query: Query<(&ToolButtonAction, &ToolButton, &Cursor)>,
     mut focused_tool: ResMut<NextState<ToolButton>>,
     mut cursor_resource: ResMut<Cursor>,
 ) {
-    for ev in keyboard_input_events.read() {
-        if ev.state == ButtonState::Pressed {
-            for (key_code, tool, cursor) in tool_button_query.iter() {
-                if let (Some(key1), ToolButtonKeyCode(key2)) =
-                    (ev.key_code, key_code)
-                {
-                    if key1 == *key2 {
-                        focused_tool.set(tool.clone());
-                        *cursor_resource = cursor.clone();
-                    }
-                }
-            }
+    for (ToolButtonAction(action), tool, cursor) in tool_button_query.iter() {
+        if action_input.just_pressed(action) {
+            focused_tool.set(tool.clone());
+            *cursor_resource = cursor.clone();
         }
     }
 }
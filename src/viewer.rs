@@ -0,0 +1,531 @@
+// 1. 只读的网页直播：学生用浏览器打开即可看到白板，不需要安装任何东西
+// 2. 默认关闭，由环境变量 LINES_VIEWER 或 ViewerConfig 指定监听地址，例如 0.0.0.0:8080
+// 3. GET / 返回网页，GET /board.svg 返回白板的 SVG，GET /events 是 Server-Sent Events：
+//    连接时先发送 snapshot（整页 SVG），之后发送 stroke、point、remove 增量事件
+// 4. 服务器线程保存当前页，按 BoardOp 更新；文字与组的修改、切换页面，以及与白板模型不一致时
+//    （打开文档、恢复自动保存等）重新发送 snapshot；每个 /events 连接的队列有上限，跟不上的连接会被断开
+// 5. 显示线条与文字，图片不发送给浏览器
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError, SyncSender},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use serde_json::{json, Value};
+
+use crate::{
+    api::color_hex,
+    background::BoardBackground,
+    board::{BoardOp, Page, StrokeData},
+    board_view::{apply_board_ops, BoardModel, BoardOpEvent},
+};
+
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(20);
+/** 没有事件时定期发送注释，及时发现断开的连接 */
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+/** 每个 /events 连接最多积压的事件数 */
+const EVENT_LIMIT: usize = 1024;
+/** 空白板的可视区域 */
+const EMPTY_VIEW: Rect = Rect {
+    min: Vec2::new(-640., -360.),
+    max: Vec2::new(640., 360.),
+};
+const VIEW_MARGIN: f32 = 20.;
+
+const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Lines</title>
+<style>
+html, body { margin: 0; height: 100%; background: #000; }
+#board, #board svg { display: block; width: 100%; height: 100%; }
+#status {
+  position: fixed; right: 8px; bottom: 8px;
+  font: 12px sans-serif; color: #888;
+}
+</style>
+</head>
+<body>
+<div id="board"></div>
+<div id="status">Connecting...</div>
+<script>
+const NS = "http://www.w3.org/2000/svg";
+const board = document.getElementById("board");
+const status = document.getElementById("status");
+let svg = null;
+
+function fit() {
+  const box = svg.getBBox();
+  if (box.width === 0 && box.height === 0) return;
+  const m = 20;
+  svg.setAttribute("viewBox",
+    [box.x - m, box.y - m, box.width + 2 * m, box.height + 2 * m].join(" "));
+}
+
+function after(line, other) {
+  const [layer, id] = [+line.dataset.layer, +line.dataset.id];
+  const [otherLayer, otherId] = [+other.dataset.layer, +other.dataset.id];
+  return otherLayer > layer || (otherLayer === layer && otherId > id);
+}
+
+function place(line) {
+  for (const other of svg.children) {
+    if (other !== line && after(line, other)) {
+      svg.insertBefore(line, other);
+      return;
+    }
+  }
+  svg.appendChild(line);
+}
+
+function stroke(data) {
+  let line = document.getElementById("s" + data.id);
+  if (!line) {
+    line = document.createElementNS(NS, "polyline");
+    line.id = "s" + data.id;
+    line.setAttribute("fill", "none");
+    line.setAttribute("stroke-linecap", "round");
+    line.setAttribute("stroke-linejoin", "round");
+  }
+  line.dataset.id = data.id;
+  line.dataset.layer = data.layer;
+  line.setAttribute("stroke", data.color);
+  line.setAttribute("stroke-width", data.width);
+  line.setAttribute("points", data.points.map(p => p.join(",")).join(" "));
+  place(line);
+}
+
+const events = new EventSource("events");
+events.onopen = () => status.textContent = "Live";
+events.onerror = () => status.textContent = "Reconnecting...";
+events.addEventListener("snapshot", e => {
+  board.innerHTML = JSON.parse(e.data).svg;
+  svg = board.querySelector("svg");
+  document.body.style.background = svg.style.background;
+  fit();
+});
+events.addEventListener("stroke", e => {
+  stroke(JSON.parse(e.data));
+  fit();
+});
+events.addEventListener("point", e => {
+  const data = JSON.parse(e.data);
+  const line = document.getElementById("s" + data.id);
+  if (!line) return;
+  const point = svg.createSVGPoint();
+  [point.x, point.y] = data.point;
+  line.points.appendItem(point);
+  fit();
+});
+events.addEventListener("remove", e => {
+  const line = document.getElementById("s" + JSON.parse(e.data).id);
+  if (line) line.remove();
+});
+</script>
+</body>
+</html>
+"#;
+
+pub struct ViewerPlugin;
+
+impl Plugin for ViewerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ViewerConfig>()
+            .init_resource::<Viewer>()
+            .add_systems(
+                PostUpdate,
+                (
+                    listen.run_if(resource_changed::<ViewerConfig>()),
+                    publish_board,
+                )
+                    .chain()
+                    .after(apply_board_ops),
+            );
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct ViewerConfig {
+    /** 例如 0.0.0.0:8080；None 表示关闭 */
+    pub address: Option<String>,
+}
+
+/// 默认从环境变量 LINES_VIEWER 读取
+impl Default for ViewerConfig {
+    fn default() -> Self {
+        ViewerConfig {
+            address: std::env::var("LINES_VIEWER").ok(),
+        }
+    }
+}
+
+/// 服务器线程与白板共用的状态
+#[derive(Default)]
+struct ViewerState {
+    page: Page,
+    background: Color,
+    /** 每个 /events 连接一个发送线程 */
+    clients: Vec<SyncSender<String>>,
+}
+
+impl ViewerState {
+    fn snapshot(&self) -> String {
+        event(
+            "snapshot",
+            json!({ "svg": page_svg(&self.page, self.background) }),
+        )
+    }
+
+    /// 断开的与队列已满的连接在这里移除
+    fn broadcast(&mut self, event: String) {
+        self.clients
+            .retain(|client| client.try_send(event.clone()).is_ok());
+    }
+
+    /// 更新自己的一页，返回对应的增量事件；线条以外的修改发送整页
+    fn apply(&mut self, op: &BoardOp) -> Option<String> {
+        let stroke = op.id().is_some_and(|id| self.page.get(id).is_some());
+        self.page.apply(op).ok()?;
+        match op {
+            BoardOp::StrokeStart { id, .. }
+            | BoardOp::Move { id, .. }
+            | BoardOp::Scale { id, .. }
+            | BoardOp::Reorder { id, .. } => match self.page.get(*id) {
+                Some(stroke) => Some(event("stroke", stroke_json(stroke))),
+                None => Some(self.snapshot()),
+            },
+            BoardOp::StrokePoint { id, point, .. } => {
+                let stroke = self.page.get(*id)?;
+                let point = svg_point(*point + stroke.offset);
+                Some(event("point", json!({ "id": id, "point": point })))
+            }
+            BoardOp::Erase { id } if stroke => {
+                Some(event("remove", json!({ "id": id })))
+            }
+            BoardOp::StrokeEnd { .. }
+            | BoardOp::AddImage { .. }
+            | BoardOp::Lock { .. }
+            | BoardOp::Group { .. }
+            | BoardOp::Ungroup { .. } => None,
+            _ => Some(self.snapshot()),
+        }
+    }
+}
+
+fn event(name: &str, data: Value) -> String {
+    format!("event: {}\ndata: {}\n\n", name, data)
+}
+
+/// SVG 的 y 轴向下
+fn svg_point(point: Vec2) -> [f32; 2] {
+    [point.x, -point.y]
+}
+
+fn stroke_json(stroke: &StrokeData) -> Value {
+    let points: Vec<[f32; 2]> = stroke.world_points().map(svg_point).collect();
+    json!({
+        "id": stroke.id,
+        "points": points,
+        "color": color_hex(stroke.color.into()),
+        "width": stroke.width,
+        "layer": stroke.layer,
+    })
+}
+
+/// 白板的 SVG，线条与文字按图层顺序排列
+pub fn page_svg(page: &Page, background: Color) -> String {
+    let view = page
+        .strokes()
+        .flat_map(|stroke| stroke.world_points())
+        .chain(page.texts().map(|text| text.position))
+        .map(|point| Vec2::from(svg_point(point)))
+        .fold(None, |view: Option<Rect>, point| {
+            Some(view.map_or(Rect::from_corners(point, point), |view| {
+                view.union_point(point)
+            }))
+        })
+        .map_or(EMPTY_VIEW, |view| view.inset(VIEW_MARGIN));
+    let mut svg = format!(
+        concat!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg""#,
+            r#" viewBox="{} {} {} {}" style="background: {}">"#,
+        ),
+        view.min.x,
+        view.min.y,
+        view.width(),
+        view.height(),
+        color_hex(background),
+    );
+    let mut elements: Vec<(i8, u64, String)> = page
+        .strokes()
+        .map(|stroke| {
+            let points: Vec<String> = stroke
+                .world_points()
+                .map(|point| {
+                    let [x, y] = svg_point(point);
+                    format!("{},{}", x, y)
+                })
+                .collect();
+            let element = format!(
+                concat!(
+                    r#"<polyline id="s{id}" data-id="{id}" data-layer="{}""#,
+                    r#" points="{}" fill="none" stroke="{}" stroke-width="{}""#,
+                    r#" stroke-linecap="round" stroke-linejoin="round"/>"#,
+                ),
+                stroke.layer,
+                points.join(" "),
+                color_hex(stroke.color.into()),
+                stroke.width,
+                id = stroke.id,
+            );
+            (stroke.layer, stroke.id, element)
+        })
+        .collect();
+    elements.extend(page.texts().map(|text| {
+        let [x, y] = svg_point(text.position);
+        let element = format!(
+            concat!(
+                r#"<text id="t{id}" data-id="{id}" data-layer="{}" x="{}""#,
+                r#" y="{}" font-size="{}" fill="{}""#,
+                r#" dominant-baseline="hanging">{}</text>"#,
+            ),
+            text.layer,
+            x,
+            y,
+            text.size,
+            color_hex(text.color.into()),
+            escape_xml(&text.text),
+            id = text.id,
+        );
+        (text.layer, text.id, element)
+    }));
+    elements.sort_by_key(|(layer, id, _)| (*layer, *id));
+    for (.., element) in elements {
+        svg.push_str(&element);
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[derive(Resource, Default)]
+pub struct Viewer {
+    /** 实际监听的地址，端口为 0 时可以从这里得到分配的端口 */
+    pub address: Option<String>,
+    state: Arc<Mutex<ViewerState>>,
+    closed: Arc<AtomicBool>,
+}
+
+impl Viewer {
+    fn open(address: &str) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        let viewer = Viewer {
+            address: Some(listener.local_addr()?.to_string()),
+            ..default()
+        };
+        let state = viewer.state.clone();
+        let closed = viewer.closed.clone();
+        thread::spawn(move || accept_connections(listener, state, closed));
+        Ok(viewer)
+    }
+
+    /// 已经打开的 /events 连接随发送端一起结束
+    fn close(&mut self) {
+        self.closed.store(true, Ordering::Relaxed);
+        self.state.lock().unwrap().clients.clear();
+        *self = Viewer::default();
+    }
+}
+
+fn accept_connections(
+    listener: TcpListener,
+    state: Arc<Mutex<ViewerState>>,
+    closed: Arc<AtomicBool>,
+) {
+    while !closed.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                let state = state.clone();
+                thread::spawn(move || handle_connection(stream, state));
+            }
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                thread::sleep(ACCEPT_INTERVAL)
+            }
+            Err(err) => {
+                warn!("failed to accept a viewer connection: {}", err);
+                thread::sleep(ACCEPT_INTERVAL);
+            }
+        }
+    }
+}
+
+/// 每个连接只处理一个请求
+fn handle_connection(mut stream: TcpStream, state: Arc<Mutex<ViewerState>>) {
+    if stream.set_nonblocking(false).is_err()
+        || stream.set_read_timeout(Some(READ_TIMEOUT)).is_err()
+    {
+        return;
+    }
+    let Ok(reader) = stream.try_clone() else {
+        return;
+    };
+    let mut reader = BufReader::new(reader);
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).is_err() {
+        return;
+    }
+    // 忽略请求头
+    loop {
+        let mut header = String::new();
+        match reader.read_line(&mut header) {
+            Ok(0) | Err(_) => return,
+            Ok(_) if header.trim().is_empty() => break,
+            Ok(_) => {}
+        }
+    }
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let target = parts.next().unwrap_or_default();
+    let path = target.split('?').next().unwrap_or_default();
+    let _ = match (method, path) {
+        ("GET", "/") => respond(&mut stream, "200 OK", "text/html", PAGE),
+        ("GET", "/board.svg") => {
+            let svg = {
+                let state = state.lock().unwrap();
+                page_svg(&state.page, state.background)
+            };
+            respond(&mut stream, "200 OK", "image/svg+xml", &svg)
+        }
+        ("GET", "/events") => stream_events(stream, &state),
+        ("GET", _) => {
+            respond(&mut stream, "404 Not Found", "text/plain", "Not found")
+        }
+        _ => respond(
+            &mut stream,
+            "405 Method Not Allowed",
+            "text/plain",
+            "Method not allowed",
+        ),
+    };
+}
+
+fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &str,
+) -> io::Result<()> {
+    write!(
+        stream,
+        concat!(
+            "HTTP/1.1 {}\r\n",
+            "Content-Type: {}; charset=utf-8\r\n",
+            "Content-Length: {}\r\n",
+            "Cache-Control: no-cache\r\n",
+            "Connection: close\r\n\r\n",
+        ),
+        status,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(body.as_bytes())
+}
+
+/// 先发送 snapshot，之后转发白板的增量事件，直到连接断开
+fn stream_events(
+    mut stream: TcpStream,
+    state: &Mutex<ViewerState>,
+) -> io::Result<()> {
+    stream.write_all(
+        concat!(
+            "HTTP/1.1 200 OK\r\n",
+            "Content-Type: text/event-stream\r\n",
+            "Cache-Control: no-cache\r\n",
+            "Connection: keep-alive\r\n\r\n",
+        )
+        .as_bytes(),
+    )?;
+    let (sender, events) = mpsc::sync_channel(EVENT_LIMIT);
+    {
+        // 在锁内发送 snapshot 并登记，之后的事件不会遗漏
+        let mut state = state.lock().unwrap();
+        sender.send(state.snapshot()).unwrap();
+        state.clients.push(sender);
+    }
+    loop {
+        match events.recv_timeout(KEEP_ALIVE) {
+            Ok(event) => stream.write_all(event.as_bytes())?,
+            Err(RecvTimeoutError::Timeout) => {
+                stream.write_all(b": keep-alive\n\n")?
+            }
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+    }
+}
+
+fn listen(
+    config: Res<ViewerConfig>,
+    mut viewer: ResMut<Viewer>,
+    model: Res<BoardModel>,
+    background: Res<BoardBackground>,
+) {
+    viewer.close();
+    let Some(address) = &config.address else {
+        return;
+    };
+    match Viewer::open(address) {
+        Ok(opened) => {
+            info!("live viewer on http://{}", opened.address.as_ref().unwrap());
+            {
+                let mut state = opened.state.lock().unwrap();
+                state.page = model.0.page().clone();
+                state.background = background.color;
+            }
+            *viewer = opened;
+        }
+        Err(err) => {
+            error!("failed to start the viewer on {}: {}", address, err)
+        }
+    }
+}
+
+fn publish_board(
+    viewer: Res<Viewer>,
+    mut board_ops: EventReader<BoardOpEvent>,
+    model: Res<BoardModel>,
+    background: Res<BoardBackground>,
+) {
+    if viewer.address.is_none() {
+        board_ops.clear();
+        return;
+    }
+    let mut state = viewer.state.lock().unwrap();
+    for BoardOpEvent(op) in board_ops.read() {
+        if let Some(event) = state.apply(op) {
+            state.broadcast(event);
+        }
+    }
+    let page = model.0.page();
+    if background.is_changed() || (model.is_changed() && state.page != *page) {
+        state.page = page.clone();
+        state.background = background.color;
+        let snapshot = state.snapshot();
+        state.broadcast(snapshot);
+    }
+}
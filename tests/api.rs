@@ -0,0 +1,430 @@
+mod common;
+
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    net::TcpStream,
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use common::TestBoard;
+use lines::{
+    api::{Api, ApiConfig, INVALID_PARAMS, METHOD_NOT_FOUND, PARSE_ERROR},
+    projection_2d_control::MainCamera,
+    selected::Selected,
+    states::ToolButton,
+};
+use serde_json::{json, Value};
+
+/// 接口的客户端，读取线程把收到的消息放进通道
+struct Client {
+    writer: Box<dyn Write>,
+    messages: Receiver<Value>,
+    notifications: Vec<Value>,
+    next_id: u64,
+}
+
+impl Client {
+    fn new<R: std::io::Read + Send + 'static>(
+        reader: R,
+        writer: Box<dyn Write>,
+    ) -> Self {
+        let (sender, messages) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(reader).lines() {
+                let Ok(line) = line else {
+                    return;
+                };
+                if sender.send(serde_json::from_str(&line).unwrap()).is_err() {
+                    return;
+                }
+            }
+        });
+        Client {
+            writer,
+            messages,
+            notifications: vec![],
+            next_id: 0,
+        }
+    }
+
+    fn send_line(&mut self, line: &str) {
+        self.writer.write_all(line.as_bytes()).unwrap();
+        self.writer.write_all(b"\n").unwrap();
+    }
+
+    /// 推进白板直到收到 id 对应的回复
+    fn response(&mut self, board: &mut TestBoard, id: Value) -> Value {
+        for _ in 0..500 {
+            board.update();
+            while let Ok(message) = self.messages.try_recv() {
+                if message.get("id") == Some(&id) {
+                    return message;
+                }
+                self.notifications.push(message);
+            }
+            thread::sleep(Duration::from_millis(2));
+        }
+        panic!("no response to request {}", id);
+    }
+
+    fn request(
+        &mut self,
+        board: &mut TestBoard,
+        method: &str,
+        params: Value,
+    ) -> Value {
+        self.next_id += 1;
+        let id = json!(self.next_id);
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params,
+        });
+        self.send_line(&request.to_string());
+        self.response(board, id)
+    }
+
+    fn call(
+        &mut self,
+        board: &mut TestBoard,
+        method: &str,
+        params: Value,
+    ) -> Value {
+        let response = self.request(board, method, params);
+        assert!(response.get("error").is_none(), "{}", response);
+        response["result"].clone()
+    }
+
+    fn error_code(
+        &mut self,
+        board: &mut TestBoard,
+        method: &str,
+        params: Value,
+    ) -> i64 {
+        let response = self.request(board, method, params);
+        response["error"]["code"].as_i64().unwrap()
+    }
+
+    /// 推进几帧并收集通知
+    fn collect_notifications(&mut self, board: &mut TestBoard) -> Vec<Value> {
+        for _ in 0..10 {
+            board.update();
+            thread::sleep(Duration::from_millis(2));
+        }
+        self.notifications.extend(self.messages.try_iter());
+        std::mem::take(&mut self.notifications)
+    }
+}
+
+fn listen(board: &mut TestBoard, address: &str) -> String {
+    board.app.insert_resource(ApiConfig {
+        address: Some(address.to_string()),
+    });
+    board.update();
+    board
+        .app
+        .world
+        .resource::<Api>()
+        .address
+        .clone()
+        .expect("the API is not listening")
+}
+
+fn connect(board: &mut TestBoard) -> Client {
+    let address = listen(board, "127.0.0.1:0");
+    let stream = TcpStream::connect(address).unwrap();
+    let writer = Box::new(stream.try_clone().unwrap());
+    Client::new(stream, writer)
+}
+
+fn stroke_ids(objects: &Value) -> Vec<u64> {
+    objects["strokes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|stroke| stroke["id"].as_u64().unwrap())
+        .collect()
+}
+
+#[test]
+fn add_strokes_and_query_objects() {
+    let mut board = TestBoard::new();
+    let mut client = connect(&mut board);
+
+    let result = client.call(
+        &mut board,
+        "board.add_stroke",
+        json!({
+            "points": [[0., 0.], [100., 50.]],
+            "color": "#ff0000",
+            "width": 6.,
+        }),
+    );
+    let id = result["id"].as_u64().unwrap();
+    board.step(2);
+
+    let strokes = board.strokes();
+    assert_eq!(strokes.len(), 1);
+    assert_eq!(strokes[0].id, id);
+    assert_eq!(Color::from(strokes[0].color), Color::rgb_u8(255, 0, 0));
+    assert_eq!(strokes[0].width, 6.);
+
+    let objects = client.call(&mut board, "board.objects", Value::Null);
+    assert_eq!(stroke_ids(&objects), vec![id]);
+    let stroke = &objects["strokes"][0];
+    assert_eq!(stroke["points"], json!([[0., 0.], [100., 50.]]));
+    assert_eq!(stroke["color"], "#ff0000");
+}
+
+#[test]
+fn add_shapes_and_text() {
+    let mut board = TestBoard::new();
+    let mut client = connect(&mut board);
+
+    let rectangle = client.call(
+        &mut board,
+        "board.add_shape",
+        json!({ "shape": "rectangle", "from": [0., 0.], "to": [100., 50.] }),
+    );
+    assert_eq!(rectangle["ids"].as_array().unwrap().len(), 1);
+    let arrow = client.call(
+        &mut board,
+        "board.add_shape",
+        json!({ "shape": "arrow", "from": [0., 0.], "to": [100., 0.] }),
+    );
+    assert_eq!(arrow["ids"].as_array().unwrap().len(), 2);
+    let text = client.call(
+        &mut board,
+        "board.add_text",
+        json!({ "text": "Well done", "position": [10., 20.], "size": 24. }),
+    );
+    board.step(2);
+
+    let objects = client.call(&mut board, "board.objects", Value::Null);
+    assert_eq!(stroke_ids(&objects).len(), 3);
+    let rectangle = &objects["strokes"][0]["points"];
+    assert_eq!(rectangle.as_array().unwrap().len(), 5);
+    let texts = objects["texts"].as_array().unwrap();
+    assert_eq!(texts.len(), 1);
+    assert_eq!(texts[0]["text"], "Well done");
+    assert_eq!(texts[0]["position"], json!([10., 20.]));
+    assert_eq!(texts[0]["id"], text["id"]);
+}
+
+#[test]
+fn switch_tools_and_run_actions() {
+    let mut board = TestBoard::new();
+    let mut client = connect(&mut board);
+
+    let tools = client.call(&mut board, "tools.list", Value::Null);
+    assert!(tools
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|tool| tool["tool"] == "eraser"));
+    client.call(&mut board, "tools.set", json!({ "tool": "eraser" }));
+    board.step(2);
+    assert_eq!(board.tool(), ToolButton::Eraser);
+    assert_eq!(client.call(&mut board, "tools.get", Value::Null), "eraser");
+
+    client.call(
+        &mut board,
+        "board.add_stroke",
+        json!({ "points": [[0., 0.], [10., 0.]] }),
+    );
+    board.step(2);
+    assert_eq!(board.strokes().len(), 1);
+    client.call(&mut board, "actions.run", json!({ "action": "undo" }));
+    board.step(2);
+    assert!(board.strokes().is_empty());
+}
+
+#[test]
+fn select_objects() {
+    let mut board = TestBoard::new();
+    let mut client = connect(&mut board);
+    let mut ids = vec![];
+    for y in [0., 100.] {
+        let result = client.call(
+            &mut board,
+            "board.add_stroke",
+            json!({ "points": [[0., y], [100., y]] }),
+        );
+        ids.push(result["id"].as_u64().unwrap());
+    }
+    board.step(2);
+
+    client.call(&mut board, "selection.set", json!({ "strokes": [ids[1]] }));
+    assert_eq!(board.app.world.resource::<Selected>().0.len(), 1);
+    let selection = client.call(&mut board, "selection.get", Value::Null);
+    assert_eq!(selection["strokes"], json!([ids[1]]));
+
+    client.call(&mut board, "selection.set", json!({}));
+    assert!(board.app.world.resource::<Selected>().0.is_empty());
+}
+
+#[test]
+fn move_the_camera() {
+    let mut board = TestBoard::new();
+    let mut client = connect(&mut board);
+
+    client.call(
+        &mut board,
+        "camera.set",
+        json!({ "center": [100., 50.], "scale": 2. }),
+    );
+    let (transform, projection) = board
+        .app
+        .world
+        .query_filtered::<(&Transform, &OrthographicProjection), With<MainCamera>>()
+        .single(&board.app.world);
+    assert_eq!(transform.translation.truncate(), Vec2::new(100., 50.));
+    assert_eq!(projection.scale, 2.);
+
+    let camera = client.call(&mut board, "camera.get", Value::Null);
+    assert_eq!(camera, json!({ "center": [100., 50.], "scale": 2. }));
+}
+
+#[test]
+fn export_the_board() {
+    let mut board = TestBoard::new();
+    let mut client = connect(&mut board);
+    client.call(
+        &mut board,
+        "board.add_stroke",
+        json!({ "points": [[0., 0.], [10., 0.], [20., 5.]] }),
+    );
+    board.step(2);
+
+    fs::create_dir_all(&board.dir).unwrap();
+    for (format, file) in [
+        ("lines", "board.json"),
+        ("excalidraw", "board.excalidraw"),
+        ("inkml", "board.inkml"),
+    ] {
+        let path = board.dir.join(file);
+        client.call(
+            &mut board,
+            "board.export",
+            json!({ "format": format, "path": path }),
+        );
+        assert!(path.exists(), "{:?}", path);
+    }
+    let inkml = fs::read_to_string(board.dir.join("board.inkml")).unwrap();
+    assert!(inkml.contains("<trace"));
+}
+
+#[test]
+fn subscribers_receive_board_changes() {
+    let mut board = TestBoard::new();
+    let mut client = connect(&mut board);
+    assert_eq!(client.call(&mut board, "subscribe", Value::Null), true);
+
+    board.drag(Vec2::new(400., 500.), Vec2::new(800., 500.), 5);
+    board.tap_key(KeyCode::Key3);
+    let notifications = client.collect_notifications(&mut board);
+
+    let methods: Vec<&str> = notifications
+        .iter()
+        .map(|notification| notification["method"].as_str().unwrap())
+        .collect();
+    assert!(methods.contains(&"board.changed"), "{:?}", methods);
+    assert!(notifications.iter().any(|notification| {
+        notification["method"] == "tool.changed"
+            && notification["params"]["tool"] == "eraser"
+    }));
+    let first = &notifications[0]["params"]["op"];
+    assert!(first.get("StrokeStart").is_some(), "{}", first);
+}
+
+#[test]
+fn errors_are_reported() {
+    let mut board = TestBoard::new();
+    let mut client = connect(&mut board);
+
+    assert_eq!(
+        client.error_code(&mut board, "board.fly", Value::Null),
+        METHOD_NOT_FOUND
+    );
+    assert_eq!(
+        client.error_code(&mut board, "board.add_stroke", json!({})),
+        INVALID_PARAMS
+    );
+    assert_eq!(
+        client.error_code(
+            &mut board,
+            "board.add_stroke",
+            json!({ "points": [[0., 0.]], "width": -1. }),
+        ),
+        INVALID_PARAMS
+    );
+    assert_eq!(
+        client.error_code(
+            &mut board,
+            "selection.set",
+            json!({ "strokes": [12345] }),
+        ),
+        INVALID_PARAMS
+    );
+    assert_eq!(
+        client.error_code(
+            &mut board,
+            "actions.run",
+            json!({ "action": "move_camera" }),
+        ),
+        INVALID_PARAMS
+    );
+
+    client.send_line("{ not json");
+    let response = client.response(&mut board, Value::Null);
+    assert_eq!(response["error"]["code"], PARSE_ERROR);
+    assert!(board.strokes().is_empty());
+}
+
+#[test]
+fn only_loopback_addresses_are_accepted() {
+    let mut board = TestBoard::new();
+    for address in ["0.0.0.0:0", "[::]:0"] {
+        board.app.insert_resource(ApiConfig {
+            address: Some(address.to_string()),
+        });
+        board.update();
+        assert_eq!(board.app.world.resource::<Api>().address, None);
+    }
+    let address = listen(&mut board, "localhost:0");
+    assert!(address.starts_with("127.0.0.1:") || address.starts_with("[::1]:"));
+}
+
+#[cfg(unix)]
+#[test]
+fn listen_on_a_unix_socket() {
+    use std::os::unix::net::UnixStream;
+
+    let mut board = TestBoard::new();
+    fs::create_dir_all(&board.dir).unwrap();
+    let path = board.dir.join("lines.sock");
+    let address = format!("unix:{}", path.display());
+    assert_eq!(listen(&mut board, &address), address);
+
+    let stream = UnixStream::connect(&path).unwrap();
+    let writer = Box::new(stream.try_clone().unwrap());
+    let mut client = Client::new(stream, writer);
+    client.call(
+        &mut board,
+        "board.add_stroke",
+        json!({ "points": [[0., 0.], [10., 0.]] }),
+    );
+    board.step(2);
+    assert_eq!(board.strokes().len(), 1);
+
+    // 关闭后删除 socket 文件
+    board.app.insert_resource(ApiConfig { address: None });
+    board.step(5);
+    thread::sleep(Duration::from_millis(100));
+    assert!(!path.exists());
+}
@@ -0,0 +1,100 @@
+mod common;
+
+use std::fs;
+
+use bevy::{prelude::*, window::WindowFocused};
+use common::TestBoard;
+use lines::{
+    board::TextData, board_view::BoardOpEvent, document::Document,
+    recording::BoardOp,
+};
+
+const FROM: Vec2 = Vec2::new(400., 500.);
+const TO: Vec2 = Vec2::new(800., 500.);
+
+/// 失去焦点时有修改的白板立即保存
+fn lose_focus(board: &mut TestBoard) {
+    let window = board.window();
+    board.app.world.send_event(WindowFocused {
+        window,
+        focused: false,
+    });
+    board.update();
+}
+
+fn files(board: &TestBoard, prefix: &str) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(board.recovery_dir())
+        .unwrap()
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| name.starts_with(prefix))
+        .collect();
+    names.sort();
+    names
+}
+
+fn last_snapshot(board: &TestBoard) -> Document {
+    let name = files(board, "autosave-").pop().expect("no autosave");
+    Document::load(&board.recovery_dir().join(name)).unwrap()
+}
+
+fn recovery_offered(board: &mut TestBoard) -> bool {
+    board
+        .app
+        .world
+        .query::<&Text>()
+        .iter(&board.app.world)
+        .flat_map(|text| &text.sections)
+        .any(|section| section.value.contains("not closed properly"))
+}
+
+#[test]
+fn edits_to_any_object_are_autosaved() {
+    let mut board = TestBoard::new();
+    board.drag(FROM, TO, 10);
+    lose_focus(&mut board);
+    assert_eq!(last_snapshot(&board).pages[0].strokes.len(), 1);
+
+    board.app.world.send_event(BoardOpEvent(BoardOp::AddText {
+        text: TextData {
+            id: 100,
+            text: "hello".to_string(),
+            position: Vec2::ZERO,
+            size: 20.,
+            color: Color::WHITE.into(),
+            layer: 0,
+        },
+    }));
+    board.update();
+    lose_focus(&mut board);
+    assert_eq!(last_snapshot(&board).pages[0].texts.len(), 1);
+}
+
+#[test]
+fn a_second_instance_is_not_offered_recovery() {
+    let mut first = TestBoard::new();
+    first.drag(FROM, TO, 10);
+    lose_focus(&mut first);
+
+    let mut second = TestBoard::sharing_recovery(&first);
+    assert!(!recovery_offered(&mut second));
+    assert_eq!(files(&first, "session").len(), 2);
+}
+
+#[test]
+fn stale_sessions_are_offered_recovery() {
+    let mut first = TestBoard::new();
+    first.drag(FROM, TO, 10);
+    lose_focus(&mut first);
+    // 异常退出的实例留下了没有加锁的锁文件
+    let stale = first.recovery_dir().join("session-0-0.lock");
+    fs::write(&stale, "0").unwrap();
+
+    let mut second = TestBoard::sharing_recovery(&first);
+    assert!(recovery_offered(&mut second));
+    assert!(!stale.exists());
+    assert_eq!(files(&first, "session").len(), 2);
+
+    second.tap_key(KeyCode::Y);
+    second.update();
+    assert_eq!(second.strokes().len(), 1);
+}
@@ -0,0 +1,603 @@
+mod common;
+
+use bevy::{
+    input::mouse::{MouseScrollUnit, MouseWheel},
+    prelude::*,
+};
+use common::TestBoard;
+use lines::{
+    board::{
+        Board, BoardError, BrushKind, ImageData, ImageSource, PageData, Rgba,
+        StrokeData, TextData, LAYER_MAX,
+    },
+    document::Document,
+    draw::LineId,
+    inkml::{read_inkml, write_inkml},
+    recording::{stroke_ops, BoardOp},
+    selected::Selected,
+    states::ToolButton,
+};
+use serde_json::json;
+
+fn start(id: u64, layer: i8) -> BoardOp {
+    BoardOp::StrokeStart {
+        id,
+        color: Rgba::WHITE,
+        width: 4.,
+        layer,
+        brush: BrushKind::Chalk,
+    }
+}
+
+fn point(id: u64, x: f32, y: f32) -> BoardOp {
+    BoardOp::StrokePoint {
+        id,
+        point: Vec2::new(x, y),
+        time: None,
+        pressure: None,
+    }
+}
+
+fn stroke(id: u64, layer: i8) -> StrokeData {
+    StrokeData {
+        id,
+        points: vec![Vec2::ZERO, Vec2::new(10., 0.)],
+        color: Rgba::WHITE,
+        width: 4.,
+        layer,
+        ..default()
+    }
+}
+
+fn apply_all(board: &mut Board, ops: &[BoardOp]) {
+    for op in ops {
+        board.apply(op).unwrap();
+    }
+}
+
+#[test]
+fn ops_build_strokes() {
+    let mut board = Board::default();
+    apply_all(
+        &mut board,
+        &[
+            start(1, 0),
+            point(1, 0., 0.),
+            point(1, 10., 5.),
+            BoardOp::Move {
+                id: 1,
+                delta: Vec2::new(2., 3.),
+            },
+        ],
+    );
+    assert!(board.page().is_open(1));
+    board.apply(&BoardOp::StrokeEnd { id: 1 }).unwrap();
+
+    let stroke = board.page().get(1).unwrap();
+    assert!(!board.page().is_open(1));
+    assert_eq!(stroke.points, vec![Vec2::ZERO, Vec2::new(10., 5.)]);
+    assert_eq!(stroke.offset, Vec2::new(2., 3.));
+    assert_eq!(
+        stroke.world_points().collect::<Vec<_>>(),
+        vec![Vec2::new(2., 3.), Vec2::new(12., 8.)]
+    );
+}
+
+#[test]
+fn strokes_are_ordered_by_layer_then_id() {
+    let mut board = Board::default();
+    for (id, layer) in [(1, 2), (2, 0), (3, 2), (4, 1)] {
+        board.insert(stroke(id, layer)).unwrap();
+    }
+    board.apply(&BoardOp::Reorder { id: 3, layer: 0 }).unwrap();
+    let order: Vec<u64> = board
+        .page()
+        .ordered()
+        .iter()
+        .map(|stroke| stroke.id)
+        .collect();
+    assert_eq!(order, vec![2, 3, 4, 1]);
+}
+
+#[test]
+fn rejected_ops_leave_the_board_unchanged() {
+    let mut board = Board::default();
+    apply_all(
+        &mut board,
+        &[start(1, 0), point(1, 0., 0.), BoardOp::StrokeEnd { id: 1 }],
+    );
+    let before = board.clone();
+
+    for (op, err) in [
+        (start(1, 0), BoardError::DuplicateId(1)),
+        (point(1, 5., 5.), BoardError::Finished(1)),
+        (point(2, 5., 5.), BoardError::UnknownId(2)),
+        (BoardOp::StrokeEnd { id: 1 }, BoardError::Finished(1)),
+        (BoardOp::Erase { id: 2 }, BoardError::UnknownId(2)),
+        (start(2, -1), BoardError::InvalidLayer(-1)),
+        (
+            BoardOp::Reorder {
+                id: 1,
+                layer: LAYER_MAX + 1,
+            },
+            BoardError::InvalidLayer(LAYER_MAX + 1),
+        ),
+        (
+            BoardOp::StrokeStart {
+                id: 2,
+                color: Rgba::WHITE,
+                width: 0.,
+                layer: 0,
+                brush: BrushKind::Chalk,
+            },
+            BoardError::InvalidWidth(0.),
+        ),
+        (
+            BoardOp::Move {
+                id: 1,
+                delta: Vec2::new(f32::NAN, 0.),
+            },
+            BoardError::InvalidPoint(1),
+        ),
+    ] {
+        assert_eq!(board.apply(&op), Err(err), "{:?}", op);
+        assert_eq!(board, before, "{:?}", op);
+    }
+}
+
+#[test]
+fn samples_must_cover_every_point() {
+    let mut board = Board::default();
+    apply_all(&mut board, &[start(1, 0), point(1, 0., 0.)]);
+    let timed = BoardOp::StrokePoint {
+        id: 1,
+        point: Vec2::ONE,
+        time: Some(0.1),
+        pressure: None,
+    };
+    assert_eq!(board.apply(&timed), Err(BoardError::Samples(1)));
+
+    let mut invalid = stroke(2, 0);
+    invalid.times = vec![0.];
+    assert_eq!(board.insert(invalid), Err(BoardError::Samples(2)));
+}
+
+#[test]
+fn stroke_ops_rebuild_the_same_stroke() {
+    let original = StrokeData {
+        id: 7,
+        points: vec![Vec2::ZERO, Vec2::new(3., 4.), Vec2::new(6., 0.)],
+        color: Rgba::new(1., 0., 0., 1.),
+        width: 3.,
+        layer: 5,
+        offset: Vec2::new(-10., 20.),
+        times: vec![0., 0.1, 0.2],
+        pressures: vec![0.5, 0.6, 0.7],
+        brush: BrushKind::Marker,
+    };
+    let mut board = Board::default();
+    apply_all(&mut board, &stroke_ops(&original));
+    assert_eq!(board.page().get(7), Some(&original));
+    assert!(!board.page().is_open(7));
+}
+
+#[test]
+fn scale_keeps_the_center_in_place() {
+    let mut board = Board::default();
+    let mut data = stroke(1, 0);
+    data.offset = Vec2::new(10., 0.);
+    board.insert(data).unwrap();
+    board
+        .apply(&BoardOp::Scale {
+            id: 1,
+            center: Vec2::new(10., 0.),
+            factor: 2.,
+        })
+        .unwrap();
+    let world: Vec<Vec2> =
+        board.page().get(1).unwrap().world_points().collect();
+    assert_eq!(world, vec![Vec2::new(10., 0.), Vec2::new(30., 0.)]);
+}
+
+#[test]
+fn ids_are_unique_and_clear_removes_every_stroke() {
+    let mut board = Board::default();
+    board.insert(stroke(1, 0)).unwrap();
+    board.insert(stroke(2, 0)).unwrap();
+    assert_eq!(board.insert(stroke(1, 0)), Err(BoardError::DuplicateId(1)));
+    assert_eq!(board.apply(&start(1, 0)), Err(BoardError::DuplicateId(1)));
+
+    board.apply(&BoardOp::Clear).unwrap();
+    assert!(board.page().is_empty());
+}
+
+#[test]
+fn inkml_exports_from_the_model() {
+    let mut board = Board::default();
+    for id in 1..=3 {
+        board.insert(stroke(id, 0)).unwrap();
+    }
+    let strokes = read_inkml(&write_inkml(&board.page().to_strokes())).unwrap();
+    assert_eq!(strokes.len(), 3);
+    assert!(strokes.iter().all(|stroke| stroke.points.len() == 2));
+}
+
+const FROM: Vec2 = Vec2::new(400., 500.);
+const TO: Vec2 = Vec2::new(800., 500.);
+
+fn assert_in_sync(board: &mut TestBoard) {
+    let strokes = board.strokes();
+    let model = board.model().page().to_strokes();
+    assert_eq!(model.len(), strokes.len());
+    for (model, view) in model.iter().zip(strokes.iter()) {
+        assert_eq!(model.id, view.id);
+        assert_eq!(model.style(), view.style());
+        assert_eq!(model.layer, view.layer);
+        assert_eq!(model.times, view.times);
+        let model_points: Vec<Vec2> = model.world_points().collect();
+        let view_points: Vec<Vec2> = view.world_points().collect();
+        assert_eq!(model_points.len(), view_points.len());
+        for (a, b) in model_points.iter().zip(view_points.iter()) {
+            assert!(a.distance(*b) < 0.01, "{:?} != {:?}", a, b);
+        }
+    }
+}
+
+#[test]
+fn the_model_follows_drawing_moving_and_erasing() {
+    let mut board = TestBoard::new();
+    board.drag(FROM, TO, 10);
+    board.drag(Vec2::new(400., 200.), Vec2::new(800., 200.), 5);
+    assert_eq!(board.model().page().len(), 2);
+    assert_in_sync(&mut board);
+
+    board.tap_key(KeyCode::Key1);
+    board.drag(FROM.lerp(TO, 0.5), FROM.lerp(TO, 0.5) + Vec2::Y * 50., 5);
+    assert_in_sync(&mut board);
+
+    board.tap_key(KeyCode::Key3);
+    board.drag(Vec2::new(600., 150.), Vec2::new(600., 250.), 5);
+    assert_eq!(board.model().page().len(), 1);
+    assert_in_sync(&mut board);
+
+    board.tap_chord(&[KeyCode::ControlLeft], KeyCode::Z);
+    assert!(board.model().page().is_empty());
+    assert_in_sync(&mut board);
+}
+
+#[test]
+fn the_model_follows_group_scaling() {
+    let mut board = TestBoard::new();
+    board.drag(FROM, TO, 5);
+    board.set_tool(ToolButton::Cursor);
+    let line = board
+        .app
+        .world
+        .query_filtered::<Entity, With<LineId>>()
+        .single(&board.app.world);
+    board.app.world.resource_mut::<Selected>().0 = vec![line];
+    board.tap_chord(&[KeyCode::ControlLeft], KeyCode::G);
+
+    board.press_key(KeyCode::AltLeft);
+    let window = board.window();
+    board.app.world.send_event(MouseWheel {
+        unit: MouseScrollUnit::Line,
+        x: 0.,
+        y: 2.,
+        window,
+    });
+    board.step(2);
+    board.release_key(KeyCode::AltLeft);
+    board.step(2);
+
+    assert!(board
+        .ops()
+        .iter()
+        .any(|op| matches!(op, BoardOp::Scale { .. })));
+    assert_in_sync(&mut board);
+}
+
+#[test]
+fn saving_writes_the_model() {
+    let mut board = TestBoard::new();
+    board.drag(FROM, TO, 5);
+    board.tap_chord(&[KeyCode::ControlLeft], KeyCode::S);
+    let document = Document::load(&board.dir.join("board.lines.json")).unwrap();
+    assert_eq!(document.pages, board.model().to_data());
+}
+
+#[test]
+fn colors_read_documents_saved_with_bevy_colors() {
+    let saved = serde_json::to_string(&Color::rgba(1., 0.5, 0., 0.8)).unwrap();
+    let color: Rgba = serde_json::from_str(&saved).unwrap();
+    assert_eq!(color, Rgba::new(1., 0.5, 0., 0.8));
+    assert_eq!(serde_json::to_string(&color).unwrap(), saved);
+}
+
+fn text(id: u64, position: Vec2) -> TextData {
+    TextData {
+        id,
+        text: "hello".to_string(),
+        position,
+        size: 20.,
+        color: Rgba::WHITE,
+        layer: 0,
+    }
+}
+
+fn image(id: u64, position: Vec2) -> ImageData {
+    ImageData {
+        id,
+        source: ImageSource::Path("board.png".into()),
+        position,
+        scale: 1.,
+        layer: 0,
+        locked: false,
+    }
+}
+
+/// 线条 1，文字 2，图片 3；组 4 包含 1 与 2，组 5 包含组 4 与 3
+fn grouped_board() -> Board {
+    let mut board = Board::from_strokes([stroke(1, 0)]).unwrap();
+    apply_all(
+        &mut board,
+        &[
+            BoardOp::AddText {
+                text: text(2, Vec2::new(0., 10.)),
+            },
+            BoardOp::AddImage {
+                image: image(3, Vec2::new(20., 0.)),
+            },
+            BoardOp::Group {
+                id: 4,
+                members: vec![1, 2],
+            },
+            BoardOp::Group {
+                id: 5,
+                members: vec![4, 3],
+            },
+        ],
+    );
+    board
+}
+
+#[test]
+fn groups_move_and_scale_every_member() {
+    let mut board = grouped_board();
+    assert_eq!(board.page().group_of(1), Some(4));
+    assert_eq!(board.page().group_of(4), Some(5));
+    assert_eq!(board.page().descendants(5), vec![5, 4, 3, 1, 2]);
+
+    board
+        .apply(&BoardOp::Move {
+            id: 5,
+            delta: Vec2::new(5., 5.),
+        })
+        .unwrap();
+    board
+        .apply(&BoardOp::Scale {
+            id: 4,
+            center: Vec2::new(5., 5.),
+            factor: 2.,
+        })
+        .unwrap();
+    let page = board.page();
+    let points: Vec<Vec2> = page.get(1).unwrap().world_points().collect();
+    assert_eq!(points, vec![Vec2::new(5., 5.), Vec2::new(25., 5.)]);
+    assert_eq!(page.text(2).unwrap().position, Vec2::new(5., 25.));
+    assert_eq!(page.text(2).unwrap().size, 40.);
+    assert_eq!(page.image(3).unwrap().position, Vec2::new(25., 5.));
+    assert_eq!(page.image(3).unwrap().scale, 1.);
+}
+
+#[test]
+fn ungrouping_and_erasing_keep_groups_consistent() {
+    let mut board = grouped_board();
+    board.apply(&BoardOp::Ungroup { id: 4 }).unwrap();
+    assert_eq!(board.page().group_of(1), Some(5));
+    assert_eq!(board.page().group(5).unwrap().members, vec![3, 1, 2]);
+
+    // 组的成员都被删除后组也被删除
+    for id in [1, 2, 3] {
+        board.apply(&BoardOp::Erase { id }).unwrap();
+    }
+    assert!(board.page().is_empty());
+
+    let mut board = grouped_board();
+    board.apply(&BoardOp::Erase { id: 5 }).unwrap();
+    assert!(board.page().is_empty());
+}
+
+#[test]
+fn rejected_object_ops_leave_the_board_unchanged() {
+    let mut board = grouped_board();
+    board
+        .apply(&BoardOp::AddText {
+            text: text(6, Vec2::ZERO),
+        })
+        .unwrap();
+    let before = board.clone();
+
+    let mut tiny = text(7, Vec2::ZERO);
+    tiny.size = 0.;
+    for (op, err) in [
+        (
+            BoardOp::AddImage {
+                image: image(2, Vec2::ZERO),
+            },
+            BoardError::DuplicateId(2),
+        ),
+        (BoardOp::AddText { text: tiny }, BoardError::InvalidSize(0.)),
+        (point(2, 0., 0.), BoardError::WrongKind(2)),
+        (
+            BoardOp::Lock {
+                id: 1,
+                locked: true,
+            },
+            BoardError::WrongKind(1),
+        ),
+        (BoardOp::Ungroup { id: 1 }, BoardError::WrongKind(1)),
+        (
+            BoardOp::Group {
+                id: 8,
+                members: vec![],
+            },
+            BoardError::EmptyGroup(8),
+        ),
+        (
+            BoardOp::Group {
+                id: 8,
+                members: vec![6, 9],
+            },
+            BoardError::UnknownId(9),
+        ),
+        (
+            BoardOp::Group {
+                id: 8,
+                members: vec![6, 6],
+            },
+            BoardError::DuplicateId(6),
+        ),
+        // 6 不在组中，1 在组 4 中
+        (
+            BoardOp::Group {
+                id: 8,
+                members: vec![6, 1],
+            },
+            BoardError::Grouped(1),
+        ),
+        (BoardOp::SelectPage { index: 1 }, BoardError::UnknownPage(1)),
+    ] {
+        assert_eq!(board.apply(&op), Err(err), "{:?}", op);
+        assert_eq!(board, before, "{:?}", op);
+    }
+}
+
+#[test]
+fn pages_keep_their_own_objects() {
+    let mut board = grouped_board();
+    apply_all(
+        &mut board,
+        &[
+            BoardOp::AddPage {
+                name: "Page 2".to_string(),
+            },
+            start(10, 0),
+            point(10, 0., 0.),
+            BoardOp::StrokeEnd { id: 10 },
+            BoardOp::RenamePage {
+                index: 0,
+                name: "Intro".to_string(),
+            },
+            BoardOp::Clear,
+        ],
+    );
+    assert_eq!(board.current(), 1);
+    assert!(board.page().is_empty());
+    assert_eq!(board.pages()[0].len(), 5);
+
+    board.apply(&BoardOp::SelectPage { index: 0 }).unwrap();
+    assert_eq!(board.page().name, "Intro");
+    assert!(board.contains(3));
+
+    let mut rebuilt = Board::default();
+    apply_all(&mut rebuilt, &board.to_ops());
+    assert_eq!(rebuilt, board);
+    let (loaded, errors) = Board::from_data(board.to_data(), board.current());
+    assert!(errors.is_empty());
+    assert_eq!(loaded, board);
+}
+
+#[test]
+fn legacy_documents_load_as_the_first_page() {
+    let white =
+        json!({ "Rgba": { "red": 1, "green": 1, "blue": 1, "alpha": 1 } });
+    let legacy = json!({
+        "strokes": [{ "id": 3, "points": [[0, 0], [10, 0]],
+            "color": white, "width": 4, "layer": 0 }],
+        "texts": [{ "text": "hi", "position": [0, 0], "size": 20,
+            "color": white, "layer": 1 }],
+        "images": [{ "source": { "path": "board.png" },
+            "position": [0, 0], "layer": 2 }],
+    });
+    let document: Document = serde_json::from_value(legacy).unwrap();
+    let [page] = &document.pages[..] else {
+        panic!("{:?}", document.pages);
+    };
+    assert_eq!(page.name, "Page 1");
+    assert_eq!(page.strokes[0].id, 3);
+    assert_eq!(page.texts[0].id, 4);
+    assert_eq!(page.images[0].id, 5);
+    assert_eq!(page.images[0].scale, 1.);
+
+    let (board, errors) = Board::from_data(document.pages, 0);
+    assert!(errors.is_empty());
+    assert_eq!(board.page().len(), 3);
+}
+
+#[test]
+fn invalid_saved_objects_are_skipped() {
+    let mut tiny = text(2, Vec2::ZERO);
+    tiny.size = -1.;
+    let (board, errors) = Board::from_data(
+        [PageData {
+            strokes: vec![stroke(1, 0)],
+            texts: vec![tiny, text(1, Vec2::ZERO)],
+            ..default()
+        }],
+        3,
+    );
+    assert_eq!(
+        errors,
+        vec![BoardError::InvalidSize(-1.), BoardError::DuplicateId(1)]
+    );
+    assert_eq!(board.current(), 0);
+    assert_eq!(board.page().len(), 1);
+}
+
+#[test]
+fn switching_pages_rebuilds_the_view() {
+    let mut board = TestBoard::new();
+    board.drag(FROM, TO, 5);
+    assert_eq!(board.strokes().len(), 1);
+
+    board.tap_chord(&[KeyCode::ControlLeft, KeyCode::ShiftLeft], KeyCode::N);
+    assert_eq!(board.model().pages().len(), 2);
+    assert!(board.strokes().is_empty());
+
+    board.drag(FROM, TO, 5);
+    assert_eq!(board.model().page().len(), 1);
+    assert_in_sync(&mut board);
+    let second = board.model().page().strokes().next().unwrap().id;
+
+    board.tap_key(KeyCode::PageUp);
+    assert_eq!(board.model().current(), 0);
+    assert_in_sync(&mut board);
+    assert!(!board.model().contains(second));
+
+    board.tap_key(KeyCode::PageDown);
+    assert_eq!(board.model().current(), 1);
+    assert_in_sync(&mut board);
+}
+
+#[test]
+fn group_ops_reach_the_model() {
+    let mut board = TestBoard::new();
+    board.drag(FROM, TO, 5);
+    board.drag(Vec2::new(400., 200.), Vec2::new(800., 200.), 5);
+    board.set_tool(ToolButton::Cursor);
+    let lines: Vec<Entity> = board
+        .app
+        .world
+        .query_filtered::<Entity, With<LineId>>()
+        .iter(&board.app.world)
+        .collect();
+    board.app.world.resource_mut::<Selected>().0 = lines;
+    board.tap_chord(&[KeyCode::ControlLeft], KeyCode::G);
+    let group = board.model().page().nested_groups()[0].id;
+    assert_eq!(board.model().page().descendants(group).len(), 3);
+
+    board.tap_key(KeyCode::Delete);
+    assert!(board.model().page().is_empty());
+    assert_in_sync(&mut board);
+}
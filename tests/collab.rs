@@ -0,0 +1,272 @@
+mod common;
+
+use std::{
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream},
+    thread,
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use common::TestBoard;
+use lines::{
+    board::StrokeData,
+    board_view::BoardOpEvent,
+    collab::{
+        read_messages, write_message, ClientMessage, Collab, CollabConfig,
+        ServerMessage,
+    },
+    collab_server::serve,
+    recording::BoardOp,
+};
+
+const FROM: Vec2 = Vec2::new(400., 500.);
+const TO: Vec2 = Vec2::new(800., 500.);
+
+fn start_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    thread::spawn(move || serve(listener));
+    address
+}
+
+fn join(board: &mut TestBoard, server: SocketAddr, name: &str) {
+    board.app.insert_resource(CollabConfig {
+        server: Some(server.to_string()),
+        name: name.to_string(),
+        color: Color::ORANGE,
+    });
+    wait_until(&mut [board], |boards| {
+        boards[0].app.world.resource::<Collab>().client.is_some()
+    });
+}
+
+fn connect(server: SocketAddr, name: &str) -> TestBoard {
+    let mut board = TestBoard::new();
+    join(&mut board, server, name);
+    board
+}
+
+/// 同时推进所有白板，直到条件满足；网络消息由其他线程收发，每帧稍等一下
+fn wait_until(
+    boards: &mut [&mut TestBoard],
+    done: impl Fn(&[&mut TestBoard]) -> bool,
+) {
+    for _ in 0..500 {
+        if done(boards) {
+            return;
+        }
+        for board in boards.iter_mut() {
+            board.update();
+        }
+        thread::sleep(Duration::from_millis(2));
+    }
+    panic!("collaboration did not settle");
+}
+
+/// 所有本地操作都被确认，并且各客户端的模型一致
+fn settle(boards: &mut [&mut TestBoard]) {
+    wait_until(boards, |boards| {
+        let first = boards[0].model().page().to_strokes();
+        boards.iter().all(|board| {
+            board.app.world.resource::<Collab>().pending() == 0
+                && board.model().page().to_strokes() == first
+        })
+    });
+    for board in boards.iter_mut() {
+        board.step(2);
+    }
+}
+
+fn world_points(strokes: &[StrokeData]) -> Vec<Vec<Vec2>> {
+    strokes
+        .iter()
+        .map(|stroke| stroke.world_points().collect())
+        .collect()
+}
+
+#[test]
+fn strokes_appear_on_other_clients() {
+    let server = start_server();
+    let mut alice = connect(server, "alice");
+    let mut bob = connect(server, "bob");
+
+    alice.drag(FROM, TO, 10);
+    settle(&mut [&mut alice, &mut bob]);
+    assert_eq!(bob.strokes().len(), 1);
+    assert_eq!(world_points(&bob.strokes()), world_points(&alice.strokes()));
+    assert_eq!(bob.strokes()[0].times, alice.strokes()[0].times);
+
+    bob.tap_key(KeyCode::Key3);
+    bob.drag(Vec2::new(600., 400.), Vec2::new(600., 600.), 10);
+    settle(&mut [&mut alice, &mut bob]);
+    assert!(alice.strokes().is_empty());
+    assert!(alice.model().page().is_empty());
+}
+
+#[test]
+fn concurrent_strokes_get_distinct_ids() {
+    let server = start_server();
+    let mut alice = connect(server, "alice");
+    let mut bob = connect(server, "bob");
+
+    alice.drag(FROM, TO, 5);
+    bob.drag(Vec2::new(400., 200.), Vec2::new(800., 200.), 5);
+    settle(&mut [&mut alice, &mut bob]);
+    assert_eq!(alice.model().page().len(), 2);
+    assert_eq!(world_points(&alice.strokes()), world_points(&bob.strokes()));
+}
+
+#[test]
+fn late_joiners_keep_their_board() {
+    let server = start_server();
+    let mut alice = connect(server, "alice");
+    alice.drag(FROM, TO, 5);
+    alice.drag(Vec2::new(400., 200.), Vec2::new(800., 200.), 5);
+    settle(&mut [&mut alice]);
+
+    let mut carol = TestBoard::new();
+    carol.drag(Vec2::new(100., 100.), Vec2::new(200., 100.), 5);
+    join(&mut carol, server, "carol");
+    settle(&mut [&mut alice, &mut carol]);
+    // 加入前画的线条换成站点内的 id，与服务器上的白板一起保留
+    assert_eq!(carol.strokes().len(), 3);
+    assert_eq!(
+        world_points(&carol.strokes()),
+        world_points(&alice.strokes())
+    );
+
+    // 之后分配的 id 不会与上传的线条冲突
+    carol.drag(Vec2::new(100., 300.), Vec2::new(200., 300.), 5);
+    settle(&mut [&mut alice, &mut carol]);
+    assert_eq!(alice.model().page().len(), 4);
+    assert_eq!(alice.strokes().len(), 4);
+}
+
+#[test]
+fn the_first_client_uploads_its_board() {
+    let server = start_server();
+    let mut alice = TestBoard::new();
+    alice.drag(FROM, TO, 5);
+    join(&mut alice, server, "alice");
+    let mut bob = connect(server, "bob");
+    settle(&mut [&mut alice, &mut bob]);
+    assert_eq!(bob.strokes().len(), 1);
+}
+
+#[test]
+fn moving_an_erased_stroke_converges() {
+    let server = start_server();
+    let mut alice = connect(server, "alice");
+    let mut bob = connect(server, "bob");
+    alice.drag(FROM, TO, 5);
+    settle(&mut [&mut alice, &mut bob]);
+
+    // 两边在收到对方操作之前修改同一条线
+    alice.tap_key(KeyCode::Key1);
+    alice.drag(FROM.lerp(TO, 0.5), FROM.lerp(TO, 0.5) + Vec2::Y * 50., 5);
+    bob.tap_key(KeyCode::Key3);
+    bob.drag(Vec2::new(600., 400.), Vec2::new(600., 600.), 5);
+    settle(&mut [&mut alice, &mut bob]);
+
+    assert!(alice.model().page().is_empty());
+    assert!(alice.strokes().is_empty());
+    assert!(bob.strokes().is_empty());
+}
+
+#[test]
+fn concurrent_reorders_converge_in_server_order() {
+    let server = start_server();
+    let mut alice = connect(server, "alice");
+    let mut bob = connect(server, "bob");
+    alice.drag(FROM, TO, 5);
+    settle(&mut [&mut alice, &mut bob]);
+    let id = alice.model().page().strokes().next().unwrap().id;
+
+    alice
+        .app
+        .world
+        .send_event(BoardOpEvent(BoardOp::Reorder { id, layer: 3 }));
+    bob.app
+        .world
+        .send_event(BoardOpEvent(BoardOp::Reorder { id, layer: 7 }));
+    alice.update();
+    bob.update();
+    settle(&mut [&mut alice, &mut bob]);
+
+    let layer = alice.model().page().get(id).unwrap().layer;
+    assert!(layer == 3 || layer == 7, "layer {}", layer);
+    assert_eq!(bob.model().page().get(id).unwrap().layer, layer);
+}
+
+#[test]
+fn remote_cursors_show_names() {
+    let server = start_server();
+    let mut alice = connect(server, "alice");
+    let mut bob = connect(server, "bob");
+    alice.move_cursor(FROM);
+    alice.update();
+
+    let expected = TestBoard::world_point(FROM);
+    wait_until(&mut [&mut alice, &mut bob], |boards| {
+        boards[1]
+            .app
+            .world
+            .resource::<Collab>()
+            .peers
+            .values()
+            .any(|peer| {
+                peer.name == "alice"
+                    && peer
+                        .cursor
+                        .is_some_and(|cursor| cursor.distance(expected) < 1.)
+            })
+    });
+    bob.step(2);
+    let labels: Vec<String> = bob
+        .app
+        .world
+        .query::<&Text>()
+        .iter(&bob.app.world)
+        .flat_map(|text| text.sections.iter().map(|s| s.value.clone()))
+        .collect();
+    assert!(labels.contains(&"alice".to_string()), "{:?}", labels);
+}
+
+/// 直接用协议加入，返回连接与服务器分配的站点
+fn hello(server: SocketAddr, name: &str) -> (TcpStream, u64) {
+    let stream = TcpStream::connect(server).unwrap();
+    write_message(
+        &stream,
+        &ClientMessage::Hello {
+            name: name.to_string(),
+            color: Color::ORANGE,
+        },
+    )
+    .unwrap();
+    let mut site = None;
+    read_messages(stream.try_clone().unwrap(), |message: ServerMessage| {
+        if let ServerMessage::Welcome { site: s, .. } = message {
+            site = Some(s);
+        }
+        site.is_none()
+    });
+    (stream, site.unwrap())
+}
+
+#[test]
+fn sites_of_departed_clients_are_reused() {
+    let server = start_server();
+    let (alice, alice_site) = hello(server, "alice");
+    let (_bob, bob_site) = hello(server, "bob");
+    assert_eq!((alice_site, bob_site), (0, 1));
+
+    alice.shutdown(Shutdown::Both).unwrap();
+    for _ in 0..500 {
+        let (_carol, site) = hello(server, "carol");
+        if site == 0 {
+            return;
+        }
+        thread::sleep(Duration::from_millis(2));
+    }
+    panic!("the free site was not reused");
+}
@@ -0,0 +1,284 @@
+// 无窗口、无 GPU 的测试白板：不创建 winit 窗口与渲染器，手动推进帧并注入键盘、鼠标、指针事件
+// 使用去掉 winit、GPU 后端与音频的 DefaultPlugins 而不是 MinimalPlugins：
+// 白板需要资产、窗口、UI、文字与渲染插件注册的类型与资源
+// 不读取 LINES_* 环境变量，协作、控制接口与网页直播默认关闭，由测试自己打开
+#![allow(dead_code)]
+
+use std::{
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use bevy::{
+    app::Plugins,
+    audio::AudioPlugin,
+    ecs::system::SystemState,
+    input::{keyboard::KeyboardInput, mouse::MouseButtonInput, ButtonState},
+    log::LogPlugin,
+    prelude::*,
+    render::{settings::WgpuSettings, RenderPlugin},
+    time::TimeUpdateStrategy,
+    window::{PrimaryWindow, WindowResolution},
+    winit::WinitPlugin,
+};
+use lines::{
+    api::ApiConfig,
+    autosave::AutosaveConfig,
+    board::Board,
+    board_view::BoardModel,
+    collab::CollabConfig,
+    document::{collect_strokes, DocumentPath, LineQuery, StrokeData},
+    keybindings::KeyBindingsPath,
+    plugins::LinesPlugins,
+    recording::{BoardOp, Recording},
+    states::{CursorState, ToolButton},
+    viewer::ViewerConfig,
+};
+
+/// 每帧推进的时间
+pub const FRAME: Duration = Duration::from_millis(16);
+
+pub const WINDOW_SIZE: Vec2 = Vec2::new(1280., 720.);
+
+pub struct TestBoard {
+    pub app: App,
+    pub dir: PathBuf,
+}
+
+impl TestBoard {
+    pub fn new() -> Self {
+        Self::with_plugins(LinesPlugins::default())
+    }
+
+    /// plugins 中可以在白板之前加入宿主程序自己的插件
+    pub fn with_plugins<M>(plugins: impl Plugins<M>) -> Self {
+        Self::build(plugins, None)
+    }
+
+    /// 与 other 使用同一个恢复目录，模拟同时打开的另一个实例
+    pub fn sharing_recovery(other: &TestBoard) -> Self {
+        Self::build(LinesPlugins::default(), Some(other.recovery_dir()))
+    }
+
+    pub fn recovery_dir(&self) -> PathBuf {
+        self.app.world.resource::<AutosaveConfig>().dir.clone()
+    }
+
+    fn build<M>(plugins: impl Plugins<M>, recovery: Option<PathBuf>) -> Self {
+        // 每个测试使用独立的目录，自动保存等文件不会互相影响
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "lines-test-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let mut app = App::new();
+        app.add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: Some(Window {
+                        resolution: WindowResolution::new(
+                            WINDOW_SIZE.x,
+                            WINDOW_SIZE.y,
+                        ),
+                        ..default()
+                    }),
+                    ..default()
+                })
+                .set(RenderPlugin {
+                    render_creation: WgpuSettings {
+                        backends: None,
+                        ..default()
+                    }
+                    .into(),
+                })
+                .disable::<WinitPlugin>()
+                .disable::<LogPlugin>()
+                .disable::<AudioPlugin>(),
+        )
+        .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME))
+        .insert_resource(AutosaveConfig {
+            dir: recovery.unwrap_or_else(|| dir.join("recovery")),
+            ..default()
+        })
+        .insert_resource(DocumentPath(dir.join("board.lines.json")))
+        .insert_resource(KeyBindingsPath(dir.join("keybindings.json")))
+        .insert_resource(CollabConfig {
+            server: None,
+            name: "test".to_string(),
+            color: Color::ORANGE,
+        })
+        .insert_resource(ApiConfig { address: None })
+        .insert_resource(ViewerConfig { address: None })
+        .add_plugins(plugins);
+        app.finish();
+        app.cleanup();
+
+        let mut board = TestBoard { app, dir };
+        board.step(2);
+        board
+    }
+
+    pub fn update(&mut self) {
+        self.app.update();
+    }
+
+    pub fn step(&mut self, frames: usize) {
+        for _ in 0..frames {
+            self.app.update();
+        }
+    }
+
+    pub fn window(&mut self) -> Entity {
+        self.app
+            .world
+            .query_filtered::<Entity, With<PrimaryWindow>>()
+            .single(&self.app.world)
+    }
+
+    pub fn press_key(&mut self, key: KeyCode) {
+        self.send_key(key, ButtonState::Pressed);
+    }
+
+    pub fn release_key(&mut self, key: KeyCode) {
+        self.send_key(key, ButtonState::Released);
+    }
+
+    /// 按下并松开，各推进一帧
+    pub fn tap_key(&mut self, key: KeyCode) {
+        self.press_key(key);
+        self.update();
+        self.release_key(key);
+        self.update();
+    }
+
+    /// 按住修饰键再按下按键，例如 (ControlLeft, Z)
+    pub fn tap_chord(&mut self, modifiers: &[KeyCode], key: KeyCode) {
+        for modifier in modifiers {
+            self.press_key(*modifier);
+        }
+        self.tap_key(key);
+        for modifier in modifiers {
+            self.release_key(*modifier);
+        }
+        self.update();
+    }
+
+    fn send_key(&mut self, key: KeyCode, state: ButtonState) {
+        let window = self.window();
+        self.app.world.send_event(KeyboardInput {
+            scan_code: 0,
+            key_code: Some(key),
+            state,
+            window,
+        });
+    }
+
+    /// 把指针移到窗口坐标（左上角为原点）
+    pub fn move_cursor(&mut self, position: Vec2) {
+        let window = self.window();
+        self.app
+            .world
+            .get_mut::<Window>(window)
+            .unwrap()
+            .set_cursor_position(Some(position));
+        self.app.world.send_event(CursorMoved { window, position });
+    }
+
+    pub fn press_mouse(&mut self) {
+        self.send_mouse(ButtonState::Pressed);
+    }
+
+    pub fn release_mouse(&mut self) {
+        self.send_mouse(ButtonState::Released);
+    }
+
+    fn send_mouse(&mut self, state: ButtonState) {
+        let window = self.window();
+        self.app.world.send_event(MouseButtonInput {
+            button: MouseButton::Left,
+            state,
+            window,
+        });
+    }
+
+    pub fn click(&mut self, position: Vec2) {
+        self.move_cursor(position);
+        self.update();
+        self.press_mouse();
+        self.step(2);
+        self.release_mouse();
+        self.step(2);
+    }
+
+    /// 按下左键从 from 拖到 to，中间经过 steps 个点，每个点推进一帧
+    pub fn drag(&mut self, from: Vec2, to: Vec2, steps: usize) {
+        self.move_cursor(from);
+        self.update();
+        self.press_mouse();
+        self.step(2);
+        for i in 1..=steps {
+            self.move_cursor(from.lerp(to, i as f32 / steps as f32));
+            self.update();
+        }
+        self.release_mouse();
+        self.step(2);
+    }
+
+    /// 窗口坐标对应的世界坐标，相机未移动时窗口中心为原点
+    pub fn world_point(position: Vec2) -> Vec2 {
+        let centered = position - WINDOW_SIZE / 2.;
+        Vec2::new(centered.x, -centered.y)
+    }
+
+    pub fn tool(&self) -> ToolButton {
+        self.app.world.resource::<State<ToolButton>>().get().clone()
+    }
+
+    pub fn cursor_state(&self) -> CursorState {
+        self.app
+            .world
+            .resource::<State<CursorState>>()
+            .get()
+            .clone()
+    }
+
+    pub fn set_tool(&mut self, tool: ToolButton) {
+        self.app
+            .world
+            .resource_mut::<NextState<ToolButton>>()
+            .set(tool);
+        self.update();
+    }
+
+    /// 白板上的线条，按 id 排序
+    pub fn strokes(&mut self) -> Vec<StrokeData> {
+        let mut state: SystemState<LineQuery> =
+            SystemState::new(&mut self.app.world);
+        collect_strokes(&state.get(&self.app.world))
+    }
+
+    /// 与白板实体同步的模型
+    pub fn model(&self) -> &Board {
+        &self.app.world.resource::<BoardModel>().0
+    }
+
+    /// 录制下来的操作
+    pub fn ops(&self) -> Vec<BoardOp> {
+        self.app
+            .world
+            .resource::<Recording>()
+            .ops
+            .iter()
+            .map(|timed| timed.op.clone())
+            .collect()
+    }
+}
+
+impl Drop for TestBoard {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
@@ -0,0 +1,575 @@
+use std::collections::BTreeSet;
+
+use bevy::prelude::*;
+use lines::{
+    board::{stroke_ops, Board, BoardOp, BrushKind, Page, Rgba, StrokeData},
+    board_view::BoardCrdt,
+    crdt::{keep_ranges, CrdtBoard, CrdtOp, OpKind},
+    document::Document,
+};
+use proptest::prelude::*;
+
+const SITES: usize = 3;
+
+#[derive(Debug, Clone)]
+enum Action {
+    Add {
+        width: f32,
+        layer: i8,
+    },
+    Append {
+        target: usize,
+        points: Vec<(f32, f32)>,
+        timed: bool,
+    },
+    Erase {
+        target: usize,
+    },
+    Split {
+        target: usize,
+        remove: Vec<usize>,
+    },
+    Move {
+        target: usize,
+        delta: (f32, f32),
+    },
+    Restyle {
+        target: usize,
+        color: u8,
+        width: Option<f32>,
+        brush: bool,
+    },
+    Reorder {
+        target: usize,
+        layer: i8,
+    },
+    /// 把 from 的全部操作发给执行的站点
+    Sync {
+        from: usize,
+    },
+}
+
+fn action() -> impl Strategy<Value = Action> {
+    prop_oneof![
+        3 => (0.5f32..10., 0i8..10)
+            .prop_map(|(width, layer)| Action::Add { width, layer }),
+        4 => (
+            any::<usize>(),
+            prop::collection::vec((-100f32..100., -100f32..100.), 1..6),
+            any::<bool>(),
+        )
+            .prop_map(|(target, points, timed)| Action::Append {
+                target,
+                points,
+                timed,
+            }),
+        1 => any::<usize>().prop_map(|target| Action::Erase { target }),
+        1 => (any::<usize>(), prop::collection::vec(0usize..12, 0..4))
+            .prop_map(|(target, remove)| Action::Split { target, remove }),
+        2 => (any::<usize>(), (-50f32..50., -50f32..50.))
+            .prop_map(|(target, delta)| Action::Move { target, delta }),
+        1 => (
+            any::<usize>(),
+            any::<u8>(),
+            prop::option::of(0.5f32..10.),
+            any::<bool>(),
+        )
+            .prop_map(|(target, color, width, brush)| Action::Restyle {
+                target,
+                color,
+                width,
+                brush,
+            }),
+        1 => (any::<usize>(), 0i8..10)
+            .prop_map(|(target, layer)| Action::Reorder { target, layer }),
+        2 => (0..SITES).prop_map(|from| Action::Sync { from }),
+    ]
+}
+
+/// 每一步由某个站点执行，站点之间不时互相同步
+fn script() -> impl Strategy<Value = Vec<(usize, Action)>> {
+    prop::collection::vec((0..SITES, action()), 0..40)
+}
+
+fn target(replica: &CrdtBoard, index: usize) -> Option<u64> {
+    let ids: Vec<u64> = replica.strokes().map(|stroke| stroke.id).collect();
+    (!ids.is_empty()).then(|| ids[index % ids.len()])
+}
+
+fn perform(replica: &mut CrdtBoard, action: &Action) -> Option<CrdtOp> {
+    let kind = match action.clone() {
+        Action::Add { width, layer } => OpKind::AddStroke {
+            color: Rgba::WHITE,
+            width,
+            brush: BrushKind::Chalk,
+            layer,
+        },
+        Action::Append {
+            target: index,
+            points,
+            timed,
+        } => OpKind::AppendPoints {
+            stroke: target(replica, index)?,
+            times: if timed {
+                (0..points.len()).map(|i| i as f32).collect()
+            } else {
+                vec![]
+            },
+            points: points.into_iter().map(Vec2::from).collect(),
+            pressures: vec![],
+        },
+        Action::Erase { target: index } => OpKind::Erase {
+            stroke: target(replica, index)?,
+        },
+        Action::Split {
+            target: index,
+            remove,
+        } => {
+            let stroke = target(replica, index)?;
+            let len = replica.get(stroke).unwrap().points.len();
+            OpKind::Split {
+                stroke,
+                keep: keep_ranges(len, &remove.into_iter().collect()),
+            }
+        }
+        Action::Move {
+            target: index,
+            delta,
+        } => OpKind::Move {
+            stroke: target(replica, index)?,
+            delta: Vec2::from(delta),
+        },
+        Action::Restyle {
+            target: index,
+            color,
+            width,
+            brush,
+        } => OpKind::Restyle {
+            stroke: target(replica, index)?,
+            color: Some(Rgba::new(color as f32 / 255., 0., 0., 1.)),
+            width,
+            brush: brush.then_some(BrushKind::Marker),
+        },
+        Action::Reorder {
+            target: index,
+            layer,
+        } => OpKind::Reorder {
+            stroke: target(replica, index)?,
+            layer,
+        },
+        Action::Sync { .. } => return None,
+    };
+    Some(replica.edit(kind).unwrap())
+}
+
+/// 执行脚本，返回各站点的副本
+fn run(script: &[(usize, Action)]) -> Vec<CrdtBoard> {
+    let mut replicas: Vec<CrdtBoard> =
+        (0..SITES).map(|site| CrdtBoard::new(site as u16)).collect();
+    for (site, action) in script {
+        if let Action::Sync { from } = action {
+            let from = replicas[*from].clone();
+            replicas[*site].merge(&from);
+        } else {
+            perform(&mut replicas[*site], action);
+        }
+    }
+    replicas
+}
+
+fn merged(replicas: &[CrdtBoard]) -> CrdtBoard {
+    let mut all = CrdtBoard::new(100);
+    for replica in replicas {
+        all.merge(replica);
+    }
+    all
+}
+
+fn state(replica: &CrdtBoard) -> Vec<StrokeData> {
+    replica.strokes().cloned().collect()
+}
+
+proptest! {
+    #[test]
+    fn replicas_converge_after_exchanging_ops(script in script()) {
+        let mut replicas = run(&script);
+        let all = merged(&replicas);
+        for replica in replicas.iter_mut() {
+            replica.merge(&all);
+            prop_assert_eq!(state(replica), state(&all));
+        }
+        // 合并后的白板总是合法的模型
+        prop_assert!(all.to_board().is_ok());
+    }
+
+    #[test]
+    fn delivery_order_does_not_matter(
+        script in script(),
+        keys in prop::collection::vec(any::<u32>(), 1..64),
+    ) {
+        let all = merged(&run(&script));
+        let mut ops: Vec<(u32, CrdtOp)> = all
+            .ops()
+            .enumerate()
+            .map(|(i, op)| (keys[i % keys.len()], op))
+            .collect();
+        ops.sort_by_key(|(key, _)| *key);
+
+        let mut shuffled = CrdtBoard::new(101);
+        for (_, op) in ops.iter().cloned() {
+            prop_assert!(shuffled.receive(op).unwrap());
+        }
+        prop_assert_eq!(state(&shuffled), state(&all));
+
+        let mut reversed = CrdtBoard::new(102);
+        for (_, op) in ops.into_iter().rev() {
+            reversed.receive(op).unwrap();
+        }
+        prop_assert_eq!(state(&reversed), state(&all));
+    }
+
+    #[test]
+    fn merge_is_commutative_and_idempotent(script in script()) {
+        let replicas = run(&script);
+        let (mut ab, mut ba) = (replicas[0].clone(), replicas[1].clone());
+        ab.merge(&replicas[1]);
+        ba.merge(&replicas[0]);
+        prop_assert_eq!(state(&ab), state(&ba));
+
+        let before = state(&ab);
+        ab.merge(&ba);
+        prop_assert_eq!(state(&ab), before);
+    }
+
+    #[test]
+    fn ops_since_version_fills_the_gap(script in script()) {
+        let replicas = run(&script);
+        let all = merged(&replicas);
+        let mut behind = replicas[0].clone();
+        let missing = all.ops_since(&behind.version());
+        for op in missing {
+            behind.receive(op).unwrap();
+        }
+        prop_assert_eq!(state(&behind), state(&all));
+    }
+}
+
+fn add(replica: &mut CrdtBoard, points: &[Vec2]) -> u64 {
+    let op = replica
+        .edit(OpKind::AddStroke {
+            color: Rgba::WHITE,
+            width: 4.,
+            brush: BrushKind::Chalk,
+            layer: 0,
+        })
+        .unwrap();
+    let id = op.id.stroke_id(0);
+    replica
+        .edit(OpKind::AppendPoints {
+            stroke: id,
+            points: points.to_vec(),
+            times: vec![],
+            pressures: vec![],
+        })
+        .unwrap();
+    id
+}
+
+fn line(len: usize) -> Vec<Vec2> {
+    (0..len).map(|i| Vec2::new(i as f32 * 10., 0.)).collect()
+}
+
+#[test]
+fn concurrent_strokes_get_distinct_ids() {
+    let mut alice = CrdtBoard::new(1);
+    let mut bob = CrdtBoard::new(2);
+    let a = add(&mut alice, &line(2));
+    let b = add(&mut bob, &line(3));
+    assert_ne!(a, b);
+
+    alice.merge(&bob);
+    bob.merge(&alice);
+    assert_eq!(state(&alice), state(&bob));
+    assert_eq!(alice.strokes().count(), 2);
+}
+
+#[test]
+fn erase_wins_over_concurrent_edits() {
+    let mut alice = CrdtBoard::new(1);
+    let id = add(&mut alice, &line(2));
+    let mut bob = alice.clone();
+    bob.edit(OpKind::Erase { stroke: id }).unwrap();
+    // 在收到擦除之前继续编辑
+    for _ in 0..3 {
+        alice
+            .edit(OpKind::Move {
+                stroke: id,
+                delta: Vec2::ONE,
+            })
+            .unwrap();
+    }
+
+    alice.merge(&bob);
+    bob.merge(&alice);
+    assert!(alice.get(id).is_none());
+    assert_eq!(state(&alice), state(&bob));
+    assert!(alice.edit(OpKind::Erase { stroke: id }).is_err());
+}
+
+#[test]
+fn concurrent_moves_add_up() {
+    let mut alice = CrdtBoard::new(1);
+    let id = add(&mut alice, &line(2));
+    let mut bob = alice.clone();
+    let delta = |x, y| OpKind::Move {
+        stroke: id,
+        delta: Vec2::new(x, y),
+    };
+    alice.edit(delta(10., 0.)).unwrap();
+    bob.edit(delta(0., 5.)).unwrap();
+
+    alice.merge(&bob);
+    bob.merge(&alice);
+    assert_eq!(alice.get(id).unwrap().offset, Vec2::new(10., 5.));
+    assert_eq!(state(&alice), state(&bob));
+}
+
+#[test]
+fn the_later_restyle_wins() {
+    let mut alice = CrdtBoard::new(1);
+    let id = add(&mut alice, &line(2));
+    let mut bob = alice.clone();
+    let restyle = |color| OpKind::Restyle {
+        stroke: id,
+        color: Some(color),
+        width: None,
+        brush: None,
+    };
+    alice.edit(restyle(Rgba::new(1., 0., 0., 1.))).unwrap();
+    // 看到 alice 的修改之后再改，OpId 更大
+    bob.merge(&alice);
+    bob.edit(restyle(Rgba::new(0., 0., 1., 1.))).unwrap();
+    bob.edit(OpKind::Reorder {
+        stroke: id,
+        layer: 5,
+    })
+    .unwrap();
+
+    alice.merge(&bob);
+    let stroke = alice.get(id).unwrap();
+    assert_eq!(stroke.color, Rgba::new(0., 0., 1., 1.));
+    assert_eq!(stroke.layer, 5);
+    assert_eq!(stroke.width, 4.);
+}
+
+#[test]
+fn split_keeps_the_remaining_pieces() {
+    let mut alice = CrdtBoard::new(1);
+    let id = add(&mut alice, &line(6));
+    let remove: BTreeSet<usize> = [2, 3].into_iter().collect();
+    let keep = keep_ranges(6, &remove);
+    assert_eq!(keep, vec![0..2, 4..6]);
+
+    let op = alice.edit(OpKind::Split { stroke: id, keep }).unwrap();
+    assert!(alice.get(id).is_none());
+    let first = alice.get(op.id.stroke_id(1)).unwrap();
+    assert_eq!(first.points, line(2));
+    let second = alice.get(op.id.stroke_id(2)).unwrap();
+    assert_eq!(second.points, line(6)[4..].to_vec());
+}
+
+#[test]
+fn ops_round_trip_through_json() {
+    let mut alice = CrdtBoard::new(1);
+    add(&mut alice, &line(3));
+    let json = serde_json::to_string(&alice.ops().collect::<Vec<_>>()).unwrap();
+    let ops: Vec<CrdtOp> = serde_json::from_str(&json).unwrap();
+
+    let mut copy = CrdtBoard::new(2);
+    for op in ops {
+        copy.receive(op).unwrap();
+    }
+    assert_eq!(state(&copy), state(&alice));
+}
+
+#[test]
+fn imported_pages_keep_their_strokes() {
+    let mut page = Page::new("Page 1");
+    page.insert(StrokeData {
+        id: 7,
+        points: line(3),
+        color: Rgba::WHITE,
+        width: 4.,
+        layer: 2,
+        offset: Vec2::new(5., 5.),
+        times: vec![0., 0.1, 0.2],
+        ..default()
+    })
+    .unwrap();
+
+    let crdt = CrdtBoard::from_page(3, &page);
+    let stroke = crdt.strokes().next().unwrap();
+    assert_eq!(stroke.layer, 2);
+    assert_eq!(stroke.times, vec![0., 0.1, 0.2]);
+    assert_eq!(
+        stroke.world_points().collect::<Vec<_>>(),
+        page.get(7).unwrap().world_points().collect::<Vec<_>>()
+    );
+    assert_eq!(crdt.to_board().unwrap().page().len(), 1);
+}
+
+#[test]
+fn invalid_ops_are_rejected() {
+    let mut alice = CrdtBoard::new(1);
+    assert!(alice
+        .edit(OpKind::AddStroke {
+            color: Rgba::WHITE,
+            width: f32::NAN,
+            brush: BrushKind::Chalk,
+            layer: 0,
+        })
+        .is_err());
+    assert!(alice
+        .edit(OpKind::Move {
+            stroke: 1,
+            delta: Vec2::ONE,
+        })
+        .is_err());
+    assert_eq!(alice.ops().count(), 0);
+}
+
+#[test]
+fn board_ops_replay_on_a_remote_replica() {
+    let stroke = StrokeData {
+        id: 42,
+        points: line(3),
+        color: Rgba::WHITE,
+        width: 4.,
+        layer: 1,
+        pressures: vec![0.5, 0.6, 0.7],
+        ..default()
+    };
+    let mut ops = stroke_ops(&stroke);
+    ops.extend([
+        BoardOp::Move {
+            id: 42,
+            delta: Vec2::new(10., 0.),
+        },
+        BoardOp::Scale {
+            id: 42,
+            center: Vec2::ZERO,
+            factor: 2.,
+        },
+        BoardOp::Reorder { id: 42, layer: 5 },
+    ]);
+    let mut expected = Board::default();
+    let mut alice = CrdtBoard::new(1);
+    let mut bob = CrdtBoard::new(2);
+    for op in &ops {
+        expected.apply(op).unwrap();
+        for crdt_op in alice.edit_board_op(op).unwrap() {
+            bob.receive(crdt_op).unwrap();
+        }
+    }
+
+    let expected = expected.page().get(42).unwrap();
+    let stroke = bob.get(alice.resolve(42)).unwrap();
+    assert_eq!(stroke.layer, 5);
+    assert_eq!(stroke.pressures, expected.pressures);
+    assert_eq!(
+        stroke.world_points().collect::<Vec<_>>(),
+        expected.world_points().collect::<Vec<_>>()
+    );
+
+    for crdt_op in alice.edit_board_op(&BoardOp::Clear).unwrap() {
+        bob.receive(crdt_op).unwrap();
+    }
+    assert_eq!(bob.strokes().count(), 0);
+}
+
+#[test]
+fn saved_boards_merge() {
+    let dir = std::env::temp_dir()
+        .join(format!("lines-test-merge-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let save = |name: &str, site: u16, offset: Vec2| {
+        let board = Board::from_strokes([StrokeData {
+            // 两个白板各自从 0 开始编号
+            id: 0,
+            points: line(3),
+            color: Rgba::WHITE,
+            width: 4.,
+            offset,
+            ..default()
+        }])
+        .unwrap();
+        let document = Document::from_board(
+            &board,
+            &mut BoardCrdt::new(site),
+            &default(),
+            &default(),
+        );
+        let path = dir.join(name);
+        document.save(&path).unwrap();
+        Document::load(&path).unwrap()
+    };
+    let alice = save("alice.lines.json", 1, Vec2::ZERO);
+    let bob = save("bob.lines.json", 2, Vec2::new(0., 50.));
+
+    let merged = alice.merge(bob).unwrap();
+    let strokes = &merged.pages[0].strokes;
+    let mut offsets: Vec<Vec2> =
+        strokes.iter().map(|stroke| stroke.offset).collect();
+    offsets.sort_by(|a, b| a.y.total_cmp(&b.y));
+    assert_eq!(offsets, vec![Vec2::ZERO, Vec2::new(0., 50.)]);
+    assert_ne!(strokes[0].id, strokes[1].id);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn copies_of_one_save_merge_their_edits() {
+    let stroke = |id: u64, y: f32| StrokeData {
+        id,
+        points: line(3),
+        color: Rgba::WHITE,
+        width: 4.,
+        offset: Vec2::new(0., y),
+        ..default()
+    };
+    let board = Board::from_strokes([stroke(0, 0.), stroke(1, 50.)]).unwrap();
+    let saved = Document::from_board(
+        &board,
+        &mut BoardCrdt::new(1),
+        &default(),
+        &default(),
+    );
+    // 两个副本打开同一次保存的文档，各自编辑后保存
+    let edit = |site: u16, op: BoardOp| {
+        let (mut board, errors) = Board::from_data(saved.pages.clone(), 0);
+        assert!(errors.is_empty());
+        board.apply(&op).unwrap();
+        let mut crdt = BoardCrdt::load(site, &saved.crdt);
+        Document::from_board(&board, &mut crdt, &default(), &default())
+    };
+    let erased = edit(2, BoardOp::Erase { id: 0 });
+    let moved = edit(
+        3,
+        BoardOp::Move {
+            id: 1,
+            delta: Vec2::new(10., 0.),
+        },
+    );
+
+    for merged in [
+        erased.clone().merge(moved.clone()).unwrap(),
+        moved.merge(erased).unwrap(),
+    ] {
+        // 擦除的线条不会从另一个副本回来
+        let strokes = &merged.pages[0].strokes;
+        assert_eq!(strokes.len(), 1);
+        assert_eq!(strokes[0].offset, Vec2::new(10., 50.));
+        // 合并的结果可以继续合并
+        let again = merged.clone().merge(merged).unwrap();
+        assert_eq!(again.pages[0].strokes.len(), 1);
+    }
+}
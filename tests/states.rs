@@ -0,0 +1,115 @@
+mod common;
+
+use bevy::prelude::*;
+use common::TestBoard;
+use lines::{
+    double_click::DoubleClickEvent,
+    states::{CursorState, RunMode, ToolButton},
+};
+
+#[test]
+fn starts_with_pen_while_hovering() {
+    let board = TestBoard::new();
+    assert_eq!(board.tool(), ToolButton::Pen);
+    assert_eq!(board.cursor_state(), CursorState::Hovering);
+}
+
+#[test]
+fn mouse_press_and_release_switch_cursor_state() {
+    let mut board = TestBoard::new();
+    board.move_cursor(Vec2::new(400., 500.));
+    board.update();
+    board.press_mouse();
+    board.step(2);
+    assert_eq!(board.cursor_state(), CursorState::Draging);
+    board.release_mouse();
+    board.step(2);
+    assert_eq!(board.cursor_state(), CursorState::Hovering);
+}
+
+#[test]
+fn holding_space_moves_camera_and_restores_previous_tool() {
+    let mut board = TestBoard::new();
+    board.tap_key(KeyCode::Key3);
+    assert_eq!(board.tool(), ToolButton::Eraser);
+
+    board.press_key(KeyCode::Space);
+    board.step(2);
+    assert_eq!(board.tool(), ToolButton::MoveCamera);
+
+    board.release_key(KeyCode::Space);
+    board.step(2);
+    assert_eq!(board.tool(), ToolButton::Eraser);
+}
+
+#[test]
+fn dragging_with_space_pans_the_camera() {
+    let mut board = TestBoard::new();
+    board.press_key(KeyCode::Space);
+    board.step(2);
+    board.drag(Vec2::new(600., 400.), Vec2::new(500., 400.), 5);
+    board.release_key(KeyCode::Space);
+    board.step(2);
+
+    let camera = board
+        .app
+        .world
+        .query_filtered::<&Transform, With<Camera>>()
+        .single(&board.app.world)
+        .translation;
+    assert!((camera.x - 100.).abs() < 1., "camera at {:?}", camera);
+}
+
+#[test]
+fn escape_toggles_debug_mode() {
+    let mut board = TestBoard::new();
+    board.tap_key(KeyCode::Escape);
+    board.update();
+    assert_eq!(
+        *board.app.world.resource::<State<RunMode>>().get(),
+        RunMode::Debug
+    );
+    board.tap_key(KeyCode::Escape);
+    board.update();
+    assert_eq!(
+        *board.app.world.resource::<State<RunMode>>().get(),
+        RunMode::Normal
+    );
+}
+
+#[derive(Resource, Default)]
+struct DoubleClicks(usize);
+
+fn count_double_clicks(
+    mut events: EventReader<DoubleClickEvent>,
+    mut count: ResMut<DoubleClicks>,
+) {
+    count.0 += events.read().count();
+}
+
+fn board_counting_double_clicks() -> TestBoard {
+    let mut board = TestBoard::new();
+    board
+        .app
+        .init_resource::<DoubleClicks>()
+        .add_systems(Last, count_double_clicks);
+    board.set_tool(ToolButton::Cursor);
+    board
+}
+
+#[test]
+fn two_quick_clicks_are_a_double_click() {
+    let mut board = board_counting_double_clicks();
+    board.click(Vec2::new(400., 500.));
+    board.click(Vec2::new(400., 500.));
+    assert_eq!(board.app.world.resource::<DoubleClicks>().0, 1);
+}
+
+#[test]
+fn slow_clicks_are_not_a_double_click() {
+    let mut board = board_counting_double_clicks();
+    board.click(Vec2::new(400., 500.));
+    board.step(30);
+    board.click(Vec2::new(400., 500.));
+    assert_eq!(board.app.world.resource::<DoubleClicks>().0, 0);
+}
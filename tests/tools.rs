@@ -0,0 +1,310 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::ShapePlugin;
+use common::TestBoard;
+use lines::{
+    api::ApiPlugin,
+    autosave::AutosavePlugin,
+    background::BoardBackground,
+    brush::{BrushKind, CurrentBrush, HighlighterMaterial},
+    collab::CollabPlugin,
+    keybindings::{actions, ActionRegistry, KeyBindings},
+    palette::Palette,
+    plugins::LinesPlugins,
+    recording::BoardOp,
+    replay::{ReplayPlugin, ReplayState},
+    states::ToolButton,
+    tools::ToolRegistry,
+    viewer::ViewerPlugin,
+};
+
+const FROM: Vec2 = Vec2::new(400., 500.);
+const TO: Vec2 = Vec2::new(800., 500.);
+
+fn board_with_stroke() -> TestBoard {
+    let mut board = TestBoard::new();
+    board.drag(FROM, TO, 10);
+    assert_eq!(board.strokes().len(), 1);
+    board
+}
+
+#[test]
+fn number_keys_switch_tools() {
+    let mut board = TestBoard::new();
+    for (key, tool) in [
+        (KeyCode::Key1, ToolButton::Cursor),
+        (KeyCode::Key3, ToolButton::Eraser),
+        (KeyCode::Key4, ToolButton::Eyedropper),
+        (KeyCode::Key5, ToolButton::Laser),
+        (KeyCode::Key2, ToolButton::Pen),
+    ] {
+        board.tap_key(key);
+        board.update();
+        assert_eq!(board.tool(), tool);
+    }
+}
+
+#[test]
+fn built_in_tools_come_from_the_registry() {
+    let board = TestBoard::new();
+    let world = &board.app.world;
+    let tools: Vec<ToolButton> = world
+        .resource::<ToolRegistry>()
+        .0
+        .iter()
+        .map(|info| info.tool.clone())
+        .collect();
+    assert_eq!(
+        tools,
+        vec![
+            ToolButton::Cursor,
+            ToolButton::Pen,
+            ToolButton::Eraser,
+            ToolButton::Eyedropper,
+            ToolButton::Laser,
+        ]
+    );
+    for info in world.resource::<ToolRegistry>().0.iter() {
+        assert!(world
+            .resource::<ActionRegistry>()
+            .get(&info.action)
+            .is_some());
+        assert!(!world
+            .resource::<KeyBindings>()
+            .chords(&info.action)
+            .is_empty());
+    }
+}
+
+#[test]
+fn default_bindings_have_no_conflicts() {
+    let board = TestBoard::new();
+    let bindings = board.app.world.resource::<KeyBindings>();
+    assert_eq!(bindings.conflicts(), vec![]);
+    for action in [actions::RECOVERY_RESTORE, actions::RECOVERY_DISMISS] {
+        assert!(!bindings.chords(action).is_empty(), "{}", action);
+    }
+}
+
+#[test]
+fn host_app_can_add_the_shape_plugin_first() {
+    let mut board =
+        TestBoard::with_plugins((ShapePlugin, LinesPlugins::default()));
+    board.drag(FROM, TO, 10);
+    assert_eq!(board.strokes().len(), 1);
+}
+
+#[test]
+fn autosave_and_network_can_be_turned_off() {
+    let mut board = TestBoard::with_plugins(
+        LinesPlugins::default()
+            .with_autosave(false)
+            .with_network(false),
+    );
+    assert!(!board.app.is_plugin_added::<AutosavePlugin>());
+    for added in [
+        board.app.is_plugin_added::<CollabPlugin>(),
+        board.app.is_plugin_added::<ApiPlugin>(),
+        board.app.is_plugin_added::<ViewerPlugin>(),
+    ] {
+        assert!(!added);
+    }
+    board.drag(FROM, TO, 10);
+    assert_eq!(board.strokes().len(), 1);
+}
+
+#[test]
+fn host_app_can_disable_single_plugins() {
+    let mut board = TestBoard::with_plugins(
+        LinesPlugins::default().build().disable::<ReplayPlugin>(),
+    );
+    board.drag(FROM, TO, 10);
+    board.tap_key(KeyCode::F5);
+    assert_eq!(
+        board.app.world.resource::<State<ReplayState>>().get(),
+        &ReplayState::Off
+    );
+    assert_eq!(board.strokes().len(), 1);
+}
+
+#[test]
+fn highlighter_blends_by_background() {
+    let mut board = TestBoard::new();
+    board
+        .app
+        .insert_resource(CurrentBrush(BrushKind::Highlighter));
+    board.drag(FROM, TO, 10);
+    let multiply = |board: &mut TestBoard| -> Vec<bool> {
+        let world = &mut board.app.world;
+        let handles: Vec<Handle<HighlighterMaterial>> = world
+            .query::<&Handle<HighlighterMaterial>>()
+            .iter(world)
+            .cloned()
+            .collect();
+        let materials = world.resource::<Assets<HighlighterMaterial>>();
+        handles
+            .iter()
+            .map(|handle| materials.get(handle).unwrap().multiply)
+            .collect()
+    };
+    // 默认的黑色背景上相乘会完全看不见
+    assert!(board.app.world.resource::<BoardBackground>().is_dark());
+    assert_eq!(multiply(&mut board), vec![false]);
+
+    board.app.world.resource_mut::<BoardBackground>().color = Color::WHITE;
+    board.update();
+    assert_eq!(multiply(&mut board), vec![true]);
+}
+
+#[test]
+fn pen_drag_draws_a_stroke() {
+    let mut board = board_with_stroke();
+    let stroke = &board.strokes()[0];
+    let first = TestBoard::world_point(FROM.lerp(TO, 0.1));
+    let last = TestBoard::world_point(TO);
+    assert!(stroke.points.len() >= 9, "{} points", stroke.points.len());
+    assert!(stroke.points[0].distance(first) < 1.);
+    assert!(stroke.points.last().unwrap().distance(last) < 1.);
+
+    let ops = board.ops();
+    assert!(matches!(ops.first(), Some(BoardOp::StrokeStart { .. })));
+    assert!(matches!(ops.last(), Some(BoardOp::StrokeEnd { .. })));
+}
+
+#[test]
+fn pen_uses_the_palette_color() {
+    let mut board = TestBoard::new();
+    board.app.world.resource_mut::<Palette>().set(Color::RED);
+    board.update();
+    board.drag(FROM, TO, 5);
+    assert_eq!(Color::from(board.strokes()[0].color), Color::RED);
+}
+
+#[test]
+fn pen_click_without_moving_leaves_no_stroke() {
+    let mut board = TestBoard::new();
+    board.click(FROM);
+    assert!(board.strokes().is_empty());
+}
+
+#[test]
+fn eraser_removes_touched_strokes() {
+    let mut board = board_with_stroke();
+    board.tap_key(KeyCode::Key3);
+    board.drag(Vec2::new(600., 400.), Vec2::new(600., 600.), 10);
+    assert!(board.strokes().is_empty());
+    assert!(matches!(board.ops().last(), Some(BoardOp::Erase { .. })));
+}
+
+#[test]
+fn eraser_keeps_strokes_it_does_not_touch() {
+    let mut board = board_with_stroke();
+    board.tap_key(KeyCode::Key3);
+    board.drag(Vec2::new(600., 100.), Vec2::new(700., 150.), 5);
+    assert_eq!(board.strokes().len(), 1);
+}
+
+#[test]
+fn eraser_hits_moved_strokes_where_they_are_drawn() {
+    let mut board = board_with_stroke();
+    board.tap_key(KeyCode::Key1);
+    let middle = FROM.lerp(TO, 0.5);
+    board.drag(middle, middle - Vec2::Y * 200., 5);
+    board.tap_key(KeyCode::Key3);
+
+    board.drag(Vec2::new(600., 450.), Vec2::new(600., 550.), 10);
+    assert_eq!(board.strokes().len(), 1);
+
+    board.drag(Vec2::new(600., 250.), Vec2::new(600., 350.), 10);
+    assert!(board.strokes().is_empty());
+}
+
+#[test]
+fn undo_removes_the_last_stroke() {
+    let mut board = board_with_stroke();
+    board.drag(Vec2::new(400., 600.), Vec2::new(800., 600.), 5);
+    assert_eq!(board.strokes().len(), 2);
+    board.tap_chord(&[KeyCode::ControlLeft], KeyCode::Z);
+    let strokes = board.strokes();
+    assert_eq!(strokes.len(), 1);
+    let first_y = TestBoard::world_point(FROM).y;
+    assert!((strokes[0].points[0].y - first_y).abs() < 1.);
+}
+
+#[test]
+fn clear_removes_every_stroke() {
+    let mut board = board_with_stroke();
+    board.drag(Vec2::new(400., 600.), Vec2::new(800., 600.), 5);
+    board.tap_key(KeyCode::C);
+    assert!(board.strokes().is_empty());
+    assert_eq!(board.ops().last(), Some(&BoardOp::Clear));
+}
+
+#[test]
+fn cursor_drags_the_selected_stroke() {
+    let mut board = board_with_stroke();
+    board.tap_key(KeyCode::Key1);
+    board.drag(FROM.lerp(TO, 0.5), FROM.lerp(TO, 0.5) + Vec2::Y * 50., 5);
+    let stroke = &board.strokes()[0];
+    assert!(
+        (stroke.offset - Vec2::new(0., -50.)).length() < 1.,
+        "offset {:?}",
+        stroke.offset
+    );
+    assert!(board
+        .ops()
+        .iter()
+        .any(|op| matches!(op, BoardOp::Move { .. })));
+}
+
+#[test]
+fn eyedropper_picks_the_stroke_color() {
+    let mut board = TestBoard::new();
+    board.app.world.resource_mut::<Palette>().set(Color::RED);
+    board.update();
+    board.drag(FROM, TO, 5);
+    board.app.world.resource_mut::<Palette>().set(Color::BLUE);
+    board.tap_key(KeyCode::Key4);
+    board.click(FROM.lerp(TO, 0.5));
+    assert_eq!(board.app.world.resource::<Palette>().current, Color::RED);
+}
+
+#[test]
+fn laser_does_not_draw() {
+    let mut board = TestBoard::new();
+    board.tap_key(KeyCode::Key5);
+    board.drag(FROM, TO, 10);
+    assert!(board.strokes().is_empty());
+    assert!(board.ops().is_empty());
+}
+
+#[test]
+fn send_to_back_and_bring_to_front() {
+    let mut board = board_with_stroke();
+    let below = Vec2::Y * 100.;
+    board.drag(FROM + below, TO + below, 10);
+    let layer = |board: &mut TestBoard, index: usize| {
+        let mut strokes = board.strokes();
+        strokes.sort_by_key(|stroke| stroke.id);
+        strokes[index].layer
+    };
+    assert!(layer(&mut board, 0) < layer(&mut board, 1));
+
+    // 第一条线在第 0 层，第二条线要放到它下面
+    board.tap_key(KeyCode::Key1);
+    board.click(FROM.lerp(TO, 0.5) + below);
+    board.tap_chord(&[KeyCode::ControlLeft], KeyCode::BracketLeft);
+    assert!(layer(&mut board, 1) < layer(&mut board, 0));
+
+    board.click(FROM.lerp(TO, 0.5));
+    board.tap_chord(&[KeyCode::ControlLeft], KeyCode::BracketLeft);
+    assert!(layer(&mut board, 0) < layer(&mut board, 1));
+    board.tap_chord(&[KeyCode::ControlLeft], KeyCode::BracketRight);
+    assert!(layer(&mut board, 1) < layer(&mut board, 0));
+
+    // 之后画的线条在移到最上层的对象之上
+    board.tap_key(KeyCode::Key2);
+    board.drag(FROM - below, TO - below, 10);
+    assert!(layer(&mut board, 0) < layer(&mut board, 2));
+}
@@ -0,0 +1,123 @@
+mod common;
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use common::TestBoard;
+use lines::viewer::{Viewer, ViewerConfig};
+use serde_json::Value;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn listen(board: &mut TestBoard) -> String {
+    board.app.insert_resource(ViewerConfig {
+        address: Some("127.0.0.1:0".to_string()),
+    });
+    board.update();
+    board
+        .app
+        .world
+        .resource::<Viewer>()
+        .address
+        .clone()
+        .expect("the viewer is not listening")
+}
+
+fn request(address: &str, path: &str) -> TcpStream {
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(stream, "GET {} HTTP/1.1\r\nHost: lines\r\n\r\n", path).unwrap();
+    stream
+}
+
+/// 返回状态行与正文
+fn get(address: &str, path: &str) -> (String, String) {
+    let mut response = String::new();
+    request(address, path)
+        .read_to_string(&mut response)
+        .unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    (head.lines().next().unwrap().to_string(), body.to_string())
+}
+
+/// 读取线程把收到的事件（名字与数据）放进通道
+fn events(address: &str) -> Receiver<(String, Value)> {
+    let stream = request(address, "/events");
+    let (sender, events) = mpsc::channel();
+    thread::spawn(move || {
+        let mut name = String::new();
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else {
+                return;
+            };
+            if let Some(event) = line.strip_prefix("event: ") {
+                name = event.to_string();
+            } else if let Some(data) = line.strip_prefix("data: ") {
+                let data = serde_json::from_str(data).unwrap();
+                if sender.send((name.clone(), data)).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+    events
+}
+
+fn next_event(events: &Receiver<(String, Value)>) -> (String, Value) {
+    events.recv_timeout(TIMEOUT).expect("no viewer event")
+}
+
+#[test]
+fn serves_the_page_and_the_board() {
+    let mut board = TestBoard::new();
+    let address = listen(&mut board);
+    board.drag(Vec2::new(400., 500.), Vec2::new(800., 500.), 5);
+
+    let (status, page) = get(&address, "/");
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert!(page.contains("EventSource"));
+
+    let (status, svg) = get(&address, "/board.svg");
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert!(svg.starts_with("<svg"), "{}", svg);
+    let id = board.strokes()[0].id;
+    assert!(svg.contains(&format!(r#"id="s{}""#, id)), "{}", svg);
+
+    let (status, _) = get(&address, "/missing");
+    assert_eq!(status, "HTTP/1.1 404 Not Found");
+}
+
+#[test]
+fn events_follow_the_board() {
+    let mut board = TestBoard::new();
+    let address = listen(&mut board);
+    let events = events(&address);
+    let (name, snapshot) = next_event(&events);
+    assert_eq!(name, "snapshot");
+    assert!(snapshot["svg"].as_str().unwrap().starts_with("<svg"));
+
+    board.drag(Vec2::new(400., 500.), Vec2::new(800., 500.), 5);
+    let id = board.strokes()[0].id;
+    let (name, stroke) = next_event(&events);
+    assert_eq!(name, "stroke");
+    assert_eq!(stroke["id"], id);
+    let (name, point) = next_event(&events);
+    assert_eq!(name, "point");
+    assert_eq!(point["id"], id);
+
+    board.tap_chord(&[KeyCode::ControlLeft], KeyCode::Z);
+    assert!(board.strokes().is_empty());
+    loop {
+        let (name, data) = next_event(&events);
+        if name == "remove" {
+            assert_eq!(data["id"], id);
+            break;
+        }
+        assert_eq!(name, "point");
+    }
+}